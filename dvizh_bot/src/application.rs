@@ -1,6 +1,9 @@
 use crate::args;
 use crate::bot_config;
 use crate::db::repository::DvizhRepository;
+use crate::ics::ImportedEvent;
+use crate::tg::csv_import::CsvImport;
+use crate::tg::pending_input::PendingInput;
 use crate::tg::tg_utils::parse_memes;
 use crate::LanguageCache;
 use anyhow::Result;
@@ -11,10 +14,65 @@ use env_logger;
 use log::{debug, error};
 use reqwest::Client;
 use rust_bert::pipelines::translation::{Language, TranslationModel, TranslationModelBuilder};
+use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::{Mutex, RwLock};
 
+/// How long previews, drafts and searches wait for their buttons.
+const PENDING_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Values waiting for a button, forgotten after `PENDING_TTL`.
+#[derive(Debug)]
+pub struct ExpiringMap<V> {
+    entries: HashMap<(i64, i64), (Instant, V)>,
+}
+
+impl<V> Default for ExpiringMap<V> {
+    fn default() -> Self {
+        ExpiringMap {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+impl<V> ExpiringMap<V> {
+    /// Stores `value` and drops the entries that have expired.
+    pub fn insert(&mut self, key: (i64, i64), value: V) {
+        let now = Instant::now();
+        self.entries
+            .retain(|_, (created, _)| now.duration_since(*created) < PENDING_TTL);
+        self.entries.insert(key, (now, value));
+    }
+
+    pub fn get(&self, key: &(i64, i64)) -> Option<&V> {
+        self.entries
+            .get(key)
+            .filter(|(created, _)| created.elapsed() < PENDING_TTL)
+            .map(|(_, value)| value)
+    }
+
+    pub fn remove(&mut self, key: &(i64, i64)) -> Option<V> {
+        self.entries
+            .remove(key)
+            .filter(|(created, _)| created.elapsed() < PENDING_TTL)
+            .map(|(_, value)| value)
+    }
+}
+
+/// Events of previewed `.ics` files waiting for confirmation, by chat and user.
+pub type PendingImports = ExpiringMap<Vec<ImportedEvent>>;
+
+/// Rows of previewed CSV files waiting for confirmation, by chat and user.
+pub type PendingCsvImports = ExpiringMap<CsvImport>;
+
+/// Texts of event searches by chat and message of their result list.
+pub type EventSearches = ExpiringMap<String>;
+
+/// Arguments of `/addevent` waiting for a date picked from the calendar, by chat and user.
+pub type EventDrafts = ExpiringMap<Vec<String>>;
+
 #[derive(Derivative, Clone)]
 #[derivative(Debug)]
 pub struct Application {
@@ -23,6 +81,11 @@ pub struct Application {
     pub dvizh_repo: Arc<Mutex<DvizhRepository>>,
     pub language_cache: Arc<RwLock<LanguageCache>>,
     pub meme_cache: Arc<RwLock<Vec<String>>>,
+    pub pending_inputs: Arc<RwLock<HashMap<(i64, i64), PendingInput>>>,
+    pub pending_imports: Arc<RwLock<PendingImports>>,
+    pub pending_csv_imports: Arc<RwLock<PendingCsvImports>>,
+    pub event_searches: Arc<RwLock<EventSearches>>,
+    pub event_drafts: Arc<RwLock<EventDrafts>>,
     #[derivative(Debug = "ignore")]
     pub translation_model: Arc<Mutex<TranslationModel>>,
 }
@@ -32,6 +95,11 @@ impl Application {
         let client = Client::new();
         let language_cache = Arc::new(RwLock::new(LanguageCache::new()));
         let meme_cache = Arc::new(RwLock::new(Vec::new()));
+        let pending_inputs = Arc::new(RwLock::new(HashMap::new()));
+        let pending_imports = Arc::new(RwLock::new(ExpiringMap::default()));
+        let pending_csv_imports = Arc::new(RwLock::new(ExpiringMap::default()));
+        let event_searches = Arc::new(RwLock::new(ExpiringMap::default()));
+        let event_drafts = Arc::new(RwLock::new(ExpiringMap::default()));
         let conf = bot_config::load_config();
         let args = args::Arguments::parse();
         let dvizh_repo = Arc::new(Mutex::new(DvizhRepository::new(&conf.db_path)?));
@@ -68,6 +136,11 @@ impl Application {
             dvizh_repo,
             language_cache,
             meme_cache,
+            pending_inputs,
+            pending_imports,
+            pending_csv_imports,
+            event_searches,
+            event_drafts,
             translation_model,
         })
     }
@@ -0,0 +1,46 @@
+use chrono::{Datelike, NaiveDate};
+
+/// Birthdates are stored as `DD.MM.YYYY`, or as `DD.MM` when the year is not shared.
+pub static BIRTHDAY_FORMAT: &str = "%d.%m";
+
+/// Returns the `DD.MM` birthdays celebrated on `date`.
+/// In non-leap years February 29 birthdays are celebrated on the day given by
+/// the `leap_birthday` chat setting, `feb28` or `mar1`.
+pub fn celebrated_birthdays(date: NaiveDate, leap_birthday: &str) -> Vec<String> {
+    let mut birthdays = vec![date.format(BIRTHDAY_FORMAT).to_string()];
+    let is_leap_year = NaiveDate::from_ymd_opt(date.year(), 2, 29).is_some();
+    let leap_day = match (date.month(), date.day()) {
+        (2, 28) => "feb28",
+        (3, 1) => "mar1",
+        _ => "",
+    };
+    if !is_leap_year && leap_day == leap_birthday {
+        birthdays.push("29.02".to_string());
+    }
+    birthdays
+}
+
+/// Returns the first day from `today` on which the birthday is celebrated,
+/// February 29 birthdays are moved as in `celebrated_birthdays`.
+pub fn next_birthday(birthdate: &str, today: NaiveDate, leap_birthday: &str) -> Option<NaiveDate> {
+    let mut parts = birthdate.split('.');
+    let day = parts.next()?.parse::<u32>().ok()?;
+    let month = parts.next()?.parse::<u32>().ok()?;
+
+    [today.year(), today.year() + 1]
+        .into_iter()
+        .filter_map(|year| {
+            NaiveDate::from_ymd_opt(year, month, day).or_else(|| match (month, day) {
+                (2, 29) if leap_birthday == "feb28" => NaiveDate::from_ymd_opt(year, 2, 28),
+                (2, 29) => NaiveDate::from_ymd_opt(year, 3, 1),
+                _ => None,
+            })
+        })
+        .find(|date| *date >= today)
+}
+
+/// Returns the age turned in the year of `date`, `None` when the year of birth is unknown.
+pub fn age_on(birthdate: &str, date: NaiveDate) -> Option<i32> {
+    let year = birthdate.split('.').nth(2)?.parse::<i32>().ok()?;
+    Some(date.year() - year)
+}
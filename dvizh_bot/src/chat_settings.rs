@@ -0,0 +1,140 @@
+use crate::time_utils::{format_digest_schedule, format_reminder_offsets};
+use crate::validations::{validate_digest_schedule, validate_reminder_offsets, ValidationError};
+
+/// Admins can be told about a birthday at most this many days ahead.
+pub static MAX_BIRTHDAY_REMINDER_DAYS: i64 = 30;
+
+/// Per-chat options that admins change with `/settings [name] [value]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChatSetting {
+    /// Show the names of attendees on event cards
+    RsvpNames,
+    /// How long before an event reminders are sent, e.g. `7d,1d,2h`
+    Reminders,
+    /// Where reminders go: `group`, `dm` to attendees or `both`
+    ReminderMode,
+    /// Forum topic receiving event proposals, `off` sends them to admins privately
+    AdminTopic,
+    /// When the weekly digest is posted, e.g. `mon 10:00` in chat-local time, or `off`
+    Digest,
+    /// Pin the weekly digest, unpinning the previous one
+    DigestPin,
+    /// When February 29 birthdays are celebrated in other years, `feb28` or `mar1`
+    LeapBirthday,
+    /// Post the birthdays of the month on its first day
+    MonthlyBirthdays,
+    /// How many days before a birthday admins are told privately, or `off`
+    BirthdayReminder,
+}
+
+pub static CHAT_SETTINGS: &[ChatSetting] = &[
+    ChatSetting::RsvpNames,
+    ChatSetting::Reminders,
+    ChatSetting::ReminderMode,
+    ChatSetting::AdminTopic,
+    ChatSetting::Digest,
+    ChatSetting::DigestPin,
+    ChatSetting::LeapBirthday,
+    ChatSetting::MonthlyBirthdays,
+    ChatSetting::BirthdayReminder,
+];
+
+pub fn chat_setting_from_str(t: &str) -> Option<ChatSetting> {
+    match t.to_lowercase().as_str() {
+        "rsvp_names" => Some(ChatSetting::RsvpNames),
+        "reminders" => Some(ChatSetting::Reminders),
+        "reminder_mode" => Some(ChatSetting::ReminderMode),
+        "admin_topic" => Some(ChatSetting::AdminTopic),
+        "digest" => Some(ChatSetting::Digest),
+        "digest_pin" => Some(ChatSetting::DigestPin),
+        "leap_birthday" => Some(ChatSetting::LeapBirthday),
+        "monthly_birthdays" => Some(ChatSetting::MonthlyBirthdays),
+        "birthday_reminder" => Some(ChatSetting::BirthdayReminder),
+        _ => None,
+    }
+}
+
+pub fn chat_setting_to_str(setting: &ChatSetting) -> &'static str {
+    match setting {
+        ChatSetting::RsvpNames => "rsvp_names",
+        ChatSetting::Reminders => "reminders",
+        ChatSetting::ReminderMode => "reminder_mode",
+        ChatSetting::AdminTopic => "admin_topic",
+        ChatSetting::Digest => "digest",
+        ChatSetting::DigestPin => "digest_pin",
+        ChatSetting::LeapBirthday => "leap_birthday",
+        ChatSetting::MonthlyBirthdays => "monthly_birthdays",
+        ChatSetting::BirthdayReminder => "birthday_reminder",
+    }
+}
+
+pub fn chat_setting_default(setting: &ChatSetting) -> &'static str {
+    match setting {
+        ChatSetting::RsvpNames => "on",
+        ChatSetting::Reminders => "1d,2h",
+        ChatSetting::ReminderMode => "group",
+        ChatSetting::AdminTopic => "off",
+        ChatSetting::Digest => "off",
+        ChatSetting::DigestPin => "off",
+        ChatSetting::LeapBirthday => "mar1",
+        ChatSetting::MonthlyBirthdays => "off",
+        ChatSetting::BirthdayReminder => "off",
+    }
+}
+
+/// Describes the values accepted by `setting`, shown by `/settings`.
+pub fn chat_setting_values(setting: &ChatSetting) -> &'static str {
+    match setting {
+        ChatSetting::RsvpNames => "on | off",
+        ChatSetting::Reminders => "7d,1d,2h,30m | off",
+        ChatSetting::ReminderMode => "group | dm | both",
+        ChatSetting::AdminTopic => "here | [topic id] | off",
+        ChatSetting::Digest => "[weekday] [HH:MM] | off",
+        ChatSetting::DigestPin => "on | off",
+        ChatSetting::LeapBirthday => "feb28 | mar1",
+        ChatSetting::MonthlyBirthdays => "on | off",
+        ChatSetting::BirthdayReminder => "[days 1-30] | off",
+    }
+}
+
+/// Validates and normalizes `value` for `setting`.
+pub fn validate_chat_setting(
+    setting: &ChatSetting,
+    value: &str,
+) -> Result<String, ValidationError> {
+    let value = value.trim().to_lowercase();
+    match setting {
+        ChatSetting::RsvpNames => validate_choice(&value, &["on", "off"]),
+        ChatSetting::Reminders => {
+            validate_reminder_offsets(&value).map(|offsets| format_reminder_offsets(&offsets))
+        }
+        ChatSetting::ReminderMode => validate_choice(&value, &["group", "dm", "both"]),
+        ChatSetting::AdminTopic => match value.parse::<i64>() {
+            Ok(topic_id) if topic_id > 0 => Ok(topic_id.to_string()),
+            _ => validate_choice(&value, &["off"]),
+        },
+        ChatSetting::Digest => match validate_digest_schedule(&value) {
+            Ok((weekday, time)) => Ok(format_digest_schedule(weekday, time)),
+            Err(error) => validate_choice(&value, &["off"]).map_err(|_| error),
+        },
+        ChatSetting::DigestPin => validate_choice(&value, &["on", "off"]),
+        ChatSetting::LeapBirthday => validate_choice(&value, &["feb28", "mar1"]),
+        ChatSetting::MonthlyBirthdays => validate_choice(&value, &["on", "off"]),
+        ChatSetting::BirthdayReminder => match value.parse::<i64>() {
+            Ok(days) if (1..=MAX_BIRTHDAY_REMINDER_DAYS).contains(&days) => Ok(days.to_string()),
+            _ => validate_choice(&value, &["off"]),
+        },
+    }
+}
+
+pub fn is_enabled(value: &str) -> bool {
+    value == "on"
+}
+
+fn validate_choice(value: &str, choices: &[&str]) -> Result<String, ValidationError> {
+    if choices.contains(&value) {
+        Ok(value.to_string())
+    } else {
+        Err(ValidationError::InvalidSettingValue)
+    }
+}
@@ -0,0 +1,160 @@
+use crate::db::db_objects::{Event, User};
+use crate::recurrence::{expand_event, format_recurrence_rule};
+use crate::time_utils::{format_event_datetime, timezone_or_default, today_in};
+use chrono::Duration;
+
+/// Columns of a birthday CSV, a row names the user by `user_id` or `username`.
+static BIRTHDAY_COLUMNS: [&str; 4] = ["user_id", "username", "first_name", "birthdate"];
+/// Columns of an event CSV, the same as the arguments of `/addevent`.
+static EVENT_COLUMNS: [&str; 5] = ["title", "date", "location", "description", "recurrence"];
+/// How far ahead the next occurrence of an exported recurring event is looked for.
+static NEXT_OCCURRENCE_DAYS: i64 = 366;
+
+/// What the rows of a CSV file describe, told apart by the header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsvLayout {
+    Birthdays,
+    Events,
+}
+
+/// Returns the layout of a CSV file with the lowercase `header`.
+pub fn csv_layout(header: &[String]) -> Option<CsvLayout> {
+    let has = |column: &str| header.iter().any(|name| name == column);
+    if has("birthdate") && (has("user_id") || has("username")) {
+        Some(CsvLayout::Birthdays)
+    } else if has("title") && has("date") {
+        Some(CsvLayout::Events)
+    } else {
+        None
+    }
+}
+
+/// Splits CSV `content` into records with the line they start on.
+/// Fields may be quoted with `"` and contain separators, quotes written as `""` and line breaks.
+/// Spreadsheets saving with `;` as the separator are read as well.
+pub fn parse_csv(content: &str) -> Vec<(usize, Vec<String>)> {
+    let content = content.trim_start_matches('\u{feff}');
+    let first_line = content.lines().next().unwrap_or_default();
+    let separator = if first_line.contains(';') && !first_line.contains(',') {
+        ';'
+    } else {
+        ','
+    };
+
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => in_quotes = !in_quotes,
+            '\n' if in_quotes => {
+                line += 1;
+                field.push(c);
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                fields.push(std::mem::take(&mut field));
+                // Blank lines are skipped
+                if fields.iter().any(|field| !field.trim().is_empty()) {
+                    records.push((record_line, std::mem::take(&mut fields)));
+                }
+                fields.clear();
+                line += 1;
+                record_line = line;
+            }
+            c if c == separator && !in_quotes => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    if fields.iter().any(|field| !field.trim().is_empty()) {
+        records.push((record_line, fields));
+    }
+    records
+}
+
+/// Joins `fields` into a CSV line, quoting the fields that need it.
+fn csv_line(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', ';', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Builds a birthday CSV that `/import` reads back.
+/// Users whose Telegram id is not known yet are exported by username only,
+/// and users hiding their age without the year of birth.
+pub fn birthdays_to_csv(users: &[User]) -> String {
+    let mut lines = vec![csv_line(&BIRTHDAY_COLUMNS.map(str::to_string))];
+    for user in users {
+        lines.push(csv_line(&[
+            if user.id > 0 {
+                user.id.to_string()
+            } else {
+                String::new()
+            },
+            user.username.clone().unwrap_or_default(),
+            user.first_name.clone().unwrap_or_default(),
+            user.birthdate
+                .as_deref()
+                .map(|birthdate| {
+                    if user.show_age {
+                        birthdate
+                    } else {
+                        birthdate.get(..5).unwrap_or(birthdate)
+                    }
+                })
+                .unwrap_or_default()
+                .to_string(),
+        ]));
+    }
+    lines.join("\r\n") + "\r\n"
+}
+
+/// Builds an event CSV that `/import` reads back.
+/// Recurring events start from their next occurrence, as past dates are not imported.
+pub fn events_to_csv(events: &[Event]) -> String {
+    let mut lines = vec![csv_line(&EVENT_COLUMNS.map(str::to_string))];
+    for event in events {
+        let tz = timezone_or_default(&event.timezone);
+        let start = match event.recurrence {
+            Some(_) => {
+                let today = today_in(&tz);
+                let next = expand_event(event, today, today + Duration::days(NEXT_OCCURRENCE_DAYS));
+                next.first()
+                    .map_or(event.start, |occurrence| occurrence.start)
+            }
+            None => event.start,
+        };
+        let end = event.end.map(|end| end + (start - event.start));
+        let recurrence = event
+            .recurrence
+            .map(|recurrence| {
+                format_recurrence_rule(&recurrence, event.recurrence_until, &event.skipped_dates)
+            })
+            .unwrap_or_default();
+        lines.push(csv_line(&[
+            event.title.clone(),
+            format!("{} {}", format_event_datetime(start, end, &tz), tz.name()),
+            event.location.clone(),
+            event.description.clone(),
+            recurrence,
+        ]));
+    }
+    lines.join("\r\n") + "\r\n"
+}
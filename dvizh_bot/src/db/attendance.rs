@@ -0,0 +1,208 @@
+use crate::db::db_objects::{rsvp_status_from_str, rsvp_status_to_str, Attendance, RsvpStatus};
+use crate::db::repository::DvizhRepository;
+use anyhow::Result;
+use chrono::Utc;
+use log::debug;
+use rusqlite::{params, OptionalExtension, Transaction};
+
+impl DvizhRepository {
+    /// Stores the RSVP of a user while keeping at most `capacity` users going.
+    /// Going to a full event puts the user on the waitlist, places freed are given
+    /// to the waitlist in order. Choosing going again keeps a place on the waitlist.
+    /// Returns the stored status and the users promoted from the waitlist.
+    pub fn set_attendance(
+        &self,
+        attendance: &Attendance,
+        capacity: Option<i64>,
+    ) -> Result<(RsvpStatus, Vec<Attendance>)> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+
+        let current = tx
+            .query_row(
+                "SELECT status FROM EventAttendance
+                WHERE event_id = ?1 AND occurrence = ?2 AND user_id = ?3",
+                params![
+                    attendance.event_id,
+                    attendance.occurrence,
+                    attendance.user_id
+                ],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?
+            .and_then(|status| rsvp_status_from_str(&status));
+
+        let status = match (attendance.status, current) {
+            (RsvpStatus::Going, Some(RsvpStatus::Waitlisted)) => RsvpStatus::Waitlisted,
+            (RsvpStatus::Going, Some(RsvpStatus::Going)) => RsvpStatus::Going,
+            (RsvpStatus::Going, _) => {
+                let going = count_going_tx(&tx, attendance.event_id, &attendance.occurrence)?;
+                match capacity {
+                    Some(capacity) if going >= capacity => RsvpStatus::Waitlisted,
+                    _ => RsvpStatus::Going,
+                }
+            }
+            (status, _) => status,
+        };
+
+        // An unchanged status keeps its place in the order
+        if current != Some(status) {
+            tx.execute(
+                "INSERT INTO EventAttendance (event_id, occurrence, user_id, username, first_name, status, updated_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                ON CONFLICT(event_id, occurrence, user_id) DO UPDATE SET
+                    username = excluded.username,
+                    first_name = excluded.first_name,
+                    status = excluded.status,
+                    updated_at = excluded.updated_at",
+                params![
+                    attendance.event_id,
+                    attendance.occurrence,
+                    attendance.user_id,
+                    attendance.username,
+                    attendance.first_name,
+                    rsvp_status_to_str(&status),
+                    Utc::now()
+                ],
+            )?;
+        }
+
+        let promoted =
+            promote_waitlisted_tx(&tx, attendance.event_id, &attendance.occurrence, capacity)?;
+        tx.commit()?;
+
+        debug!("db set attendance {attendance:#?} as {status:?}, promoted {promoted:#?}");
+
+        Ok((status, promoted))
+    }
+
+    /// Gives places freed by a changed `capacity` to the waitlists of all occurrences of an event.
+    /// Returns the users promoted from the waitlists.
+    pub fn promote_waitlisted(
+        &self,
+        event_id: i64,
+        capacity: Option<i64>,
+    ) -> Result<Vec<Attendance>> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+
+        let occurrences = {
+            let mut stmt = tx.prepare(
+                "SELECT DISTINCT occurrence FROM EventAttendance
+                WHERE event_id = ?1 AND status = ?2",
+            )?;
+            let rows = stmt.query_map(
+                params![event_id, rsvp_status_to_str(&RsvpStatus::Waitlisted)],
+                |row| row.get::<_, String>(0),
+            )?;
+            rows.collect::<rusqlite::Result<Vec<String>>>()?
+        };
+
+        let mut promoted = Vec::new();
+        for occurrence in occurrences {
+            promoted.extend(promote_waitlisted_tx(&tx, event_id, &occurrence, capacity)?);
+        }
+        tx.commit()?;
+
+        debug!("db promoted from the waitlist of event {event_id}: {promoted:#?}");
+
+        Ok(promoted)
+    }
+
+    /// Returns the RSVPs to an event occurrence in the order they were last changed.
+    pub fn get_attendance(&self, event_id: i64, occurrence: &str) -> Result<Vec<Attendance>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT event_id, occurrence, user_id, username, first_name, status
+            FROM EventAttendance
+            WHERE event_id = ?1 AND occurrence = ?2
+            ORDER BY updated_at",
+        )?;
+        let attendance = stmt
+            .query_map(params![event_id, occurrence], |row| {
+                Ok(Attendance::new(
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                    row.get(4)?,
+                    rsvp_status_from_str(&row.get::<_, String>(5)?).unwrap_or(RsvpStatus::Maybe),
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<Attendance>>>()?;
+
+        debug!("db get attendance of event {event_id} {occurrence}: {attendance:#?}");
+
+        Ok(attendance)
+    }
+}
+
+fn count_going_tx(tx: &Transaction, event_id: i64, occurrence: &str) -> Result<i64> {
+    Ok(tx.query_row(
+        "SELECT COUNT(*) FROM EventAttendance
+        WHERE event_id = ?1 AND occurrence = ?2 AND status = ?3",
+        params![event_id, occurrence, rsvp_status_to_str(&RsvpStatus::Going)],
+        |row| row.get(0),
+    )?)
+}
+
+/// Moves the longest waiting users of an occurrence to going while places are free.
+fn promote_waitlisted_tx(
+    tx: &Transaction,
+    event_id: i64,
+    occurrence: &str,
+    capacity: Option<i64>,
+) -> Result<Vec<Attendance>> {
+    let free = match capacity {
+        Some(capacity) => capacity - count_going_tx(tx, event_id, occurrence)?,
+        None => i64::MAX,
+    };
+    if free <= 0 {
+        return Ok(Vec::new());
+    }
+
+    let promoted = {
+        let mut stmt = tx.prepare(
+            "SELECT event_id, occurrence, user_id, username, first_name
+            FROM EventAttendance
+            WHERE event_id = ?1 AND occurrence = ?2 AND status = ?3
+            ORDER BY updated_at
+            LIMIT ?4",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                event_id,
+                occurrence,
+                rsvp_status_to_str(&RsvpStatus::Waitlisted),
+                free
+            ],
+            |row| {
+                Ok(Attendance::new(
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                    row.get(4)?,
+                    RsvpStatus::Going,
+                ))
+            },
+        )?;
+        rows.collect::<rusqlite::Result<Vec<Attendance>>>()?
+    };
+
+    for attendance in &promoted {
+        tx.execute(
+            "UPDATE EventAttendance SET status = ?1, updated_at = ?2
+            WHERE event_id = ?3 AND occurrence = ?4 AND user_id = ?5",
+            params![
+                rsvp_status_to_str(&RsvpStatus::Going),
+                Utc::now(),
+                attendance.event_id,
+                attendance.occurrence,
+                attendance.user_id
+            ],
+        )?;
+    }
+
+    Ok(promoted)
+}
@@ -1,5 +1,6 @@
 use crate::db::db_objects::Chat;
 use crate::db::repository::DvizhRepository;
+use crate::time_utils::DEFAULT_TIMEZONE;
 use anyhow::Result;
 use log::debug;
 use rusqlite::params;
@@ -8,12 +9,13 @@ impl DvizhRepository {
     pub fn add_chat(&self, chat: Chat) -> Result<()> {
         let conn = self.pool.get()?;
         conn.execute(
-            "INSERT INTO Chat (id, title, language_code)
-            VALUES (?1, ?2, ?3)
+            "INSERT INTO Chat (id, title, language_code, timezone)
+            VALUES (?1, ?2, ?3, ?4)
             ON CONFLICT(id) DO UPDATE SET
                 title = CASE WHEN Chat.title IS NOT NULL THEN excluded.title ELSE Chat.title END,
-                language_code = Chat.language_code",
-            params![chat.id, chat.title, chat.language_code],
+                language_code = Chat.language_code,
+                timezone = Chat.timezone",
+            params![chat.id, chat.title, chat.language_code, chat.timezone],
         )?;
 
         debug!("db added new chat {chat:#?}");
@@ -30,6 +32,15 @@ impl DvizhRepository {
         Ok(())
     }
 
+    pub fn update_chat_timezone(&self, chat_id: i64, timezone: &str) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE Chat SET timezone = ?1 WHERE id = ?2",
+            params![timezone, chat_id],
+        )?;
+        Ok(())
+    }
+
     pub fn get_all_chat_ids(&self) -> Result<Vec<i64>> {
         let conn = self.pool.get()?;
         let mut stmt = conn.prepare("SELECT id FROM Chat")?;
@@ -55,4 +66,28 @@ impl DvizhRepository {
 
         Ok(code)
     }
+
+    pub fn get_chat_title(&self, group_id: i64) -> Result<String> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT title FROM Chat WHERE id = ?1")?;
+        let title = stmt
+            .query_row(params![group_id], |row| row.get(0))
+            .unwrap_or_default();
+
+        debug!("db get chat title: {}", title);
+
+        Ok(title)
+    }
+
+    pub fn get_chat_timezone(&self, group_id: i64) -> Result<String> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT timezone FROM Chat WHERE id = ?1")?;
+        let timezone = stmt
+            .query_row(params![group_id], |row| row.get(0))
+            .unwrap_or_else(|_| DEFAULT_TIMEZONE.to_string());
+
+        debug!("db get chat timezone: {}", timezone);
+
+        Ok(timezone)
+    }
 }
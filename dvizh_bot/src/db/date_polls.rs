@@ -0,0 +1,164 @@
+use crate::db::db_objects::DatePoll;
+use crate::db::repository::DvizhRepository;
+use anyhow::Result;
+use log::debug;
+use rusqlite::{params, OptionalExtension, Row};
+
+static DATE_POLL_SELECT: &str =
+    "SELECT id, poll_id, group_id, message_id, title, timezone, created_by, closed, event_id,
+        (SELECT group_concat(value, char(10) ORDER BY position)
+            FROM DatePollOptions WHERE poll_id = DatePolls.id)
+    FROM DatePolls";
+
+impl DvizhRepository {
+    pub fn add_date_poll(&self, poll: &DatePoll) -> Result<i64> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO DatePolls (poll_id, group_id, message_id, title, timezone, created_by)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                poll.poll_id,
+                poll.group_id,
+                poll.message_id,
+                poll.title,
+                poll.timezone,
+                poll.created_by
+            ],
+        )?;
+        let id = tx.last_insert_rowid();
+        for (position, value) in poll.options.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO DatePollOptions (poll_id, position, value) VALUES (?1, ?2, ?3)",
+                params![id, position, value],
+            )?;
+        }
+        tx.commit()?;
+
+        debug!("db added date poll {id}: {poll:#?}");
+
+        Ok(id)
+    }
+
+    pub fn get_date_poll(&self, id: i64) -> Result<Option<DatePoll>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!("{DATE_POLL_SELECT} WHERE id = ?1"))?;
+        let poll = stmt.query_row(params![id], date_poll_from_row).optional()?;
+
+        debug!("db get date poll {id}: {poll:#?}");
+
+        Ok(poll)
+    }
+
+    pub fn find_date_poll_by_poll_id(&self, poll_id: &str) -> Result<Option<DatePoll>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!("{DATE_POLL_SELECT} WHERE poll_id = ?1"))?;
+        let poll = stmt
+            .query_row(params![poll_id], date_poll_from_row)
+            .optional()?;
+
+        debug!("db find date poll by poll id {poll_id}: {poll:#?}");
+
+        Ok(poll)
+    }
+
+    pub fn find_date_poll_by_message(
+        &self,
+        group_id: i64,
+        message_id: i64,
+    ) -> Result<Option<DatePoll>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "{DATE_POLL_SELECT} WHERE group_id = ?1 AND message_id = ?2"
+        ))?;
+        let poll = stmt
+            .query_row(params![group_id, message_id], date_poll_from_row)
+            .optional()?;
+
+        debug!("db find date poll of message {message_id} in {group_id}: {poll:#?}");
+
+        Ok(poll)
+    }
+
+    /// Replaces the answers of `user_id`, an empty `positions` retracts the vote.
+    pub fn set_date_poll_votes(&self, id: i64, user_id: i64, positions: &[i64]) -> Result<()> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM DatePollVotes WHERE poll_id = ?1 AND user_id = ?2",
+            params![id, user_id],
+        )?;
+        for position in positions {
+            tx.execute(
+                "INSERT INTO DatePollVotes (poll_id, user_id, position) VALUES (?1, ?2, ?3)
+                ON CONFLICT(poll_id, user_id, position) DO NOTHING",
+                params![id, user_id, position],
+            )?;
+        }
+        tx.commit()?;
+
+        debug!("db set votes of {user_id} in date poll {id}: {positions:?}");
+
+        Ok(())
+    }
+
+    /// Returns the number of votes of every option of the poll in poll order.
+    pub fn get_date_poll_vote_counts(&self, poll: &DatePoll) -> Result<Vec<i64>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT position, COUNT(*) FROM DatePollVotes WHERE poll_id = ?1 GROUP BY position",
+        )?;
+        let rows = stmt.query_map(params![poll.id], |row| {
+            Ok((row.get::<_, usize>(0)?, row.get::<_, i64>(1)?))
+        })?;
+
+        let mut counts = vec![0; poll.options.len()];
+        for row in rows {
+            let (position, count) = row?;
+            if let Some(slot) = counts.get_mut(position) {
+                *slot = count;
+            }
+        }
+
+        debug!("db date poll {} vote counts: {counts:?}", poll.id);
+
+        Ok(counts)
+    }
+
+    pub fn close_date_poll(&self, id: i64) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute("UPDATE DatePolls SET closed = 1 WHERE id = ?1", params![id])?;
+
+        debug!("db closed date poll {id}");
+
+        Ok(())
+    }
+
+    pub fn set_date_poll_event(&self, id: i64, event_id: i64) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE DatePolls SET event_id = ?2 WHERE id = ?1",
+            params![id, event_id],
+        )?;
+
+        debug!("db date poll {id} created event {event_id}");
+
+        Ok(())
+    }
+}
+
+fn date_poll_from_row(row: &Row) -> rusqlite::Result<DatePoll> {
+    let options = row.get::<_, Option<String>>(9)?.unwrap_or_default();
+    Ok(DatePoll {
+        id: row.get(0)?,
+        poll_id: row.get(1)?,
+        group_id: row.get(2)?,
+        message_id: row.get(3)?,
+        title: row.get(4)?,
+        timezone: row.get(5)?,
+        created_by: row.get(6)?,
+        closed: row.get(7)?,
+        event_id: row.get(8)?,
+        options: options.lines().map(|value| value.to_string()).collect(),
+    })
+}
@@ -1,25 +1,45 @@
+use crate::recurrence::Recurrence;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Default, Serialize, Deserialize, Debug, Clone)]
 pub struct User {
-    pub username: String,
+    /// Telegram id, negative for users migrated from usernames whose id is not known yet.
+    pub id: i64,
+    pub username: Option<String>,
     pub first_name: Option<String>,
+    /// `DD.MM.YYYY`, or `DD.MM` when the year is not shared.
     pub birthdate: Option<String>,
     pub language_code: Option<String>,
+    /// Whether birthday messages mention the age.
+    pub show_age: bool,
 }
 
 impl User {
     pub fn new(
-        username: String,
+        id: i64,
+        username: Option<String>,
         first_name: Option<String>,
         birthdate: Option<String>,
         language_code: Option<String>,
     ) -> Self {
         User {
+            id,
             username,
             first_name,
             birthdate,
             language_code,
+            show_age: true,
+        }
+    }
+
+    /// First name followed by the username when the user has one.
+    pub fn display_name(&self) -> String {
+        let first_name = self.first_name.as_deref().unwrap_or_default();
+        match &self.username {
+            Some(username) if first_name.is_empty() => format!("@{username}"),
+            Some(username) => format!("{first_name} (@{username})"),
+            None => first_name.to_string(),
         }
     }
 }
@@ -29,14 +49,16 @@ pub struct Chat {
     pub id: i64,
     pub title: String,
     pub language_code: String,
+    pub timezone: String,
 }
 
 impl Chat {
-    pub fn new(id: i64, title: String, language_code: String) -> Self {
+    pub fn new(id: i64, title: String, language_code: String, timezone: String) -> Self {
         Chat {
             id,
             title,
             language_code,
+            timezone,
         }
     }
 }
@@ -49,27 +71,161 @@ pub struct Members {
 
 #[derive(Default, Serialize, Deserialize, Debug, Clone)]
 pub struct Event {
+    pub id: i64,
     pub group_id: i64,
     pub title: String,
     pub location: String,
-    pub date: String,
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    pub timezone: String,
     pub description: String,
+    pub recurrence: Option<Recurrence>,
+    pub recurrence_until: Option<NaiveDate>,
+    /// Local dates of cancelled occurrences of a recurring event.
+    pub skipped_dates: Vec<NaiveDate>,
+    /// Telegram id of the organizer.
+    pub created_by: Option<i64>,
+    /// Reminder offsets such as `7d,1d,2h`, `None` uses the chat setting.
+    pub reminders: Option<String>,
+    /// UID of an event imported from an iCalendar file.
+    pub uid: Option<String>,
+    /// Proposed by a member and waiting for the approval of an admin.
+    pub pending: bool,
+    /// Number of confirmed places, `None` when unlimited.
+    pub capacity: Option<i64>,
+    /// Telegram file id of the poster photo.
+    pub poster: Option<String>,
+    /// Latitude and longitude of the venue.
+    pub coordinates: Option<(f64, f64)>,
+    /// Hidden from the members and reminded to the admins privately, such as gift planning.
+    pub admins_only: bool,
 }
 
 impl Event {
     pub fn new(
         group_id: i64,
         title: String,
-        date: String,
+        start: DateTime<Utc>,
+        end: Option<DateTime<Utc>>,
+        timezone: String,
         location: String,
         description: String,
     ) -> Self {
         Event {
+            id: 0,
             group_id,
             title,
             location,
-            date,
+            start,
+            end,
+            timezone,
             description,
+            recurrence: None,
+            recurrence_until: None,
+            skipped_dates: Vec::new(),
+            created_by: None,
+            reminders: None,
+            uid: None,
+            pending: false,
+            capacity: None,
+            poster: None,
+            coordinates: None,
+            admins_only: false,
+        }
+    }
+
+    pub fn with_recurrence(
+        mut self,
+        recurrence: Recurrence,
+        until: Option<NaiveDate>,
+        skipped_dates: Vec<NaiveDate>,
+    ) -> Self {
+        self.recurrence = Some(recurrence);
+        self.recurrence_until = until;
+        self.skipped_dates = skipped_dates;
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum RsvpStatus {
+    Going,
+    Maybe,
+    Declined,
+    /// Wants to go but the event is full, promoted to `Going` when a place frees up.
+    Waitlisted,
+}
+
+/// Statuses users can choose on event cards, waitlisting is decided by the bot.
+pub static RSVP_STATUSES: &[RsvpStatus] =
+    &[RsvpStatus::Going, RsvpStatus::Maybe, RsvpStatus::Declined];
+
+pub fn rsvp_status_from_str(t: &str) -> Option<RsvpStatus> {
+    match t {
+        "going" => Some(RsvpStatus::Going),
+        "maybe" => Some(RsvpStatus::Maybe),
+        "declined" => Some(RsvpStatus::Declined),
+        "waitlisted" => Some(RsvpStatus::Waitlisted),
+        _ => None,
+    }
+}
+
+pub fn rsvp_status_to_str(status: &RsvpStatus) -> &'static str {
+    match status {
+        RsvpStatus::Going => "going",
+        RsvpStatus::Maybe => "maybe",
+        RsvpStatus::Declined => "declined",
+        RsvpStatus::Waitlisted => "waitlisted",
+    }
+}
+
+/// RSVP of a user to an event.
+/// `occurrence` is the local date (`YYYYMMDD`) of an occurrence of a recurring event
+/// and empty for single events.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Attendance {
+    pub event_id: i64,
+    pub occurrence: String,
+    pub user_id: i64,
+    pub username: String,
+    pub first_name: String,
+    pub status: RsvpStatus,
+}
+
+impl Attendance {
+    pub fn new(
+        event_id: i64,
+        occurrence: String,
+        user_id: i64,
+        username: String,
+        first_name: String,
+        status: RsvpStatus,
+    ) -> Self {
+        Attendance {
+            event_id,
+            occurrence,
+            user_id,
+            username,
+            first_name,
+            status,
         }
     }
 }
+
+/// Native Telegram poll for choosing the date of an event.
+/// `options` are event dates as accepted by `/addevent`, read in `timezone`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DatePoll {
+    pub id: i64,
+    /// Id of the poll given by Telegram.
+    pub poll_id: String,
+    pub group_id: i64,
+    pub message_id: i64,
+    pub title: String,
+    pub timezone: String,
+    pub options: Vec<String>,
+    pub created_by: i64,
+    pub closed: bool,
+    /// Event created on the winning date.
+    pub event_id: Option<i64>,
+}
@@ -0,0 +1,45 @@
+use crate::db::repository::DvizhRepository;
+use anyhow::Result;
+use chrono::{NaiveDate, Utc};
+use log::debug;
+use rusqlite::{params, OptionalExtension};
+
+impl DvizhRepository {
+    /// Returns whether the weekly digest of the chat was posted on the local `date`.
+    pub fn is_digest_sent(&self, group_id: i64, date: NaiveDate) -> Result<bool> {
+        let conn = self.pool.get()?;
+        let mut stmt =
+            conn.prepare("SELECT 1 FROM SentDigests WHERE group_id = ?1 AND date = ?2 LIMIT 1")?;
+
+        Ok(stmt.exists(params![group_id, date])?)
+    }
+
+    pub fn mark_digest_sent(&self, group_id: i64, date: NaiveDate, message_id: i64) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT INTO SentDigests (group_id, date, message_id, sent_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(group_id, date) DO NOTHING",
+            params![group_id, date, message_id, Utc::now()],
+        )?;
+
+        debug!("db marked digest of {group_id} on {date} as sent in message {message_id}");
+
+        Ok(())
+    }
+
+    /// Returns the message of the latest weekly digest posted in the chat.
+    pub fn get_last_digest_message_id(&self, group_id: i64) -> Result<Option<i64>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT message_id FROM SentDigests WHERE group_id = ?1 ORDER BY date DESC LIMIT 1",
+        )?;
+        let message_id = stmt
+            .query_row(params![group_id], |row| row.get::<_, i64>(0))
+            .optional()?;
+
+        debug!("db get last digest message of {group_id}: {message_id:?}");
+
+        Ok(message_id)
+    }
+}
@@ -1,77 +1,469 @@
 use crate::db::db_objects::Event;
 use crate::db::repository::DvizhRepository;
+use crate::event_filter::EventFilter;
+use crate::recurrence::expand_event;
+use crate::time_utils::{day_bounds_utc, timezone_or_default, today_in};
 use anyhow::Result;
-use chrono::Local;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use log::debug;
-use rusqlite::params;
+use rusqlite::{params, OptionalExtension, Row, Transaction};
+
+/// How far ahead recurring events are expanded into upcoming occurrences.
+static UPCOMING_DAYS: i64 = 30;
+
+static EVENT_SELECT: &str = "SELECT id, group_id, title, starts_at, ends_at, timezone, location,
+        description, recurrence, recurrence_until, created_by, reminders, uid, pending,
+        capacity, poster, latitude, longitude, admins_only,
+        (SELECT group_concat(date) FROM EventExceptions WHERE event_id = Events.id)
+    FROM Events";
 
 impl DvizhRepository {
-    pub fn add_or_update_event(&self, event: Event) -> Result<()> {
+    pub fn add_event(&self, event: Event) -> Result<i64> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+        let id = add_event_tx(&tx, &event)?;
+        tx.commit()?;
+
+        debug!("db added event {id}: {event:#?}");
+
+        Ok(id)
+    }
+
+    /// Adds the events of an imported file, all of them or none.
+    pub fn import_events(&self, events: Vec<Event>) -> Result<()> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+        for event in &events {
+            add_event_tx(&tx, event)?;
+        }
+        tx.commit()?;
+
+        debug!("db imported {} events", events.len());
+
+        Ok(())
+    }
+
+    pub fn update_event(&self, event: &Event) -> Result<()> {
         let conn = self.pool.get()?;
         conn.execute(
-            "INSERT INTO Events (group_id, title, date, location, description)
-                VALUES (?1, ?2, ?3, ?4, ?5)
-                ON CONFLICT(group_id, title) DO UPDATE SET
-                    date = CASE WHEN Events.date IS NOT NULL THEN excluded.date ELSE Events.date END,
-                    location = CASE WHEN Events.location IS NOT NULL THEN excluded.location ELSE Events.location END,
-                    description = CASE WHEN Events.description IS NOT NULL THEN excluded.description ELSE Events.description END",
-            params![event.group_id, event.title, event.date, event.location, event.description],
+            "UPDATE Events SET title = ?1, starts_at = ?2, ends_at = ?3, timezone = ?4,
+                location = ?5, description = ?6, reminders = ?7, recurrence = ?8,
+                recurrence_until = ?9, uid = ?10, capacity = ?11, poster = ?12, latitude = ?13,
+                longitude = ?14
+            WHERE id = ?15",
+            params![
+                event.title,
+                event.start,
+                event.end,
+                event.timezone,
+                event.location,
+                event.description,
+                event.reminders,
+                event.recurrence.map(|recurrence| recurrence.to_string()),
+                event.recurrence_until,
+                event.uid,
+                event.capacity,
+                event.poster,
+                event.coordinates.map(|(latitude, _)| latitude),
+                event.coordinates.map(|(_, longitude)| longitude),
+                event.id
+            ],
         )?;
 
-        debug!("db updated or added event {event:#?}");
+        debug!("db updated event {event:#?}");
 
         Ok(())
     }
 
-    pub fn get_upcoming_events_for_chat(&self, group_id: i64) -> Result<Vec<Event>> {
+    pub fn delete_event(&self, event_id: i64) -> Result<()> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+        tx.execute(
+            "DELETE FROM EventExceptions WHERE event_id = ?1",
+            params![event_id],
+        )?;
+        tx.execute(
+            "DELETE FROM EventAttendance WHERE event_id = ?1",
+            params![event_id],
+        )?;
+        tx.execute(
+            "DELETE FROM SentReminders WHERE event_id = ?1",
+            params![event_id],
+        )?;
+        tx.execute("DELETE FROM Events WHERE id = ?1", params![event_id])?;
+        tx.commit()?;
+
+        debug!("db deleted event {event_id}");
+
+        Ok(())
+    }
+
+    /// Turns a pending event proposed by a member into a regular event.
+    pub fn approve_event(&self, event_id: i64) -> Result<()> {
         let conn = self.pool.get()?;
-        let mut stmt = conn.prepare(
-            "SELECT group_id, title, date, location, description
-            FROM Events WHERE group_id = ?1 AND substr(date, 7, 4) || '-' || substr(date, 4, 2) || '-' || substr(date, 1, 2) >= strftime('%Y-%m-%d', 'now')",
+        conn.execute(
+            "UPDATE Events SET pending = 0 WHERE id = ?1",
+            params![event_id],
         )?;
-        let events = stmt
-            .query_map(params![group_id], |row| {
-                Ok(Event::new(
-                    row.get(0)?,
-                    row.get(1)?,
-                    row.get(2)?,
-                    row.get(3)?,
-                    row.get(4)?,
-                ))
-            })?
+
+        debug!("db approved event {event_id}");
+
+        Ok(())
+    }
+
+    /// Cancels the occurrence of a recurring event on the local `date`.
+    pub fn skip_event_occurrence(&self, event_id: i64, date: NaiveDate) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT INTO EventExceptions (event_id, date) VALUES (?1, ?2)
+            ON CONFLICT(event_id, date) DO NOTHING",
+            params![event_id, date],
+        )?;
+
+        debug!("db skipped occurrence of event {event_id} on {date}");
+
+        Ok(())
+    }
+
+    pub fn get_event(&self, event_id: i64) -> Result<Option<Event>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!("{EVENT_SELECT} WHERE id = ?1"))?;
+        let event = stmt
+            .query_row(params![event_id], event_from_row)
+            .optional()?;
+
+        debug!("db get event {event_id}: {event:#?}");
+
+        Ok(event)
+    }
+
+    pub fn find_event_id_by_uid(&self, group_id: i64, uid: &str) -> Result<Option<i64>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT id FROM Events WHERE group_id = ?1 AND uid = ?2")?;
+        let event_id = stmt
+            .query_row(params![group_id, uid], |row| row.get::<_, i64>(0))
+            .optional()?;
+
+        debug!("db find event of {group_id} by uid {uid}: {event_id:?}");
+
+        Ok(event_id)
+    }
+
+    /// Returns the occurrence of a recurring event on the local `date`,
+    /// or the event itself when it is not recurring or `date` is not given.
+    pub fn get_event_occurrence(
+        &self,
+        event_id: i64,
+        date: Option<NaiveDate>,
+    ) -> Result<Option<Event>> {
+        let event = match self.get_event(event_id)? {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+
+        Ok(match (event.recurrence, date) {
+            (Some(_), Some(date)) => expand_event(&event, date, date + Duration::days(1)).pop(),
+            _ => Some(event),
+        })
+    }
+
+    /// Returns events of the chat that have not ended before today in the chat timezone.
+    /// Recurring events are expanded into their occurrences of the next `UPCOMING_DAYS` days.
+    pub fn get_upcoming_events_for_chat(&self, group_id: i64) -> Result<Vec<Event>> {
+        let tz = timezone_or_default(&self.get_chat_timezone(group_id)?);
+        let today = today_in(&tz);
+        let (today_start, _) = day_bounds_utc(&tz, today);
+
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "{EVENT_SELECT} WHERE group_id = ?1 AND NOT pending AND NOT admins_only AND (
+                (recurrence IS NULL AND COALESCE(ends_at, starts_at) >= ?2)
+                OR (recurrence IS NOT NULL AND (recurrence_until IS NULL OR recurrence_until >= ?3))
+            )"
+        ))?;
+        let mut events = stmt
+            .query_map(params![group_id, today_start, today], event_from_row)?
             .map(|result| result.unwrap())
+            .flat_map(|event| {
+                if event.recurrence.is_some() {
+                    expand_event(&event, today, today + Duration::days(UPCOMING_DAYS))
+                } else {
+                    vec![event]
+                }
+            })
             .collect::<Vec<Event>>();
+        events.sort_by_key(|event| event.start);
 
         debug!("db get events for chat {group_id}: {events:#?}");
 
         Ok(events)
     }
 
-    pub fn get_today_events(&self) -> Result<Vec<Event>> {
+    /// Returns events of the chat that have not ended before today in the chat timezone,
+    /// recurring events once as their whole series.
+    pub fn get_chat_event_series(&self, group_id: i64) -> Result<Vec<Event>> {
+        let tz = timezone_or_default(&self.get_chat_timezone(group_id)?);
+        let today = today_in(&tz);
+        let (today_start, _) = day_bounds_utc(&tz, today);
+
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "{EVENT_SELECT} WHERE group_id = ?1 AND NOT pending AND NOT admins_only AND (
+                (recurrence IS NULL AND COALESCE(ends_at, starts_at) >= ?2)
+                OR (recurrence IS NOT NULL AND (recurrence_until IS NULL OR recurrence_until >= ?3))
+            )
+            ORDER BY starts_at"
+        ))?;
+        let events = stmt
+            .query_map(params![group_id, today_start, today], event_from_row)?
+            .map(|result| result.unwrap())
+            .collect::<Vec<Event>>();
+
+        debug!("db get event series of {group_id}: {events:#?}");
+
+        Ok(events)
+    }
+
+    /// Returns the events of the chat shown by a list with `filter`.
+    pub fn get_events_for_filter(&self, group_id: i64, filter: &EventFilter) -> Result<Vec<Event>> {
+        let tz = timezone_or_default(&self.get_chat_timezone(group_id)?);
+        match filter.bounds(today_in(&tz)) {
+            Some((from, to)) => {
+                let (mut start, _) = day_bounds_utc(&tz, from);
+                let (_, end) = day_bounds_utc(&tz, to);
+                // This week and this month only show what is still ahead
+                if matches!(filter, EventFilter::Week | EventFilter::Month) {
+                    start = start.max(Utc::now());
+                }
+                self.get_events_starting_between(group_id, start, end)
+            }
+            None => self.get_upcoming_events_for_chat(group_id),
+        }
+    }
+
+    /// Returns events and occurrences of the chat that have already started, newest first.
+    pub fn get_past_events(&self, group_id: i64, filter: &EventFilter) -> Result<Vec<Event>> {
+        let tz = timezone_or_default(&self.get_chat_timezone(group_id)?);
+        let now = Utc::now();
+        let (from, to) = match filter.past_bounds(today_in(&tz)) {
+            Some((from, to)) => (
+                day_bounds_utc(&tz, from).0,
+                day_bounds_utc(&tz, to).1.min(now),
+            ),
+            None => (DateTime::UNIX_EPOCH, now),
+        };
+
+        let mut events = self.get_events_starting_between(group_id, from, to)?;
+        events.reverse();
+
+        Ok(events)
+    }
+
+    /// Returns events of the chat whose title, location or description match the words of `text`.
+    /// Without a filter every matching event is returned once, the best matches first,
+    /// recurring events as their next occurrence. With a filter the matching events
+    /// and occurrences of the filtered list are returned.
+    pub fn search_events(
+        &self,
+        group_id: i64,
+        text: &str,
+        filter: &EventFilter,
+    ) -> Result<Vec<Event>> {
+        let event_ids = self.search_event_ids(group_id, text)?;
+        if *filter != EventFilter::All {
+            let events = self
+                .get_events_for_filter(group_id, filter)?
+                .into_iter()
+                .filter(|event| event_ids.contains(&event.id))
+                .collect();
+            return Ok(events);
+        }
+
+        let mut events = Vec::new();
+        for event_id in event_ids {
+            if let Some(event) = self.get_event(event_id)? {
+                let today = today_in(&timezone_or_default(&event.timezone));
+                let next = match event.recurrence {
+                    Some(_) => expand_event(&event, today, today + Duration::days(UPCOMING_DAYS))
+                        .into_iter()
+                        .next(),
+                    None => None,
+                };
+                events.push(next.unwrap_or(event));
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Returns the ids of events of the chat matching the words of `text`, the best matches first.
+    /// Every word also matches longer words starting with it.
+    fn search_event_ids(&self, group_id: i64, text: &str) -> Result<Vec<i64>> {
+        let query = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| format!("\"{word}\"*"))
+            .collect::<Vec<String>>()
+            .join(" ");
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
         let conn = self.pool.get()?;
         let mut stmt = conn.prepare(
-            "SELECT group_id, title, location, date, description
-            FROM Events WHERE substr(date, 7, 4) || '-' || substr(date, 4, 2) || '-' || substr(date, 1, 2) = strftime('%Y-%m-%d', 'now')",
+            "SELECT Events.id FROM EventSearch JOIN Events ON Events.id = EventSearch.rowid
+            WHERE EventSearch MATCH ?1 AND Events.group_id = ?2 AND NOT Events.pending
+                AND NOT Events.admins_only
+            ORDER BY EventSearch.rank",
         )?;
-        let users = stmt
-            .query_map([], |row| {
-                Ok(Event::new(
-                    row.get(0)?,
-                    row.get(1)?,
-                    row.get(2)?,
-                    row.get(3)?,
-                    row.get(4)?,
-                ))
-            })?
+        let event_ids = stmt
+            .query_map(params![query, group_id], |row| row.get::<_, i64>(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+
+        debug!("db search events of {group_id} for {query}: {event_ids:?}");
+
+        Ok(event_ids)
+    }
+
+    /// Returns events and occurrences of recurring events of the chat starting in `[from, to)`.
+    pub fn get_events_starting_between(
+        &self,
+        group_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Event>> {
+        self.events_starting_between(group_id, from, to, false)
+    }
+
+    /// Returns the events to remind of starting in `[from, to)`, including those only admins see.
+    pub fn get_events_to_remind(
+        &self,
+        group_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Event>> {
+        self.events_starting_between(group_id, from, to, true)
+    }
+
+    fn events_starting_between(
+        &self,
+        group_id: i64,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        with_admins_only: bool,
+    ) -> Result<Vec<Event>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "{EVENT_SELECT} WHERE group_id = ?1 AND NOT pending AND (?5 OR NOT admins_only) AND (
+                (recurrence IS NULL AND starts_at >= ?2 AND starts_at < ?3)
+                OR (recurrence IS NOT NULL AND starts_at < ?3
+                    AND (recurrence_until IS NULL OR recurrence_until >= ?4))
+            )"
+        ))?;
+        let mut events = stmt
+            .query_map(
+                params![
+                    group_id,
+                    from,
+                    to,
+                    from.date_naive() - Duration::days(1),
+                    with_admins_only
+                ],
+                event_from_row,
+            )?
             .map(|result| result.unwrap())
+            .flat_map(|event| {
+                if event.recurrence.is_some() {
+                    // Local dates may differ from UTC ones by a day in either direction
+                    let tz = timezone_or_default(&event.timezone);
+                    let first = from.with_timezone(&tz).date_naive();
+                    let last = to.with_timezone(&tz).date_naive() + Duration::days(1);
+                    expand_event(&event, first, last)
+                } else {
+                    vec![event]
+                }
+            })
+            .filter(|event| event.start >= from && event.start < to)
             .collect::<Vec<Event>>();
+        events.sort_by_key(|event| event.start);
 
-        debug!(
-            "db get events by today {}: {:#?}",
-            Local::now().date_naive(),
-            users
-        );
+        debug!("db get events of {group_id} starting between {from} and {to}: {events:#?}");
 
-        Ok(users)
+        Ok(events)
     }
 }
+
+fn event_from_row(row: &Row) -> rusqlite::Result<Event> {
+    Ok(Event {
+        id: row.get(0)?,
+        group_id: row.get(1)?,
+        title: row.get(2)?,
+        start: row.get(3)?,
+        end: row.get(4)?,
+        timezone: row.get(5)?,
+        location: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+        description: row.get::<_, Option<String>>(7)?.unwrap_or_default(),
+        recurrence: row
+            .get::<_, Option<String>>(8)?
+            .and_then(|recurrence| recurrence.parse().ok()),
+        recurrence_until: row.get(9)?,
+        created_by: row.get(10)?,
+        reminders: row.get(11)?,
+        uid: row.get(12)?,
+        pending: row.get(13)?,
+        capacity: row.get(14)?,
+        poster: row.get(15)?,
+        coordinates: match (row.get(16)?, row.get(17)?) {
+            (Some(latitude), Some(longitude)) => Some((latitude, longitude)),
+            _ => None,
+        },
+        admins_only: row.get(18)?,
+        skipped_dates: row
+            .get::<_, Option<String>>(19)?
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|date| date.parse().ok())
+            .collect(),
+    })
+}
+
+fn add_event_tx(tx: &Transaction, event: &Event) -> Result<i64> {
+    tx.execute(
+        "INSERT INTO Events (group_id, title, starts_at, ends_at, timezone, location, description,
+                recurrence, recurrence_until, created_by, reminders, uid, pending, capacity,
+                poster, latitude, longitude, admins_only)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                ?18)",
+        params![
+            event.group_id,
+            event.title,
+            event.start,
+            event.end,
+            event.timezone,
+            event.location,
+            event.description,
+            event.recurrence.map(|recurrence| recurrence.to_string()),
+            event.recurrence_until,
+            event.created_by,
+            event.reminders,
+            event.uid,
+            event.pending,
+            event.capacity,
+            event.poster,
+            event.coordinates.map(|(latitude, _)| latitude),
+            event.coordinates.map(|(_, longitude)| longitude),
+            event.admins_only
+        ],
+    )?;
+    let id = tx.last_insert_rowid();
+
+    for date in &event.skipped_dates {
+        tx.execute(
+            "INSERT INTO EventExceptions (event_id, date) VALUES (?1, ?2)
+            ON CONFLICT(event_id, date) DO NOTHING",
+            params![id, date],
+        )?;
+    }
+
+    Ok(id)
+}
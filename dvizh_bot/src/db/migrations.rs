@@ -0,0 +1,234 @@
+use crate::db::repository::DvizhRepository;
+use crate::time_utils::{local_to_utc, timezone_or_default, DATE_FORMAT};
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use log::{info, warn};
+use rusqlite::{params, Transaction};
+use std::collections::HashMap;
+
+enum Migration {
+    Sql(&'static str),
+    Rust(fn(&Transaction) -> Result<()>),
+}
+
+/// Schema migrations, applied in order.
+/// The number of applied migrations is stored in `PRAGMA user_version`.
+static MIGRATIONS: &[Migration] = &[
+    Migration::Sql(include_str!("migrations/001_initial_schema.sql")),
+    Migration::Sql(include_str!("migrations/002_event_ids.sql")),
+    Migration::Rust(migrate_event_datetimes),
+    Migration::Sql(include_str!("migrations/004_recurring_events.sql")),
+    Migration::Sql(include_str!("migrations/005_rsvp.sql")),
+    Migration::Sql(include_str!("migrations/006_reminders.sql")),
+    Migration::Sql(include_str!("migrations/007_event_uids.sql")),
+    Migration::Sql(include_str!("migrations/008_event_search.sql")),
+    Migration::Sql(include_str!("migrations/009_event_proposals.sql")),
+    Migration::Sql(include_str!("migrations/010_event_capacity.sql")),
+    Migration::Sql(include_str!("migrations/011_date_polls.sql")),
+    Migration::Sql(include_str!("migrations/012_weekly_digests.sql")),
+    Migration::Sql(include_str!("migrations/013_event_media.sql")),
+    Migration::Rust(migrate_user_ids),
+    Migration::Sql(include_str!("migrations/015_wishlists.sql")),
+    Migration::Sql(include_str!("migrations/016_admin_events.sql")),
+];
+
+impl DvizhRepository {
+    pub(super) fn run_migrations(&self) -> Result<()> {
+        let mut conn = self.pool.get()?;
+        let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = conn.transaction()?;
+            match migration {
+                Migration::Sql(sql) => tx.execute_batch(sql)?,
+                Migration::Rust(migrate) => migrate(&tx)?,
+            }
+            tx.pragma_update(None, "user_version", index + 1)?;
+            tx.commit()?;
+
+            info!("db applied migration {}", index + 1);
+        }
+
+        Ok(())
+    }
+}
+
+/// Replaces the `DD.MM.YYYY [HH:MM[-HH:MM]]` text date of events with UTC start and end times
+/// and a timezone. Events without a time start at midnight in the timezone of their chat.
+/// Events with a date that can not be read are copied to `Events_unmigrated`.
+fn migrate_event_datetimes(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(include_str!("migrations/003_event_datetimes.sql"))?;
+
+    let events = {
+        let mut stmt = tx.prepare(
+            "SELECT Events.id, Events.group_id, Events.title, Events.date, Events.location,
+                Events.description, Chat.timezone
+            FROM Events LEFT JOIN Chat ON Chat.id = Events.group_id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<String>>(5)?,
+                row.get::<_, Option<String>>(6)?,
+            ))
+        })?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+
+    for (id, group_id, title, date, location, description, timezone) in events {
+        let tz = timezone_or_default(timezone.as_deref().unwrap_or_default());
+        match parse_legacy_event_date(&tz, &date) {
+            Some((start, end)) => {
+                tx.execute(
+                    "INSERT INTO Events_new (id, group_id, title, starts_at, ends_at, timezone, location, description)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![id, group_id, title, start, end, tz.name(), location, description],
+                )?;
+            }
+            None => {
+                warn!(
+                    "db kept event {id} '{title}' with invalid date '{date}' in Events_unmigrated"
+                );
+                tx.execute(
+                    "INSERT INTO Events_unmigrated (id, group_id, title, date, location, description)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![id, group_id, title, date, location, description],
+                )?;
+            }
+        }
+    }
+
+    tx.execute_batch(
+        "DROP TABLE Events;
+        ALTER TABLE Events_new RENAME TO Events;
+        CREATE INDEX idx_events_group_starts ON Events (group_id, starts_at);",
+    )?;
+
+    Ok(())
+}
+
+/// Reads the text date of an event saved before events had times,
+/// `DD.MM.YYYY`, `DD/MM/YYYY` or `YYYY-MM-DD` optionally followed by `HH:MM[-HH:MM]`.
+/// An end time before the start is on the next day.
+fn parse_legacy_event_date(tz: &Tz, value: &str) -> Option<(DateTime<Utc>, Option<DateTime<Utc>>)> {
+    let mut parts = value.split_whitespace();
+    let date = parts.next()?;
+    let date = [DATE_FORMAT, "%d/%m/%Y", "%Y-%m-%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(date, format).ok())?;
+    let (start, end) = match parts.next() {
+        Some(time) => match time.split_once('-') {
+            Some((start, end)) => (
+                NaiveTime::parse_from_str(start, "%H:%M").ok()?,
+                Some(NaiveTime::parse_from_str(end, "%H:%M").ok()?),
+            ),
+            None => (NaiveTime::parse_from_str(time, "%H:%M").ok()?, None),
+        },
+        None => (NaiveTime::MIN, None),
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let end = match end {
+        Some(end) if end <= start => Some((date + Duration::days(1)).and_time(end)),
+        Some(end) => Some(date.and_time(end)),
+        None => None,
+    };
+    let end = match end {
+        Some(end) => Some(local_to_utc(tz, end)?),
+        None => None,
+    };
+    Some((local_to_utc(tz, date.and_time(start))?, end))
+}
+
+/// Keys users by their Telegram id instead of the username.
+/// Ids are taken from RSVPs, other users get negative placeholder ids
+/// until they are seen again.
+fn migrate_user_ids(tx: &Transaction) -> Result<()> {
+    let users = {
+        let mut stmt =
+            tx.prepare("SELECT username, first_name, birthdate, language_code FROM User")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+    let members = select_pairs(tx, "SELECT group_id, user_id FROM Members")?;
+    let admins = select_pairs(tx, "SELECT group_id, user_id FROM Admins")?;
+    let known_ids: HashMap<String, i64> = {
+        let mut stmt = tx.prepare(
+            "SELECT lower(username), user_id FROM EventAttendance
+            WHERE username IS NOT NULL AND username != ''
+            ORDER BY updated_at",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+
+    tx.execute_batch(include_str!("migrations/014_user_ids.sql"))?;
+
+    let mut ids = HashMap::new();
+    for (username, first_name, birthdate, language_code) in users {
+        let id = user_id_for(tx, &known_ids, &mut ids, &username)?;
+        tx.execute(
+            "UPDATE User SET first_name = ?2, birthdate = ?3, language_code = ?4 WHERE id = ?1",
+            params![id, first_name, birthdate, language_code],
+        )?;
+    }
+
+    for (table, rows) in [("Members", members), ("Admins", admins)] {
+        for (group_id, username) in rows {
+            let id = user_id_for(tx, &known_ids, &mut ids, &username)?;
+            tx.execute(
+                &format!("INSERT OR IGNORE INTO {table} (group_id, user_id) VALUES (?1, ?2)"),
+                params![group_id, id],
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn select_pairs(tx: &Transaction, sql: &str) -> Result<Vec<(i64, String)>> {
+    let mut stmt = tx.prepare(sql)?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// Returns the id of a migrated user, adding the user when it is seen for the first time.
+fn user_id_for(
+    tx: &Transaction,
+    known_ids: &HashMap<String, i64>,
+    ids: &mut HashMap<String, i64>,
+    username: &str,
+) -> Result<i64> {
+    if let Some(id) = ids.get(username) {
+        return Ok(*id);
+    }
+
+    let id = match known_ids.get(&username.to_lowercase()) {
+        Some(id) => *id,
+        None => {
+            warn!("db has no telegram id for user {username}, using a placeholder");
+            -(ids.len() as i64 + 1)
+        }
+    };
+    tx.execute(
+        "INSERT OR IGNORE INTO User (id, username) VALUES (?1, ?2)",
+        params![id, username],
+    )?;
+    ids.insert(username.to_string(), id);
+
+    Ok(id)
+}
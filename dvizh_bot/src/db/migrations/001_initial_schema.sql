@@ -0,0 +1,33 @@
+CREATE TABLE IF NOT EXISTS User (
+    username VARCHAR (50) NOT NULL UNIQUE PRIMARY KEY,
+    first_name VARCHAR (50),
+    birthdate DATETIME,
+    language_code VARCHAR (5)
+);
+
+CREATE TABLE IF NOT EXISTS Chat (
+    id INTEGER PRIMARY KEY NOT NULL UNIQUE,
+    title VARCHAR (50) NOT NULL,
+    language_code VARCHAR (5)
+);
+
+CREATE TABLE IF NOT EXISTS Members (
+    group_id INTEGER REFERENCES Chat (id),
+    user_id VARCHAR (50) REFERENCES User (username)
+);
+CREATE UNIQUE INDEX IF NOT EXISTS unique_member_group ON Members (group_id, user_id);
+
+CREATE TABLE IF NOT EXISTS Admins (
+    group_id INTEGER REFERENCES Chat (id),
+    user_id VARCHAR (50) REFERENCES User (username)
+);
+CREATE UNIQUE INDEX IF NOT EXISTS unique_admin_group ON Admins (group_id, user_id);
+
+CREATE TABLE IF NOT EXISTS Events (
+    group_id INTEGER NOT NULL REFERENCES Chat (id),
+    title VARCHAR (50) NOT NULL,
+    date Date NOT NULL,
+    location VARCHAR (20),
+    description VARCHAR (100)
+);
+CREATE UNIQUE INDEX IF NOT EXISTS unique_group_event ON Events (group_id, title);
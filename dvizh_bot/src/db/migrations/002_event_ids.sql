@@ -0,0 +1,17 @@
+-- Events get a surrogate id, existing rows keep their rowid as id
+CREATE TABLE Events_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    group_id INTEGER NOT NULL REFERENCES Chat (id),
+    title VARCHAR (50) NOT NULL,
+    date Date NOT NULL,
+    location VARCHAR (20),
+    description VARCHAR (100)
+);
+
+INSERT INTO Events_new (id, group_id, title, date, location, description)
+    SELECT rowid, group_id, title, date, location, description FROM Events;
+
+DROP TABLE Events;
+ALTER TABLE Events_new RENAME TO Events;
+
+CREATE INDEX idx_events_group ON Events (group_id);
@@ -0,0 +1,24 @@
+ALTER TABLE Chat ADD COLUMN timezone VARCHAR (50) NOT NULL DEFAULT 'Europe/Warsaw';
+
+CREATE TABLE Events_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    group_id INTEGER NOT NULL REFERENCES Chat (id),
+    title VARCHAR (50) NOT NULL,
+    starts_at DATETIME NOT NULL,
+    ends_at DATETIME,
+    timezone VARCHAR (50) NOT NULL,
+    location VARCHAR (20),
+    description VARCHAR (100)
+);
+
+DROP INDEX IF EXISTS idx_events_group;
+
+-- Events whose text date can not be read are kept here instead of being dropped
+CREATE TABLE Events_unmigrated (
+    id INTEGER PRIMARY KEY,
+    group_id INTEGER NOT NULL,
+    title VARCHAR (50) NOT NULL,
+    date VARCHAR (50),
+    location VARCHAR (20),
+    description VARCHAR (100)
+);
@@ -0,0 +1,9 @@
+ALTER TABLE Events ADD COLUMN recurrence VARCHAR (30);
+ALTER TABLE Events ADD COLUMN recurrence_until DATE;
+
+-- Cancelled occurrences of recurring events, by local date
+CREATE TABLE EventExceptions (
+    event_id INTEGER NOT NULL REFERENCES Events (id),
+    date DATE NOT NULL,
+    PRIMARY KEY (event_id, date)
+);
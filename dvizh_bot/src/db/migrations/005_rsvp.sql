@@ -0,0 +1,23 @@
+-- Per-chat settings changed with /settings
+CREATE TABLE ChatSettings (
+    group_id INTEGER NOT NULL REFERENCES Chat (id),
+    key VARCHAR (30) NOT NULL,
+    value VARCHAR (100) NOT NULL,
+    PRIMARY KEY (group_id, key)
+);
+
+-- Telegram id of the user who created the event
+ALTER TABLE Events ADD COLUMN created_by INTEGER;
+
+-- RSVP responses; occurrence is the local date (YYYYMMDD) of a recurring event
+-- occurrence and empty for single events
+CREATE TABLE EventAttendance (
+    event_id INTEGER NOT NULL REFERENCES Events (id),
+    occurrence VARCHAR (8) NOT NULL DEFAULT '',
+    user_id INTEGER NOT NULL,
+    username VARCHAR (50),
+    first_name VARCHAR (50) NOT NULL,
+    status VARCHAR (10) NOT NULL,
+    updated_at DATETIME NOT NULL,
+    PRIMARY KEY (event_id, occurrence, user_id)
+);
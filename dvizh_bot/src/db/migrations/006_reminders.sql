@@ -0,0 +1,11 @@
+-- Reminder offsets of the event, e.g. 7d,1d,2h; NULL uses the chat setting
+ALTER TABLE Events ADD COLUMN reminders VARCHAR (50);
+
+-- Reminders already sent, so a restart does not send them again
+CREATE TABLE SentReminders (
+    event_id INTEGER NOT NULL REFERENCES Events (id),
+    starts_at DATETIME NOT NULL,
+    offset_minutes INTEGER NOT NULL,
+    sent_at DATETIME NOT NULL,
+    PRIMARY KEY (event_id, starts_at, offset_minutes)
+);
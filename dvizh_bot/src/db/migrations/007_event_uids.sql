@@ -0,0 +1,4 @@
+-- UID of events imported from iCalendar files, used to detect duplicates
+ALTER TABLE Events ADD COLUMN uid VARCHAR (255);
+
+CREATE UNIQUE INDEX idx_events_group_uid ON Events (group_id, uid);
@@ -0,0 +1,29 @@
+-- Full-text index over the title, location and description of events
+CREATE VIRTUAL TABLE EventSearch USING fts5 (
+    title,
+    location,
+    description,
+    content = 'Events',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO EventSearch (EventSearch) VALUES ('rebuild');
+
+-- Keep the index in sync with the Events table
+CREATE TRIGGER events_search_insert AFTER INSERT ON Events BEGIN
+    INSERT INTO EventSearch (rowid, title, location, description)
+        VALUES (new.id, new.title, new.location, new.description);
+END;
+
+CREATE TRIGGER events_search_delete AFTER DELETE ON Events BEGIN
+    INSERT INTO EventSearch (EventSearch, rowid, title, location, description)
+        VALUES ('delete', old.id, old.title, old.location, old.description);
+END;
+
+CREATE TRIGGER events_search_update AFTER UPDATE OF title, location, description ON Events BEGIN
+    INSERT INTO EventSearch (EventSearch, rowid, title, location, description)
+        VALUES ('delete', old.id, old.title, old.location, old.description);
+    INSERT INTO EventSearch (rowid, title, location, description)
+        VALUES (new.id, new.title, new.location, new.description);
+END;
@@ -0,0 +1,2 @@
+-- Events proposed by members stay pending until an admin approves them
+ALTER TABLE Events ADD COLUMN pending BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Number of confirmed places of an event, NULL when unlimited.
+-- RSVPs beyond it wait in EventAttendance with the status 'waitlisted'.
+ALTER TABLE Events ADD COLUMN capacity INTEGER;
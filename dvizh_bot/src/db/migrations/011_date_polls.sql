@@ -0,0 +1,29 @@
+-- Native polls for choosing the date of an event, created with /datepoll
+CREATE TABLE DatePolls (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    poll_id VARCHAR (50) NOT NULL UNIQUE,
+    group_id INTEGER NOT NULL REFERENCES Chat (id),
+    message_id INTEGER NOT NULL,
+    title VARCHAR (255) NOT NULL,
+    timezone VARCHAR (50) NOT NULL,
+    created_by INTEGER NOT NULL,
+    closed BOOLEAN NOT NULL DEFAULT 0,
+    -- Event created on the winning date, it may have been deleted since
+    event_id INTEGER
+);
+
+-- Answer options in poll order, each an event date as accepted by /addevent
+CREATE TABLE DatePollOptions (
+    poll_id INTEGER NOT NULL REFERENCES DatePolls (id),
+    position INTEGER NOT NULL,
+    value VARCHAR (100) NOT NULL,
+    PRIMARY KEY (poll_id, position)
+);
+
+-- Current answers of each voter, kept up to date from poll_answer updates
+CREATE TABLE DatePollVotes (
+    poll_id INTEGER NOT NULL REFERENCES DatePolls (id),
+    user_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (poll_id, user_id, position)
+);
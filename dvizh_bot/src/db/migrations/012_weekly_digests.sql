@@ -0,0 +1,8 @@
+-- Weekly digests already posted; date is the local date of the post
+CREATE TABLE SentDigests (
+    group_id INTEGER NOT NULL REFERENCES Chat (id),
+    date DATE NOT NULL,
+    message_id INTEGER NOT NULL,
+    sent_at DATETIME NOT NULL,
+    PRIMARY KEY (group_id, date)
+);
@@ -0,0 +1,5 @@
+-- Telegram file id of the poster photo of the event
+ALTER TABLE Events ADD COLUMN poster VARCHAR (255);
+-- Coordinates of the venue, both NULL when unknown
+ALTER TABLE Events ADD COLUMN latitude REAL;
+ALTER TABLE Events ADD COLUMN longitude REAL;
@@ -0,0 +1,30 @@
+-- Users are keyed by their Telegram id, usernames are optional and may change.
+-- Users whose id is not known yet get a negative placeholder id.
+-- Birthdates may be DD.MM without the year, which the numeric affinity of a DATETIME
+-- column would turn into a number, so they are kept as text.
+DROP TABLE Members;
+DROP TABLE Admins;
+DROP TABLE User;
+
+CREATE TABLE User (
+    id INTEGER PRIMARY KEY NOT NULL,
+    username VARCHAR (50) COLLATE NOCASE,
+    first_name VARCHAR (50),
+    birthdate VARCHAR (10),
+    language_code VARCHAR (5),
+    -- 0 when the user's age is left out of birthday greetings
+    show_age INTEGER NOT NULL DEFAULT 1
+);
+CREATE INDEX idx_user_username ON User (username);
+
+CREATE TABLE Members (
+    group_id INTEGER REFERENCES Chat (id),
+    user_id INTEGER REFERENCES User (id)
+);
+CREATE UNIQUE INDEX unique_member_group ON Members (group_id, user_id);
+
+CREATE TABLE Admins (
+    group_id INTEGER REFERENCES Chat (id),
+    user_id INTEGER REFERENCES User (id)
+);
+CREATE UNIQUE INDEX unique_admin_group ON Admins (group_id, user_id);
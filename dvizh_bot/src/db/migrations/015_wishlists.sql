@@ -0,0 +1,9 @@
+-- Wishlist entries of a user, a short text or a link each;
+-- only members of a group shared with the user see them
+CREATE TABLE Wishes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES User (id),
+    text VARCHAR (200) NOT NULL,
+    created_at DATETIME NOT NULL
+);
+CREATE INDEX idx_wishes_user ON Wishes (user_id);
@@ -0,0 +1,3 @@
+-- Events only the admins of the chat see, such as the gift planning event of a birthday.
+-- They are left out of lists, calendars and digests, and their reminders go to the admins privately
+ALTER TABLE Events ADD COLUMN admins_only INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,45 @@
+use crate::db::repository::DvizhRepository;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::debug;
+use rusqlite::params;
+
+impl DvizhRepository {
+    /// Returns whether the reminder `offset_minutes` before the occurrence
+    /// of `event_id` starting at `starts_at` was already sent.
+    pub fn is_reminder_sent(
+        &self,
+        event_id: i64,
+        starts_at: DateTime<Utc>,
+        offset_minutes: i64,
+    ) -> Result<bool> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT 1 FROM SentReminders
+            WHERE event_id = ?1 AND starts_at = ?2 AND offset_minutes = ?3 LIMIT 1",
+        )?;
+
+        Ok(stmt.exists(params![event_id, starts_at, offset_minutes])?)
+    }
+
+    pub fn mark_reminder_sent(
+        &self,
+        event_id: i64,
+        starts_at: DateTime<Utc>,
+        offset_minutes: i64,
+    ) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT INTO SentReminders (event_id, starts_at, offset_minutes, sent_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(event_id, starts_at, offset_minutes) DO NOTHING",
+            params![event_id, starts_at, offset_minutes, Utc::now()],
+        )?;
+
+        debug!(
+            "db marked reminder {offset_minutes}m before {starts_at} of event {event_id} as sent"
+        );
+
+        Ok(())
+    }
+}
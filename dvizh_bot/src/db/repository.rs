@@ -12,6 +12,8 @@ impl DvizhRepository {
     pub fn new(db_path: &str) -> Result<Self> {
         let manager = SqliteConnectionManager::file(db_path);
         let pool = Pool::builder().max_size(15).build(manager)?;
-        Ok(DvizhRepository { pool })
+        let repo = DvizhRepository { pool };
+        repo.run_migrations()?;
+        Ok(repo)
     }
 }
@@ -0,0 +1,42 @@
+use crate::chat_settings::{chat_setting_default, chat_setting_to_str, ChatSetting};
+use crate::db::repository::DvizhRepository;
+use anyhow::Result;
+use log::debug;
+use rusqlite::{params, OptionalExtension};
+
+impl DvizhRepository {
+    pub fn get_chat_setting(&self, group_id: i64, setting: &ChatSetting) -> Result<String> {
+        let conn = self.pool.get()?;
+        let mut stmt =
+            conn.prepare("SELECT value FROM ChatSettings WHERE group_id = ?1 AND key = ?2")?;
+        let value = stmt
+            .query_row(params![group_id, chat_setting_to_str(setting)], |row| {
+                row.get::<_, String>(0)
+            })
+            .optional()?
+            .unwrap_or_else(|| chat_setting_default(setting).to_string());
+
+        debug!("db get chat setting {setting:?} for {group_id}: {value}");
+
+        Ok(value)
+    }
+
+    pub fn set_chat_setting(
+        &self,
+        group_id: i64,
+        setting: &ChatSetting,
+        value: &str,
+    ) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT INTO ChatSettings (group_id, key, value)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT(group_id, key) DO UPDATE SET value = excluded.value",
+            params![group_id, chat_setting_to_str(setting), value],
+        )?;
+
+        debug!("db set chat setting {setting:?} for {group_id}: {value}");
+
+        Ok(())
+    }
+}
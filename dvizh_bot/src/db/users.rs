@@ -2,73 +2,174 @@ use crate::db::db_objects::User;
 use crate::db::repository::DvizhRepository;
 use anyhow::Result;
 use log::debug;
-use rusqlite::{params, Transaction};
+use rusqlite::{params, OptionalExtension, Row, Transaction};
+
+const USER_SELECT: &str =
+    "SELECT id, username, first_name, birthdate, language_code, show_age FROM User";
 
 impl DvizhRepository {
+    /// Adds the user or updates the known fields, `None` fields keep their stored values.
     pub fn add_or_update_user(&self, user: User, chat_id: i64) -> Result<()> {
         let mut conn = self.pool.get()?;
         let tx = conn.transaction()?;
+        self.add_or_update_user_tx(&tx, user, chat_id)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Adds or updates the users of an imported birthday list, all of them or none.
+    /// Users with the id `0` are only known by username and get a placeholder id
+    /// until they are seen.
+    pub fn import_birthdays(&self, users: Vec<User>, chat_id: i64) -> Result<()> {
+        let mut conn = self.pool.get()?;
+        let tx = conn.transaction()?;
+        for mut user in users {
+            if user.id == 0 {
+                user.id = tx.query_row(
+                    "SELECT MIN(0, COALESCE(MIN(id), 0)) - 1 FROM User",
+                    [],
+                    |row| row.get(0),
+                )?;
+            }
+            // Users hiding their age are exported without the year, which is kept
+            if let Some(birthdate) = user.birthdate.as_deref().filter(|date| date.len() == 5) {
+                let stored = tx
+                    .query_row(
+                        "SELECT birthdate FROM User WHERE id = ?1",
+                        params![user.id],
+                        |row| row.get::<_, Option<String>>(0),
+                    )
+                    .optional()?
+                    .flatten();
+                if stored
+                    .as_deref()
+                    .is_some_and(|stored| stored.starts_with(birthdate))
+                {
+                    user.birthdate = stored;
+                }
+            }
+            self.add_or_update_user_tx(&tx, user, chat_id)?;
+        }
+        tx.commit()?;
+
+        debug!("db imported birthdays of {chat_id}");
+
+        Ok(())
+    }
+
+    fn add_or_update_user_tx(&self, tx: &Transaction, user: User, chat_id: i64) -> Result<()> {
+        if let Some(username) = &user.username {
+            self.claim_placeholder_tx(tx, user.id, username)?;
+            // Usernames can be given up and taken by someone else
+            tx.execute(
+                "UPDATE User SET username = NULL WHERE username = ?1 AND id != ?2",
+                params![username, user.id],
+            )?;
+        }
 
         tx.execute(
-            "INSERT INTO User (username, first_name, birthdate, language_code)
-                VALUES (?1, ?2, ?3, ?4)
-                ON CONFLICT(username) DO UPDATE SET
-                    first_name = CASE WHEN User.first_name IS NULL THEN excluded.first_name ELSE User.first_name END,
-                    birthdate = CASE WHEN excluded.birthdate IS NOT NULL THEN excluded.birthdate ELSE User.birthdate END,
-                    language_code = CASE WHEN User.language_code IS NULL THEN excluded.language_code ELSE User.language_code END",
-            params![user.username, user.first_name, user.birthdate, user.language_code],
+            "INSERT INTO User (id, username, first_name, birthdate, language_code)
+                VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT(id) DO UPDATE SET
+                    username = COALESCE(excluded.username, User.username),
+                    first_name = COALESCE(excluded.first_name, User.first_name),
+                    birthdate = COALESCE(excluded.birthdate, User.birthdate),
+                    language_code = COALESCE(User.language_code, excluded.language_code)",
+            params![
+                user.id,
+                user.username,
+                user.first_name,
+                user.birthdate,
+                user.language_code
+            ],
         )?;
 
         debug!("db updated or added user {user:#?}");
 
-        self.add_membership_tx(&tx, &user.username, chat_id)?;
-
-        tx.commit()?;
+        self.add_membership_tx(tx, user.id, chat_id)?;
 
         Ok(())
     }
 
-    pub fn get_users_by_birthday(&self, birthday: &str) -> Result<Vec<User>> {
+    pub fn get_user(&self, id: i64) -> Result<Option<User>> {
         let conn = self.pool.get()?;
-        let mut stmt = conn.prepare(
-            "SELECT username,
-                first_name,
-                birthdate,
-                language_code
-            FROM User WHERE birthdate LIKE ?1",
-        )?;
+        let user = conn
+            .query_row(
+                &format!("{USER_SELECT} WHERE id = ?1"),
+                params![id],
+                user_from_row,
+            )
+            .optional()?;
+
+        debug!("db get user {id}: {user:#?}");
+
+        Ok(user)
+    }
+
+    /// Finds a user by username, case-insensitively and without the leading `@`.
+    pub fn find_user_by_username(&self, username: &str) -> Result<Option<User>> {
+        let conn = self.pool.get()?;
+        let user = conn
+            .query_row(
+                &format!("{USER_SELECT} WHERE username = ?1 ORDER BY id > 0 DESC LIMIT 1"),
+                params![username.trim_start_matches('@')],
+                user_from_row,
+            )
+            .optional()?;
+
+        debug!("db find user {username}: {user:#?}");
+
+        Ok(user)
+    }
+
+    /// Returns the members of the chat born on the `DD.MM` birthday.
+    pub fn get_users_by_birthday(&self, group_id: i64, birthday: &str) -> Result<Vec<User>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "{USER_SELECT} WHERE birthdate LIKE ?2
+                AND id IN (SELECT user_id FROM Members WHERE group_id = ?1)"
+        ))?;
         let users = stmt
-            .query_map(params![format!("{}%", birthday)], |row| {
-                Ok(User::new(
-                    row.get(0)?,
-                    row.get(1)?,
-                    row.get(2)?,
-                    row.get(3)?,
-                ))
-            })?
+            .query_map(params![group_id, format!("{}%", birthday)], user_from_row)?
             .map(|result| result.unwrap())
             .collect::<Vec<User>>();
 
-        debug!("db get users by birthday {birthday}: {users:#?}");
+        debug!("db get users of {group_id} by birthday {birthday}: {users:#?}");
 
         Ok(users)
     }
 
-    pub fn get_chats_for_user(&self, user_id: &str) -> Result<Vec<i64>> {
+    /// Returns the members of the chat who shared their birthday.
+    pub fn get_chat_birthdays(&self, group_id: i64) -> Result<Vec<User>> {
         let conn = self.pool.get()?;
-        let mut stmt = conn.prepare("SELECT group_id FROM Members WHERE user_id = ?1")?;
-
-        let chat_ids = stmt
-            .query_map(params![user_id], |row| row.get(0))?
+        let mut stmt = conn.prepare(&format!(
+            "{USER_SELECT} WHERE birthdate IS NOT NULL
+                AND id IN (SELECT user_id FROM Members WHERE group_id = ?1)"
+        ))?;
+        let users = stmt
+            .query_map(params![group_id], user_from_row)?
             .map(|result| result.unwrap())
-            .collect::<Vec<i64>>();
+            .collect::<Vec<User>>();
 
-        debug!("db get chats for user {user_id}: {chat_ids:#?}");
+        debug!("db get birthdays of {group_id}: {users:#?}");
 
-        Ok(chat_ids)
+        Ok(users)
     }
 
-    pub fn add_admin(&self, user_id: &str, group_id: i64) -> Result<()> {
+    pub fn set_show_age(&self, user_id: i64, show_age: bool) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE User SET show_age = ?2 WHERE id = ?1",
+            params![user_id, show_age],
+        )?;
+
+        debug!("db set show age of {user_id}: {show_age}");
+
+        Ok(())
+    }
+
+    pub fn add_admin(&self, user_id: i64, group_id: i64) -> Result<()> {
         let conn = self.pool.get()?;
         conn.execute(
             "INSERT INTO Admins (group_id, user_id)
@@ -82,7 +183,7 @@ impl DvizhRepository {
         Ok(())
     }
 
-    pub fn is_not_admin(&self, user_id: &str, group_id: i64) -> Result<bool> {
+    pub fn is_not_admin(&self, user_id: i64, group_id: i64) -> Result<bool> {
         let conn = self.pool.get()?;
         let mut stmt =
             conn.prepare("SELECT 1 FROM Admins WHERE group_id = ? AND user_id = ? LIMIT 1")?;
@@ -92,10 +193,50 @@ impl DvizhRepository {
         Ok(!stmt.exists(params![group_id, user_id])?)
     }
 
-    fn add_membership_tx(&self, tx: &Transaction, user_id: &str, group_id: i64) -> Result<()> {
+    /// Moves a user migrated without a known id to the real id once the user shows up.
+    fn claim_placeholder_tx(&self, tx: &Transaction, id: i64, username: &str) -> Result<()> {
+        let placeholder: Option<i64> = tx
+            .query_row(
+                "SELECT id FROM User WHERE username = ?1 AND id < 0",
+                params![username],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let placeholder = match placeholder {
+            Some(placeholder) if id > 0 => placeholder,
+            _ => return Ok(()),
+        };
+
+        tx.execute(
+            "INSERT INTO User (id, username, first_name, birthdate, language_code)
+                SELECT ?1, username, first_name, birthdate, language_code FROM User WHERE id = ?2
+                ON CONFLICT(id) DO UPDATE SET
+                    birthdate = COALESCE(User.birthdate, excluded.birthdate),
+                    language_code = COALESCE(User.language_code, excluded.language_code)",
+            params![id, placeholder],
+        )?;
+        for table in ["Members", "Admins"] {
+            tx.execute(
+                &format!("UPDATE OR IGNORE {table} SET user_id = ?1 WHERE user_id = ?2"),
+                params![id, placeholder],
+            )?;
+            tx.execute(
+                &format!("DELETE FROM {table} WHERE user_id = ?1"),
+                params![placeholder],
+            )?;
+        }
+        tx.execute("DELETE FROM User WHERE id = ?1", params![placeholder])?;
+
+        debug!("db moved user {username} from placeholder {placeholder} to {id}");
+
+        Ok(())
+    }
+
+    /// Chats the bot does not know about are skipped.
+    fn add_membership_tx(&self, tx: &Transaction, user_id: i64, group_id: i64) -> Result<()> {
         tx.execute(
             "INSERT INTO Members (group_id, user_id)
-            VALUES (?1, ?2)
+            SELECT ?1, ?2 WHERE EXISTS (SELECT 1 FROM Chat WHERE id = ?1)
             ON CONFLICT(group_id, user_id) DO NOTHING",
             params![group_id, user_id],
         )?;
@@ -105,3 +246,15 @@ impl DvizhRepository {
         Ok(())
     }
 }
+
+fn user_from_row(row: &Row) -> rusqlite::Result<User> {
+    let mut user = User::new(
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+    );
+    user.show_age = row.get(5)?;
+    Ok(user)
+}
@@ -0,0 +1,68 @@
+use crate::db::repository::DvizhRepository;
+use anyhow::Result;
+use chrono::Utc;
+use log::debug;
+use rusqlite::params;
+
+impl DvizhRepository {
+    pub fn add_wish(&self, user_id: i64, text: &str) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT INTO Wishes (user_id, text, created_at) VALUES (?1, ?2, ?3)",
+            params![user_id, text, Utc::now()],
+        )?;
+
+        debug!("db added wish of {user_id}: {text}");
+
+        Ok(())
+    }
+
+    /// Removes the wish at the 1-based `position` of the wishlist.
+    /// Returns whether there was such a wish.
+    pub fn remove_wish(&self, user_id: i64, position: usize) -> Result<bool> {
+        let conn = self.pool.get()?;
+        let removed = conn.execute(
+            "DELETE FROM Wishes WHERE id = (
+                SELECT id FROM Wishes WHERE user_id = ?1 ORDER BY id LIMIT 1 OFFSET ?2
+            )",
+            params![user_id, position.saturating_sub(1)],
+        )?;
+
+        debug!("db removed wish {position} of {user_id}: {removed}");
+
+        Ok(removed > 0)
+    }
+
+    /// Returns the wishes of the user in the order they were added.
+    pub fn get_wishlist(&self, user_id: i64) -> Result<Vec<String>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare("SELECT text FROM Wishes WHERE user_id = ?1 ORDER BY id")?;
+        let wishes = stmt
+            .query_map(params![user_id], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+
+        debug!("db get wishlist of {user_id}: {wishes:?}");
+
+        Ok(wishes)
+    }
+
+    /// Returns whether the user is a member of the chat.
+    pub fn is_member(&self, user_id: i64, group_id: i64) -> Result<bool> {
+        let conn = self.pool.get()?;
+        let mut stmt =
+            conn.prepare("SELECT 1 FROM Members WHERE group_id = ?1 AND user_id = ?2 LIMIT 1")?;
+
+        Ok(stmt.exists(params![group_id, user_id])?)
+    }
+
+    /// Returns whether both users are members of at least one common chat.
+    pub fn share_group(&self, user_id: i64, other_id: i64) -> Result<bool> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT 1 FROM Members AS mine JOIN Members AS theirs USING (group_id)
+            WHERE mine.user_id = ?1 AND theirs.user_id = ?2 LIMIT 1",
+        )?;
+
+        Ok(stmt.exists(params![user_id, other_id])?)
+    }
+}
@@ -0,0 +1,78 @@
+use chrono::{Datelike, Duration, NaiveDate};
+
+static FILTER_DATE_FORMAT: &str = "%Y%m%d";
+
+/// Which events a list shows.
+/// Ranges are inclusive local dates of the chat.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventFilter {
+    All,
+    Week,
+    Month,
+    Range(NaiveDate, NaiveDate),
+}
+
+impl EventFilter {
+    /// Returns the first and the last local date of the filter,
+    /// or `None` when it is not limited.
+    pub fn bounds(&self, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        match *self {
+            EventFilter::All => None,
+            EventFilter::Week => {
+                let days_left = 6 - today.weekday().num_days_from_monday() as i64;
+                Some((today, today + Duration::days(days_left)))
+            }
+            EventFilter::Month => {
+                let next_month = match today.month() {
+                    12 => NaiveDate::from_ymd_opt(today.year() + 1, 1, 1),
+                    month => NaiveDate::from_ymd_opt(today.year(), month + 1, 1),
+                };
+                Some((today, next_month.unwrap() - Duration::days(1)))
+            }
+            EventFilter::Range(from, to) => Some((from, to)),
+        }
+    }
+
+    /// Returns the first and the last local date of the filter looking back,
+    /// e.g. from Monday to `today` for this week, or `None` when it is not limited.
+    pub fn past_bounds(&self, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        match *self {
+            EventFilter::All => None,
+            EventFilter::Week => {
+                let days_past = today.weekday().num_days_from_monday() as i64;
+                Some((today - Duration::days(days_past), today))
+            }
+            EventFilter::Month => Some((today.with_day(1).unwrap(), today)),
+            EventFilter::Range(from, to) => Some((from, to)),
+        }
+    }
+}
+
+/// Encodes `filter` for callback data, e.g. `week` or `20250301-20250331`.
+pub fn event_filter_to_code(filter: &EventFilter) -> String {
+    match filter {
+        EventFilter::All => "all".to_string(),
+        EventFilter::Week => "week".to_string(),
+        EventFilter::Month => "month".to_string(),
+        EventFilter::Range(from, to) => format!(
+            "{}-{}",
+            from.format(FILTER_DATE_FORMAT),
+            to.format(FILTER_DATE_FORMAT)
+        ),
+    }
+}
+
+pub fn event_filter_from_code(code: &str) -> Option<EventFilter> {
+    match code {
+        "all" => Some(EventFilter::All),
+        "week" => Some(EventFilter::Week),
+        "month" => Some(EventFilter::Month),
+        range => {
+            let (from, to) = range.split_once('-')?;
+            Some(EventFilter::Range(
+                NaiveDate::parse_from_str(from, FILTER_DATE_FORMAT).ok()?,
+                NaiveDate::parse_from_str(to, FILTER_DATE_FORMAT).ok()?,
+            ))
+        }
+    }
+}
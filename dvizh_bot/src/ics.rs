@@ -0,0 +1,484 @@
+use crate::db::db_objects::Event;
+use crate::recurrence::Recurrence;
+use crate::tg::event_utils::occurrence_key;
+use crate::time_utils::{local_to_utc, parse_timezone, timezone_or_default};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Utc, Weekday};
+use chrono_tz::Tz;
+
+static ICS_DATETIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+static UID_DOMAIN: &str = "dvizh_bot";
+/// Lines longer than this many octets are folded as required by RFC 5545.
+static MAX_LINE_OCTETS: usize = 75;
+/// How far ahead the last occurrence of an `RRULE` with a `COUNT` is looked for.
+static MAX_COUNT_DAYS: i64 = 5 * 366;
+
+/// Builds an iCalendar document with one VEVENT per event.
+/// Occurrences of recurring events are exported as separate events.
+pub fn events_to_ics(events: &[Event]) -> String {
+    let now = Utc::now();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//DvizhBot//Events//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+    ];
+
+    for event in events {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", event_uid(event)));
+        lines.push(format!("DTSTAMP:{}", format_ics_datetime(&now)));
+        lines.push(format!("DTSTART:{}", format_ics_datetime(&event.start)));
+        if let Some(end) = event.end {
+            lines.push(format!("DTEND:{}", format_ics_datetime(&end)));
+        }
+        lines.push(format!("SUMMARY:{}", escape_ics_text(&event.title)));
+        if !event.location.is_empty() {
+            lines.push(format!("LOCATION:{}", escape_ics_text(&event.location)));
+        }
+        if !event.description.is_empty() {
+            lines.push(format!(
+                "DESCRIPTION:{}",
+                escape_ics_text(&event.description)
+            ));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold_ics_line(line))
+        .collect::<Vec<String>>()
+        .join("\r\n")
+        + "\r\n"
+}
+
+/// Returns a UID that stays the same across exports of the event,
+/// occurrences of recurring events add their local date.
+/// Imported events keep the UID of the file they came from.
+pub fn event_uid(event: &Event) -> String {
+    match (occurrence_key(event).as_str(), &event.uid) {
+        ("", Some(uid)) => uid.clone(),
+        ("", None) => format!("event-{}@{UID_DOMAIN}", event.id),
+        (date, _) => format!("event-{}-{date}@{UID_DOMAIN}", event.id),
+    }
+}
+
+/// Returns the event id of a UID exported by `event_uid` for a single event.
+pub fn parse_own_uid(uid: &str) -> Option<i64> {
+    own_uid_id(uid)?.parse::<i64>().ok()
+}
+
+fn own_uid_id(uid: &str) -> Option<&str> {
+    uid.strip_prefix("event-")?
+        .strip_suffix(&format!("@{UID_DOMAIN}"))
+}
+
+/// A VEVENT read from an iCalendar file.
+#[derive(Debug, Clone)]
+pub struct ImportedEvent {
+    pub uid: String,
+    pub event: Event,
+}
+
+/// A VEVENT that could not be imported.
+/// `entry` names the VEVENT by its summary, UID or position in the file.
+#[derive(Debug, Clone)]
+pub struct ImportError {
+    pub entry: String,
+    pub error_key: String,
+}
+
+/// Parses the VEVENTs of an iCalendar document into events of the chat `group_id`.
+/// Times without a timezone are read in `default_tz`.
+/// Later VEVENTs replace earlier ones with the same UID.
+pub fn parse_ics(
+    content: &str,
+    group_id: i64,
+    default_tz: &str,
+) -> Vec<Result<ImportedEvent, ImportError>> {
+    let unfolded = content
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut entries: Vec<Result<ImportedEvent, ImportError>> = Vec::new();
+    let mut properties: Option<Vec<IcsProperty>> = None;
+    let mut nested = 0;
+    let mut position = 0;
+    for line in unfolded.lines() {
+        let property = match parse_ics_property(line) {
+            Some(property) => property,
+            None => continue,
+        };
+        match (property.name.as_str(), property.value.as_str()) {
+            ("BEGIN", "VEVENT") => properties = Some(Vec::new()),
+            ("END", "VEVENT") => {
+                if let Some(properties) = properties.take() {
+                    position += 1;
+                    let entry = parse_vevent(&properties, group_id, default_tz, position);
+                    if let Ok(imported) = &entry {
+                        entries.retain(
+                            |other| !matches!(other, Ok(other) if other.uid == imported.uid),
+                        );
+                    }
+                    entries.push(entry);
+                }
+            }
+            // Alarms and other components inside a VEVENT are not imported
+            ("BEGIN", _) if properties.is_some() => nested += 1,
+            ("END", _) if properties.is_some() => nested -= 1,
+            _ => {
+                if let (Some(properties), 0) = (properties.as_mut(), nested) {
+                    properties.push(property);
+                }
+            }
+        }
+    }
+
+    entries
+}
+
+#[derive(Debug, Clone)]
+struct IcsProperty {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl IcsProperty {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Parses a content line such as `DTSTART;TZID=Europe/Warsaw:20250321T190000`.
+fn parse_ics_property(line: &str) -> Option<IcsProperty> {
+    let mut in_quotes = false;
+    let colon = line.char_indices().find_map(|(index, c)| match c {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(index),
+        _ => None,
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+
+    let mut parts = head.split(';');
+    let name = parts.next()?.trim().to_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+
+    Some(IcsProperty {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+fn parse_vevent(
+    properties: &[IcsProperty],
+    group_id: i64,
+    default_tz: &str,
+    position: usize,
+) -> Result<ImportedEvent, ImportError> {
+    let find = |name: &str| properties.iter().find(|property| property.name == name);
+    let text = |name: &str| {
+        find(name)
+            .map(|property| unescape_ics_text(&property.value))
+            .unwrap_or_default()
+    };
+
+    let title = text("SUMMARY");
+    let uid = text("UID");
+    let entry = match (title.is_empty(), uid.is_empty()) {
+        (false, _) => title.clone(),
+        (true, false) => uid.clone(),
+        (true, true) => format!("#{position}"),
+    };
+    let error = |error_key: &str| ImportError {
+        entry: entry.clone(),
+        error_key: error_key.to_string(),
+    };
+
+    if uid.is_empty() {
+        return Err(error("error_ics_missing_uid"));
+    }
+    if title.is_empty() {
+        return Err(error("error_ics_missing_title"));
+    }
+    // Changes of single occurrences of a series, and occurrences exported by the bot
+    if find("RECURRENCE-ID").is_some()
+        || (own_uid_id(&uid).is_some() && parse_own_uid(&uid).is_none())
+    {
+        return Err(error("error_ics_unsupported_recurrence"));
+    }
+
+    let start = find("DTSTART")
+        .ok_or_else(|| error("error_ics_invalid_date"))
+        .and_then(|property| parse_ics_datetime(property, default_tz).map_err(|key| error(&key)))?;
+    let end = match find("DTEND") {
+        Some(property) => {
+            Some(parse_ics_datetime(property, default_tz).map_err(|key| error(&key))?)
+        }
+        None => None,
+    };
+
+    // A one-day all-day event ends when the next day starts, which is not worth showing
+    let end = end
+        .filter(|end| !(start.all_day && end.time - start.time <= Duration::days(1)))
+        .map(|end| end.time)
+        .filter(|end| *end > start.time);
+
+    let mut event = Event::new(
+        group_id,
+        title,
+        start.time,
+        end,
+        start.timezone.name().to_string(),
+        text("LOCATION"),
+        text("DESCRIPTION"),
+    );
+    event.uid = Some(uid.clone());
+
+    if let Some(rule) = find("RRULE") {
+        let first = start.time.with_timezone(&start.timezone).date_naive();
+        let (recurrence, until) = parse_ics_rrule(&rule.value, first).map_err(|key| error(&key))?;
+
+        let mut skipped_dates = Vec::new();
+        for property in properties
+            .iter()
+            .filter(|property| property.name == "EXDATE")
+        {
+            for value in property.value.split(',') {
+                let exdate = IcsProperty {
+                    value: value.to_string(),
+                    ..property.clone()
+                };
+                let date = parse_ics_datetime(&exdate, default_tz).map_err(|key| error(&key))?;
+                skipped_dates.push(date.time.with_timezone(&start.timezone).date_naive());
+            }
+        }
+
+        event = event.with_recurrence(recurrence, until, skipped_dates);
+    }
+
+    Ok(ImportedEvent { uid, event })
+}
+
+struct IcsDateTime {
+    time: DateTime<Utc>,
+    timezone: Tz,
+    all_day: bool,
+}
+
+/// Reads a `DATE` or `DATE-TIME` value in UTC, in its `TZID` or in `default_tz`.
+fn parse_ics_datetime(property: &IcsProperty, default_tz: &str) -> Result<IcsDateTime, String> {
+    let invalid = || "error_ics_invalid_date".to_string();
+    let timezone = match property.param("TZID") {
+        Some(tzid) => {
+            parse_timezone(tzid).ok_or_else(|| "error_ics_unknown_timezone".to_string())?
+        }
+        None => timezone_or_default(default_tz),
+    };
+    let value = property.value.trim();
+
+    if property.param("VALUE") == Some("DATE") || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| invalid())?;
+        let time =
+            local_to_utc(&timezone, date.and_hms_opt(0, 0, 0).unwrap()).ok_or_else(invalid)?;
+        return Ok(IcsDateTime {
+            time,
+            timezone,
+            all_day: true,
+        });
+    }
+
+    let time = match value.strip_suffix('Z') {
+        Some(value) => NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .map_err(|_| invalid())?
+            .and_utc(),
+        None => {
+            let local =
+                NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+            local_to_utc(&timezone, local).ok_or_else(invalid)?
+        }
+    };
+
+    Ok(IcsDateTime {
+        time,
+        timezone,
+        all_day: false,
+    })
+}
+
+/// Maps an `RRULE` onto the recurrences events support:
+/// daily with an interval, weekly on one day and monthly on the nth weekday.
+/// `COUNT` is turned into the date of the last occurrence.
+fn parse_ics_rrule(
+    value: &str,
+    first: NaiveDate,
+) -> Result<(Recurrence, Option<NaiveDate>), String> {
+    let unsupported = || "error_ics_unsupported_recurrence".to_string();
+    let parts = value
+        .split(';')
+        .filter_map(|part| part.split_once('='))
+        .map(|(key, value)| (key.to_uppercase(), value.to_uppercase()))
+        .collect::<Vec<(String, String)>>();
+    let get = |key: &str| {
+        parts
+            .iter()
+            .find(|(part, _)| part == key)
+            .map(|(_, value)| value.as_str())
+    };
+
+    let interval = match get("INTERVAL") {
+        Some(interval) => interval.parse::<u32>().map_err(|_| unsupported())?,
+        None => 1,
+    };
+    if interval == 0 {
+        return Err(unsupported());
+    }
+    let by_day = get("BYDAY");
+
+    let recurrence = match (get("FREQ"), by_day) {
+        (Some("DAILY"), None) => Recurrence::EveryDays(interval),
+        (Some("WEEKLY"), by_day) => {
+            let weekday = match by_day {
+                Some(day) => parse_ics_weekday(day).ok_or_else(unsupported)?,
+                None => first.weekday(),
+            };
+            match interval {
+                1 => Recurrence::Weekly(weekday),
+                _ if weekday == first.weekday() => {
+                    Recurrence::EveryDays(interval.checked_mul(7).ok_or_else(unsupported)?)
+                }
+                _ => return Err(unsupported()),
+            }
+        }
+        (Some("MONTHLY"), Some(day)) if interval == 1 => {
+            let (nth, weekday) = day.split_at(day.len().saturating_sub(2));
+            Recurrence::Monthly {
+                nth: nth.parse::<i8>().map_err(|_| unsupported())?,
+                weekday: parse_ics_weekday(weekday).ok_or_else(unsupported)?,
+            }
+        }
+        _ => return Err(unsupported()),
+    };
+    if let Recurrence::Monthly { nth, .. } = recurrence {
+        if !(nth == -1 || (1..=5).contains(&nth)) {
+            return Err(unsupported());
+        }
+    }
+
+    let until = match (get("UNTIL"), get("COUNT")) {
+        (Some(until), _) => Some(
+            NaiveDate::parse_from_str(until.get(..8).unwrap_or_default(), "%Y%m%d")
+                .map_err(|_| unsupported())?,
+        ),
+        (None, Some(count)) => {
+            let count = count.parse::<usize>().map_err(|_| unsupported())?;
+            let last = (0..MAX_COUNT_DAYS)
+                .map(|days| first + Duration::days(days))
+                .filter(|date| recurrence.matches(first, *date))
+                .nth(count.saturating_sub(1))
+                .ok_or_else(unsupported)?;
+            Some(last)
+        }
+        (None, None) => None,
+    };
+
+    Ok((recurrence, until))
+}
+
+fn parse_ics_weekday(day: &str) -> Option<Weekday> {
+    match day {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn format_ics_datetime(date_time: &DateTime<Utc>) -> String {
+    date_time.format(ICS_DATETIME_FORMAT).to_string()
+}
+
+fn unescape_ics_text(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('n') | Some('N') => unescaped.push('\n'),
+                Some(next) => unescaped.push(next),
+                None => {}
+            },
+            (c, false) => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+fn escape_ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Splits `line` into lines of at most `MAX_LINE_OCTETS` octets,
+/// continuation lines start with a space.
+fn fold_ics_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Monday 03.03.2025.
+    fn first() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, 3).unwrap()
+    }
+
+    #[test]
+    fn rrule_zero_interval_is_unsupported() {
+        assert!(parse_ics_rrule("FREQ=DAILY;INTERVAL=0;COUNT=3", first()).is_err());
+        assert!(parse_ics_rrule("FREQ=WEEKLY;INTERVAL=0;BYDAY=MO", first()).is_err());
+    }
+
+    #[test]
+    fn rrule_huge_weekly_interval_is_unsupported() {
+        assert!(parse_ics_rrule("FREQ=WEEKLY;INTERVAL=4294967295;BYDAY=MO", first()).is_err());
+    }
+
+    #[test]
+    fn rrule_weekly_interval_becomes_days() {
+        let (recurrence, until) = parse_ics_rrule("FREQ=WEEKLY;INTERVAL=2;COUNT=2", first()).unwrap();
+        assert!(matches!(recurrence, Recurrence::EveryDays(14)));
+        assert_eq!(until, NaiveDate::from_ymd_opt(2025, 3, 17));
+    }
+}
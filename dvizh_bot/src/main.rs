@@ -1,33 +1,63 @@
 mod args;
 mod bot_config;
 mod tg {
+    pub mod birthday_list;
+    pub mod birthday_reminders;
     pub mod callback_queries;
     pub mod command_utils;
     pub mod commands;
+    pub mod csv_import;
+    pub mod date_picker;
+    pub mod date_polls;
+    pub mod digest;
+    pub mod event_cards;
+    pub mod event_import;
+    pub mod event_list;
+    pub mod event_media;
+    pub mod event_proposals;
+    pub mod event_utils;
     pub mod events;
     pub mod language_utils;
     pub mod message_handler;
     pub mod messaging;
     pub mod msg_request;
     pub mod msg_type_utils;
+    pub mod pending_input;
     pub mod tg_bot;
     pub mod tg_objects;
     pub mod tg_utils;
+    pub mod user_args;
+    pub mod wishlists;
 }
 mod db {
+    pub mod attendance;
     pub mod chats;
+    pub mod date_polls;
     pub mod db_objects;
+    pub mod digests;
     pub mod events;
+    pub mod migrations;
+    pub mod reminders;
     pub mod repository;
+    pub mod settings;
     pub mod users;
+    pub mod wishlists;
 }
 mod application;
+mod birthdays;
+mod chat_settings;
 mod errors;
+mod event_filter;
 mod translations {
     pub mod language_cache;
     pub mod translation_value;
 }
+mod csv;
+mod ics;
+mod natural_dates;
+mod recurrence;
 mod spam;
+mod time_utils;
 mod validations;
 
 use anyhow::Result;
@@ -0,0 +1,539 @@
+use crate::time_utils::parse_timezone;
+use crate::validations::parse_date;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Weekday};
+
+/// Date with an optional start and end time read from a phrase.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NaturalDateTime {
+    pub date: NaiveDate,
+    pub start: Option<NaiveTime>,
+    pub end: Option<NaiveTime>,
+}
+
+static RELATIVE_DAYS: &[(&str, i64)] = &[
+    ("today", 0),
+    ("tonight", 0),
+    ("tomorrow", 1),
+    ("сегодня", 0),
+    ("завтра", 1),
+    ("послезавтра", 2),
+    ("dziś", 0),
+    ("dzisiaj", 0),
+    ("jutro", 1),
+    ("pojutrze", 2),
+];
+
+static WEEKDAYS: &[(&str, Weekday)] = &[
+    ("monday", Weekday::Mon),
+    ("mon", Weekday::Mon),
+    ("tuesday", Weekday::Tue),
+    ("tue", Weekday::Tue),
+    ("wednesday", Weekday::Wed),
+    ("wed", Weekday::Wed),
+    ("thursday", Weekday::Thu),
+    ("thu", Weekday::Thu),
+    ("friday", Weekday::Fri),
+    ("fri", Weekday::Fri),
+    ("saturday", Weekday::Sat),
+    ("sat", Weekday::Sat),
+    ("sunday", Weekday::Sun),
+    ("sun", Weekday::Sun),
+    ("понедельник", Weekday::Mon),
+    ("вторник", Weekday::Tue),
+    ("среда", Weekday::Wed),
+    ("среду", Weekday::Wed),
+    ("четверг", Weekday::Thu),
+    ("пятница", Weekday::Fri),
+    ("пятницу", Weekday::Fri),
+    ("суббота", Weekday::Sat),
+    ("субботу", Weekday::Sat),
+    ("воскресенье", Weekday::Sun),
+    ("poniedziałek", Weekday::Mon),
+    ("wtorek", Weekday::Tue),
+    ("środa", Weekday::Wed),
+    ("środę", Weekday::Wed),
+    ("czwartek", Weekday::Thu),
+    ("piątek", Weekday::Fri),
+    ("sobota", Weekday::Sat),
+    ("sobotę", Weekday::Sat),
+    ("niedziela", Weekday::Sun),
+    ("niedzielę", Weekday::Sun),
+];
+
+/// Month names as written after the day, in Russian and Polish in the genitive.
+static MONTHS: &[(&str, u32)] = &[
+    ("january", 1),
+    ("jan", 1),
+    ("february", 2),
+    ("feb", 2),
+    ("march", 3),
+    ("mar", 3),
+    ("april", 4),
+    ("apr", 4),
+    ("may", 5),
+    ("june", 6),
+    ("jun", 6),
+    ("july", 7),
+    ("jul", 7),
+    ("august", 8),
+    ("aug", 8),
+    ("september", 9),
+    ("sep", 9),
+    ("october", 10),
+    ("oct", 10),
+    ("november", 11),
+    ("nov", 11),
+    ("december", 12),
+    ("dec", 12),
+    ("января", 1),
+    ("февраля", 2),
+    ("марта", 3),
+    ("апреля", 4),
+    ("мая", 5),
+    ("июня", 6),
+    ("июля", 7),
+    ("августа", 8),
+    ("сентября", 9),
+    ("октября", 10),
+    ("ноября", 11),
+    ("декабря", 12),
+    ("stycznia", 1),
+    ("lutego", 2),
+    ("marca", 3),
+    ("kwietnia", 4),
+    ("maja", 5),
+    ("czerwca", 6),
+    ("lipca", 7),
+    ("sierpnia", 8),
+    ("września", 9),
+    ("października", 10),
+    ("listopada", 11),
+    ("grudnia", 12),
+];
+
+/// `next friday` is the Friday of the next week.
+static NEXT_WORDS: &[&str] = &[
+    "next",
+    "следующий",
+    "следующую",
+    "следующее",
+    "следующая",
+    "przyszły",
+    "przyszłą",
+    "przyszłe",
+    "następny",
+    "następną",
+    "następne",
+];
+
+/// Words before a day or a time, a number right after them is an hour.
+static PREPOSITIONS: &[&str] = &["on", "at", "this", "в", "во", "на", "w", "we", "o", "po"];
+
+/// Start of `in 3 days`, `через неделю` or `za 2 tygodnie`.
+static IN_WORDS: &[&str] = &["in", "через", "za"];
+static DAY_WORDS: &[&str] = &["day", "days", "день", "дня", "дней", "dzień", "dni"];
+static WEEK_WORDS: &[&str] = &[
+    "week",
+    "weeks",
+    "неделю",
+    "недели",
+    "недель",
+    "tydzień",
+    "tygodnie",
+    "tygodni",
+];
+
+static AM_WORDS: &[&str] = &["am", "утра", "ночи", "rano"];
+/// `дня` is also a plural of day, so `2 дня` is only an hour after `в`.
+static PM_WORDS: &[&str] = &["pm", "вечера", "дня", "wieczorem", "południu"];
+static NOON_WORDS: &[&str] = &["noon", "midday", "полдень", "południe"];
+
+fn lookup<T: Copy>(table: &[(&str, T)], word: &str) -> Option<T> {
+    table
+        .iter()
+        .find(|(name, _)| *name == word)
+        .map(|(_, value)| *value)
+}
+
+fn words(value: &str) -> Vec<String> {
+    value
+        .split_whitespace()
+        .map(|word| word.trim_matches(',').to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Whether `value` is a phrase such as `tomorrow 19:00` rather than a date in an exact format.
+/// A timezone name at the end does not count.
+pub fn is_natural_date(value: &str) -> bool {
+    value
+        .split_whitespace()
+        .filter(|word| parse_timezone(word).is_none())
+        .any(|word| word.chars().any(char::is_alphabetic))
+}
+
+/// Reads a day and month written with the month name, e.g. `5 march 1990`, `march 5`,
+/// `5 марта` or `5 marca 1990`. Returns the day, the month and the year if given.
+pub fn parse_natural_day_month(value: &str) -> Option<(u32, u32, Option<i32>)> {
+    let words = words(value);
+    let (day, month, rest) = match words.as_slice() {
+        [day, month, rest @ ..] if lookup(MONTHS, month).is_some() => (day, month, rest),
+        [month, day, rest @ ..] if lookup(MONTHS, month).is_some() => (day, month, rest),
+        _ => return None,
+    };
+    let day = day.parse::<u32>().ok()?;
+    let month = lookup(MONTHS, month)?;
+    match rest {
+        [] => Some((day, month, None)),
+        [year] if year.len() == 4 => Some((day, month, Some(year.parse::<i32>().ok()?))),
+        _ => None,
+    }
+}
+
+/// Reads `H[:MM][am|pm]` such as `19:00`, `7` or `7:30pm`.
+fn parse_clock(word: &str) -> Option<NaiveTime> {
+    let (word, shift) = match (word.strip_suffix("pm"), word.strip_suffix("am")) {
+        (Some(word), _) => (word, Some(true)),
+        (_, Some(word)) => (word, Some(false)),
+        _ => (word, None),
+    };
+    let (hour, minute) = word.split_once(':').unwrap_or((word, "0"));
+    let hour = hour.parse::<u32>().ok()?;
+    let minute = minute.parse::<u32>().ok()?;
+    let hour = match shift {
+        Some(pm) => shift_hour(hour, pm)?,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// Converts a 12-hour clock hour to 24 hours.
+fn shift_hour(hour: u32, pm: bool) -> Option<u32> {
+    match (hour, pm) {
+        (1..=11, true) => Some(hour + 12),
+        (12, false) => Some(0),
+        (1..=12, _) => Some(hour),
+        _ => None,
+    }
+}
+
+fn year_at(words: &[String], index: usize) -> Option<i32> {
+    words
+        .get(index)
+        .filter(|year| year.len() == 4)
+        .and_then(|year| year.parse::<i32>().ok())
+}
+
+/// Returns the date of `day` and `month`, by default their next occurrence from `today`.
+fn day_month_date(today: NaiveDate, day: &str, month: u32, year: Option<i32>) -> Option<NaiveDate> {
+    let day = day.parse::<u32>().ok()?;
+    if let Some(year) = year {
+        return NaiveDate::from_ymd_opt(year, month, day);
+    }
+    match NaiveDate::from_ymd_opt(today.year(), month, day).filter(|date| *date >= today) {
+        Some(date) => Some(date),
+        None => NaiveDate::from_ymd_opt(today.year() + 1, month, day),
+    }
+}
+
+/// Returns `weekday` on or after `today`, or in the week after the current one for `next`.
+fn weekday_date(today: NaiveDate, weekday: Weekday, next: bool) -> NaiveDate {
+    if next {
+        let next_monday = today + Duration::days(7 - today.weekday().num_days_from_monday() as i64);
+        return next_monday + Duration::days(weekday.num_days_from_monday() as i64);
+    }
+    let days = (7 + weekday.num_days_from_monday() as i64
+        - today.weekday().num_days_from_monday() as i64)
+        % 7;
+    today + Duration::days(days)
+}
+
+/// Reads a date and time phrase in English, Russian or Polish relative to the local `now`,
+/// e.g. `tomorrow 19:00`, `next friday`, `завтра в 7 вечера`, `w sobotę o 18`,
+/// `in 3 days at 7pm` or `5 march 18:00-22:00`.
+/// The date defaults to today and a day and month without the year to their next occurrence.
+/// A weekday named on that day is today, unless the time has passed.
+/// Returns `None` when a word is not understood.
+pub fn parse_natural_datetime(value: &str, now: NaiveDateTime) -> Option<NaturalDateTime> {
+    let today = now.date();
+    let words = words(value);
+    let mut date = None;
+    let mut start = None;
+    let mut end = None;
+    let mut next = false;
+    let mut bare_weekday = false;
+
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i].as_str();
+        let following = words.get(i + 1).map(|word| word.as_str());
+
+        if let Some(days) = lookup(RELATIVE_DAYS, word) {
+            date = Some(today + Duration::days(days));
+            bare_weekday = false;
+        } else if word == "day" && following == Some("after") {
+            // `day after tomorrow`
+            if words.get(i + 2).map(|word| word.as_str()) != Some("tomorrow") {
+                return None;
+            }
+            date = Some(today + Duration::days(2));
+            bare_weekday = false;
+            i += 2;
+        } else if let Some(weekday) = lookup(WEEKDAYS, word) {
+            date = Some(weekday_date(today, weekday, next));
+            bare_weekday = !next;
+        } else if NEXT_WORDS.contains(&word) {
+            next = true;
+        } else if IN_WORDS.contains(&word) {
+            // The count is optional, `через неделю` is in one week
+            let (count, unit) = match following.and_then(|count| count.parse::<i64>().ok()) {
+                Some(count) => (count, words.get(i + 2)),
+                None if following == Some("a") => (1, words.get(i + 2)),
+                None => (1, words.get(i + 1)),
+            };
+            let unit = unit?.as_str();
+            let days = if DAY_WORDS.contains(&unit) {
+                count
+            } else if WEEK_WORDS.contains(&unit) {
+                count.checked_mul(7)?
+            } else {
+                return None;
+            };
+            date = Some(today.checked_add_signed(Duration::try_days(days)?)?);
+            bare_weekday = false;
+            i += match following {
+                Some(count) if count == "a" || count.parse::<i64>().is_ok() => 2,
+                _ => 1,
+            };
+        } else if NOON_WORDS.contains(&word) {
+            start = NaiveTime::from_hms_opt(12, 0, 0);
+        } else if let Some(month) = following.and_then(|month| lookup(MONTHS, month)) {
+            // `5 march`, optionally followed by the year
+            let year = year_at(&words, i + 2);
+            date = Some(day_month_date(today, word, month, year)?);
+            bare_weekday = false;
+            i += if year.is_some() { 2 } else { 1 };
+        } else if let Some(month) = lookup(MONTHS, word) {
+            // `march 5`
+            let year = year_at(&words, i + 2);
+            date = Some(day_month_date(today, following?, month, year)?);
+            bare_weekday = false;
+            i += if year.is_some() { 2 } else { 1 };
+        } else if let Ok(exact) = parse_date(word) {
+            date = Some(exact);
+            bare_weekday = false;
+        } else if PREPOSITIONS.contains(&word) {
+            // A plain number after `at`, `в` or `o` is an hour
+            if let Some(hour) = following.filter(|hour| hour.parse::<u32>().is_ok()) {
+                start = Some(parse_clock(hour)?);
+                i += 1;
+            }
+        } else if AM_WORDS.contains(&word) || PM_WORDS.contains(&word) {
+            let time = start?;
+            let hour = shift_hour(time.hour12().1, PM_WORDS.contains(&word))?;
+            start = NaiveTime::from_hms_opt(hour, time.minute(), 0);
+        } else if let Some((from, to)) = word.split_once('-') {
+            start = Some(parse_clock(from)?);
+            end = Some(parse_clock(to)?);
+        } else if word.contains(':') || word.ends_with("am") || word.ends_with("pm") {
+            start = Some(parse_clock(word)?);
+        } else if word.parse::<u32>().is_ok()
+            && following.is_some_and(|word| {
+                (AM_WORDS.contains(&word) || PM_WORDS.contains(&word)) && !DAY_WORDS.contains(&word)
+            })
+        {
+            // `7 вечера` without a preposition
+            start = Some(parse_clock(word)?);
+        } else {
+            return None;
+        }
+        i += 1;
+    }
+
+    // A phrase naming only a time, or nothing at all, is not a date
+    if date.is_none() && start.is_none() {
+        return None;
+    }
+    // `friday 10:00` said on a Friday afternoon is the next Friday
+    if bare_weekday && date == Some(today) && start.is_some_and(|start| start <= now.time()) {
+        date = Some(today + Duration::days(7));
+    }
+    Some(NaturalDateTime {
+        date: date.unwrap_or(today),
+        start,
+        end,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Friday 21.03.2025 at 15:00.
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 3, 21)
+            .unwrap()
+            .and_hms_opt(15, 0, 0)
+            .unwrap()
+    }
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, month, day).unwrap()
+    }
+
+    fn time(hour: u32, minute: u32) -> Option<NaiveTime> {
+        NaiveTime::from_hms_opt(hour, minute, 0)
+    }
+
+    fn parse(value: &str) -> Option<(NaiveDate, Option<NaiveTime>, Option<NaiveTime>)> {
+        parse_natural_datetime(value, now())
+            .map(|natural| (natural.date, natural.start, natural.end))
+    }
+
+    #[test]
+    fn english_phrases() {
+        assert_eq!(
+            parse("tomorrow 19:00"),
+            Some((date(3, 22), time(19, 0), None))
+        );
+        assert_eq!(parse("today at 18"), Some((date(3, 21), time(18, 0), None)));
+        assert_eq!(parse("tonight 9pm"), Some((date(3, 21), time(21, 0), None)));
+        assert_eq!(parse("day after tomorrow"), Some((date(3, 23), None, None)));
+        assert_eq!(parse("next friday"), Some((date(3, 28), None, None)));
+        assert_eq!(
+            parse("on saturday at 7:30pm"),
+            Some((date(3, 22), time(19, 30), None))
+        );
+        assert_eq!(
+            parse("in 3 days at 7pm"),
+            Some((date(3, 24), time(19, 0), None))
+        );
+        assert_eq!(parse("in a week"), Some((date(3, 28), None, None)));
+        assert_eq!(
+            parse("tomorrow noon"),
+            Some((date(3, 22), time(12, 0), None))
+        );
+        assert_eq!(
+            parse("march 28 12am"),
+            Some((date(3, 28), time(0, 0), None))
+        );
+    }
+
+    #[test]
+    fn russian_phrases() {
+        assert_eq!(
+            parse("завтра в 7 вечера"),
+            Some((date(3, 22), time(19, 0), None))
+        );
+        assert_eq!(
+            parse("послезавтра в 10 утра"),
+            Some((date(3, 23), time(10, 0), None))
+        );
+        assert_eq!(
+            parse("в субботу в 12"),
+            Some((date(3, 22), time(12, 0), None))
+        );
+        assert_eq!(
+            parse("в следующую пятницу"),
+            Some((date(3, 28), None, None))
+        );
+        assert_eq!(parse("через неделю"), Some((date(3, 28), None, None)));
+        assert_eq!(
+            parse("через 2 дня в 2 дня"),
+            Some((date(3, 23), time(14, 0), None))
+        );
+        assert_eq!(
+            parse("сегодня в полдень"),
+            Some((date(3, 21), time(12, 0), None))
+        );
+    }
+
+    #[test]
+    fn polish_phrases() {
+        assert_eq!(
+            parse("w sobotę o 18"),
+            Some((date(3, 22), time(18, 0), None))
+        );
+        assert_eq!(
+            parse("jutro o 19:30"),
+            Some((date(3, 22), time(19, 30), None))
+        );
+        assert_eq!(
+            parse("pojutrze o 9 rano"),
+            Some((date(3, 23), time(9, 0), None))
+        );
+        assert_eq!(parse("w przyszłą środę"), Some((date(3, 26), None, None)));
+        assert_eq!(parse("za 2 tygodnie"), Some((date(4, 4), None, None)));
+        assert_eq!(
+            parse("dziś o 7 wieczorem"),
+            Some((date(3, 21), time(19, 0), None))
+        );
+    }
+
+    #[test]
+    fn day_and_month() {
+        // March 5 has passed, so it is next year
+        let next_year = NaiveDate::from_ymd_opt(2026, 3, 5).unwrap();
+        assert_eq!(
+            parse("5 march 18:00-22:00"),
+            Some((next_year, time(18, 0), time(22, 0)))
+        );
+        assert_eq!(parse("5 апреля"), Some((date(4, 5), None, None)));
+        assert_eq!(
+            parse("5 kwietnia 2025 o 18"),
+            Some((date(4, 5), time(18, 0), None))
+        );
+        assert_eq!(
+            parse_natural_day_month("5 march 1990"),
+            Some((5, 3, Some(1990)))
+        );
+        assert_eq!(parse_natural_day_month("march 5"), Some((5, 3, None)));
+        assert_eq!(parse_natural_day_month("5 марта"), Some((5, 3, None)));
+        assert_eq!(
+            parse_natural_day_month("5 marca 1990"),
+            Some((5, 3, Some(1990)))
+        );
+        assert_eq!(parse_natural_day_month("05.03.1990"), None);
+    }
+
+    #[test]
+    fn weekday_of_today() {
+        // Said on a Friday at 15:00
+        assert_eq!(
+            parse("friday 18:00"),
+            Some((date(3, 21), time(18, 0), None))
+        );
+        assert_eq!(
+            parse("friday 10:00"),
+            Some((date(3, 28), time(10, 0), None))
+        );
+        assert_eq!(
+            parse("в пятницу в 10 утра"),
+            Some((date(3, 28), time(10, 0), None))
+        );
+        assert_eq!(parse("friday"), Some((date(3, 21), None, None)));
+        assert_eq!(parse("today 10:00"), Some((date(3, 21), time(10, 0), None)));
+    }
+
+    #[test]
+    fn ambiguous_and_unknown_words() {
+        // `дня` after a bare number may be days as well as the afternoon
+        assert_eq!(parse("2 дня"), None);
+        assert_eq!(parse("в 2 дня"), Some((date(3, 21), time(14, 0), None)));
+        assert_eq!(parse("через 2 дня"), Some((date(3, 23), None, None)));
+        assert_eq!(parse("7 вечера"), Some((date(3, 21), time(19, 0), None)));
+        assert_eq!(parse("someday"), None);
+        assert_eq!(parse("at"), None);
+        assert_eq!(parse("tomorrow 25:00"), None);
+        assert_eq!(parse("через 100000000 дней"), None);
+        assert_eq!(parse("in 99999999999999 weeks"), None);
+    }
+
+    #[test]
+    fn natural_or_exact() {
+        assert!(is_natural_date("tomorrow 19:00"));
+        assert!(is_natural_date("завтра Europe/Warsaw"));
+        assert!(!is_natural_date("21.03.2025 19:00 Europe/Warsaw"));
+        assert!(!is_natural_date("2025-03-21"));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::db::db_objects::Event;
+use crate::time_utils::{local_to_utc, timezone_or_default, DATE_FORMAT};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How often a recurring event repeats.
+/// Stored in the database as `weekly:fri`, `monthly:2:fri`, `monthly:-1:fri` or `days:3`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Recurrence {
+    Weekly(Weekday),
+    /// The `nth` weekday of every month, `-1` stands for the last one.
+    Monthly {
+        nth: i8,
+        weekday: Weekday,
+    },
+    EveryDays(u32),
+}
+
+impl Recurrence {
+    /// Returns whether the series that started on `first` has an occurrence on `date`.
+    pub fn matches(&self, first: NaiveDate, date: NaiveDate) -> bool {
+        if date < first {
+            return false;
+        }
+        match *self {
+            Recurrence::Weekly(weekday) => date.weekday() == weekday,
+            Recurrence::Monthly { nth, weekday } => {
+                if date.weekday() != weekday {
+                    return false;
+                }
+                if nth < 0 {
+                    (date + Duration::days(7)).month() != date.month()
+                } else {
+                    (date.day() as i8 - 1) / 7 + 1 == nth
+                }
+            }
+            Recurrence::EveryDays(days) => (date - first).num_days() % days as i64 == 0,
+        }
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Recurrence::Weekly(weekday) => write!(f, "weekly:{weekday}"),
+            Recurrence::Monthly { nth, weekday } => write!(f, "monthly:{nth}:{weekday}"),
+            Recurrence::EveryDays(days) => write!(f, "days:{days}"),
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("Invalid recurrence: {s}");
+        let parts = s.split(':').collect::<Vec<&str>>();
+        match parts.as_slice() {
+            ["weekly", weekday] => Ok(Recurrence::Weekly(weekday.parse().map_err(|_| error())?)),
+            ["monthly", nth, weekday] => Ok(Recurrence::Monthly {
+                nth: nth.parse().map_err(|_| error())?,
+                weekday: weekday.parse().map_err(|_| error())?,
+            }),
+            // `matches` divides by the number of days
+            ["days", days] => Ok(Recurrence::EveryDays(
+                days.parse()
+                    .ok()
+                    .filter(|days| *days > 0)
+                    .ok_or_else(error)?,
+            )),
+            _ => Err(error()),
+        }
+    }
+}
+
+/// Expands `event` into its occurrences starting on the local dates `[from, to)`.
+/// A single event is returned as is when it starts in that range.
+pub fn expand_event(event: &Event, from: NaiveDate, to: NaiveDate) -> Vec<Event> {
+    let tz = timezone_or_default(&event.timezone);
+    let first = event.start.with_timezone(&tz);
+
+    let recurrence = match event.recurrence {
+        Some(recurrence) => recurrence,
+        None => {
+            let date = first.date_naive();
+            return if date >= from && date < to {
+                vec![event.clone()]
+            } else {
+                Vec::new()
+            };
+        }
+    };
+
+    let duration = event.end.map(|end| end - event.start);
+    let mut occurrences = Vec::new();
+    let mut date = from.max(first.date_naive());
+    let last = match event.recurrence_until {
+        Some(until) => to.min(until + Duration::days(1)),
+        None => to,
+    };
+
+    while date < last {
+        if recurrence.matches(first.date_naive(), date) && !event.skipped_dates.contains(&date) {
+            if let Some(start) = local_to_utc(&tz, date.and_time(first.time())) {
+                let mut occurrence = event.clone();
+                occurrence.start = start;
+                occurrence.end = duration.map(|duration| start + duration);
+                occurrences.push(occurrence);
+            }
+        }
+        date += Duration::days(1);
+    }
+
+    occurrences
+}
+
+/// Formats a recurrence the way `validate_recurrence` reads it,
+/// e.g. `monthly last fri until 31.12.2025 skip 26.12.2025`.
+pub fn format_recurrence_rule(
+    recurrence: &Recurrence,
+    until: Option<NaiveDate>,
+    skipped_dates: &[NaiveDate],
+) -> String {
+    let weekday = |weekday: &Weekday| weekday.to_string().to_lowercase();
+    let mut rule = match recurrence {
+        Recurrence::Weekly(day) => format!("weekly {}", weekday(day)),
+        Recurrence::Monthly {
+            nth: -1,
+            weekday: day,
+        } => format!("monthly last {}", weekday(day)),
+        Recurrence::Monthly { nth, weekday: day } => format!("monthly {nth} {}", weekday(day)),
+        Recurrence::EveryDays(days) => format!("every {days} days"),
+    };
+    if let Some(until) = until {
+        rule = format!("{rule} until {}", until.format(DATE_FORMAT));
+    }
+    if !skipped_dates.is_empty() {
+        let dates = skipped_dates
+            .iter()
+            .map(|date| date.format(DATE_FORMAT).to_string())
+            .collect::<Vec<String>>();
+        rule = format!("{rule} skip {}", dates.join(","));
+    }
+    rule
+}
@@ -0,0 +1,164 @@
+use crate::application::Application;
+use crate::birthdays::{age_on, next_birthday, BIRTHDAY_FORMAT};
+use crate::chat_settings::{is_enabled, ChatSetting};
+use crate::db::db_objects::User;
+use crate::tg::language_utils::chat_translation;
+use crate::tg::messaging::{send_msg, send_request};
+use crate::tg::msg_request::MsgRequest;
+use crate::tg::msg_type_utils::{msg_type_to_str, MsgType};
+use crate::time_utils::{timezone_or_default, today_in};
+use crate::validations::{validate_birthday_count, validation_error_to_key};
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use log::debug;
+use std::collections::HashMap;
+
+/// Number of birthdays listed when `/birthdays` is called without a count.
+static DEFAULT_BIRTHDAY_COUNT: usize = 10;
+
+/// Lists the next birthdays of the chat members, `args` may hold how many.
+pub async fn handle_birthdays_command(
+    args: Vec<String>,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    debug!("Birthdays command was called with {args:?}");
+    let count = match args.first().map(|arg| validate_birthday_count(arg)) {
+        Some(Ok(count)) => count,
+        None => DEFAULT_BIRTHDAY_COUNT,
+        Some(Err(error)) => {
+            let text = req
+                .get_translation_for(validation_error_to_key(&error))
+                .await?;
+            req.set_msg_text(&text.expect_text()?);
+            return send_msg(offset, req).await;
+        }
+    };
+
+    let chat_id = req.get_msg().chat.id;
+    let timezone = req.get_dvizh_repo().await.get_chat_timezone(chat_id)?;
+    let today = today_in(&timezone_or_default(&timezone));
+    let birthdays = upcoming_birthdays(&req.app, chat_id, today).await?;
+
+    if birthdays.is_empty() {
+        let text = req.get_translation_for("no_birthdays").await?;
+        req.set_msg_text(&text.expect_text()?);
+        return send_msg(offset, req).await;
+    }
+
+    let mut lines = vec![chat_translation(&req.app, chat_id, "upcoming_birthdays").await?];
+    for (date, user) in birthdays.iter().take(count) {
+        let days = (*date - today).num_days();
+        let when = match days {
+            0 => chat_translation(&req.app, chat_id, "birthday_today").await?,
+            _ => chat_translation(&req.app, chat_id, "birthday_in_days")
+                .await?
+                .replace("{days}", &days.to_string()),
+        };
+        let line = format_birthday(&req.app, chat_id, *date, user).await?;
+        lines.push(format!("{line} ({when})"));
+    }
+    req.set_msg_text(&lines.join("\n"));
+    send_msg(offset, req).await
+}
+
+/// Posts the birthdays of the month in the chats having `monthly_birthdays` on.
+/// Called on the first day of the month, months without birthdays are skipped.
+pub async fn perform_monthly_birthdays(app: &Application, today: NaiveDate) -> Result<()> {
+    let chats = app.dvizh_repo.lock().await.get_all_chat_ids()?;
+    for chat_id in chats {
+        let setting = app
+            .dvizh_repo
+            .lock()
+            .await
+            .get_chat_setting(chat_id, &ChatSetting::MonthlyBirthdays)?;
+        if !is_enabled(&setting) {
+            continue;
+        }
+
+        let birthdays = upcoming_birthdays(app, chat_id, today)
+            .await?
+            .into_iter()
+            .filter(|(date, _)| date.month() == today.month())
+            .collect::<Vec<(NaiveDate, User)>>();
+        if birthdays.is_empty() {
+            continue;
+        }
+
+        let months = app
+            .language_cache
+            .write()
+            .await
+            .get_translation_for_chat(&app.dvizh_repo, chat_id, "month_names")
+            .await?
+            .expect_array()?;
+        let header = chat_translation(app, chat_id, "birthdays_of_month")
+            .await?
+            .replace("{month}", &months[today.month0() as usize]);
+        let mut lines = vec![header];
+        for (date, user) in &birthdays {
+            lines.push(format_birthday(app, chat_id, *date, user).await?);
+        }
+
+        let mut params = HashMap::new();
+        params.insert("chat_id", chat_id.to_string());
+        params.insert("text", lines.join("\n"));
+        send_request(
+            &app.client,
+            &app.tg_token,
+            msg_type_to_str(&MsgType::SendMessage),
+            params,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Returns the members of the chat with the day of their next birthday, soonest first.
+pub async fn upcoming_birthdays(
+    app: &Application,
+    chat_id: i64,
+    today: NaiveDate,
+) -> Result<Vec<(NaiveDate, User)>> {
+    let (users, leap_birthday) = {
+        let dvizh_repo = app.dvizh_repo.lock().await;
+        (
+            dvizh_repo.get_chat_birthdays(chat_id)?,
+            dvizh_repo.get_chat_setting(chat_id, &ChatSetting::LeapBirthday)?,
+        )
+    };
+
+    let mut birthdays = users
+        .into_iter()
+        .filter_map(|user| {
+            let date = next_birthday(user.birthdate.as_deref()?, today, &leap_birthday)?;
+            Some((date, user))
+        })
+        .collect::<Vec<(NaiveDate, User)>>();
+    birthdays.sort_by_key(|(date, _)| *date);
+    Ok(birthdays)
+}
+
+/// Formats the birthday as `• DD.MM Name, turns N`, the age only when the user shows it.
+async fn format_birthday(
+    app: &Application,
+    chat_id: i64,
+    date: NaiveDate,
+    user: &User,
+) -> Result<String> {
+    let line = format!("• {} {}", date.format(BIRTHDAY_FORMAT), user.display_name());
+    let age = user
+        .birthdate
+        .as_deref()
+        .filter(|_| user.show_age)
+        .and_then(|birthdate| age_on(birthdate, date));
+    match age {
+        Some(age) => Ok(format!(
+            "{line}, {}",
+            chat_translation(app, chat_id, "birthday_turns")
+                .await?
+                .replace("{age}", &age.to_string())
+        )),
+        None => Ok(line),
+    }
+}
@@ -0,0 +1,225 @@
+use crate::application::Application;
+use crate::birthdays::{celebrated_birthdays, BIRTHDAY_FORMAT};
+use crate::chat_settings::ChatSetting;
+use crate::db::db_objects::Event;
+use crate::tg::event_utils::fill_event_template;
+use crate::tg::language_utils::chat_translation;
+use crate::tg::messaging::{edit_msg_and_remove_keyboard, send_msg, send_text};
+use crate::tg::msg_request::MsgRequest;
+use crate::tg::tg_utils::get_chat_administrator_ids;
+use crate::tg::wishlists::format_wishlist;
+use crate::time_utils::{local_to_utc, timezone_or_default};
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, NaiveTime};
+use log::{debug, error};
+use serde_json::json;
+
+/// Gift planning events start at this local hour on the day before the birthday.
+static GIFT_EVENT_HOUR: u32 = 18;
+
+/// Tells the admins of chats with `birthday_reminder` set privately about the birthdays
+/// coming in that many days, with a button creating a gift planning event.
+/// The member having the birthday is not told, even when an admin.
+pub async fn perform_birthday_reminders(app: &Application, today: NaiveDate) -> Result<()> {
+    let chats = app.dvizh_repo.lock().await.get_all_chat_ids()?;
+    for chat_id in chats {
+        if let Err(e) = send_birthday_reminders(app, chat_id, today).await {
+            error!("Failed to send birthday reminders of {chat_id}: {e}");
+        }
+    }
+    Ok(())
+}
+
+async fn send_birthday_reminders(app: &Application, chat_id: i64, today: NaiveDate) -> Result<()> {
+    let (setting, leap_birthday, title) = {
+        let dvizh_repo = app.dvizh_repo.lock().await;
+        (
+            dvizh_repo.get_chat_setting(chat_id, &ChatSetting::BirthdayReminder)?,
+            dvizh_repo.get_chat_setting(chat_id, &ChatSetting::LeapBirthday)?,
+            dvizh_repo.get_chat_title(chat_id)?,
+        )
+    };
+    // `off` is not a number of days
+    let days = match setting.parse::<i64>() {
+        Ok(days) => days,
+        Err(_) => return Ok(()),
+    };
+
+    let birthday = today + Duration::days(days);
+    let mut users = Vec::new();
+    for key in celebrated_birthdays(birthday, &leap_birthday) {
+        users.extend(
+            app.dvizh_repo
+                .lock()
+                .await
+                .get_users_by_birthday(chat_id, &key)?,
+        );
+    }
+    if users.is_empty() {
+        return Ok(());
+    }
+
+    let admin_ids = get_chat_administrator_ids(&app.client, &app.tg_token, chat_id).await?;
+    let button = chat_translation(app, chat_id, "button_plan_gift").await?;
+    for user in users {
+        let mut text = chat_translation(app, chat_id, "birthday_heads_up")
+            .await?
+            .replace("{name}", &user.display_name())
+            .replace("{chat}", &title)
+            .replace("{date}", &birthday.format(BIRTHDAY_FORMAT).to_string())
+            .replace("{days}", &days.to_string());
+        // Admins share the chat with the member, so they may see the wishlist
+        let wishes = app.dvizh_repo.lock().await.get_wishlist(user.id)?;
+        if !wishes.is_empty() {
+            let header = chat_translation(app, chat_id, "birthday_heads_up_wishlist").await?;
+            text = format!("{text}\n\n{header}\n{}", format_wishlist(&wishes));
+        }
+        let keyboard = json!({
+            "inline_keyboard": [[{
+                "text": button,
+                "callback_data": format!(
+                    "bdgift_{chat_id}_{}_{}",
+                    user.id,
+                    birthday.format("%Y%m%d")
+                )
+            }]]
+        })
+        .to_string();
+
+        // Admins who never started a private chat with the bot are skipped
+        for admin_id in admin_ids.iter().filter(|admin_id| **admin_id != user.id) {
+            if let Err(e) = send_text(app, *admin_id, None, &text, Some(&keyboard)).await {
+                error!("Failed to send birthday reminder to {admin_id}: {e}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Creates the gift planning event of a birthday, once for all admins.
+/// Callback data has the form `bdgift_{chat id}_{user id}_{YYYYMMDD}`.
+pub async fn handle_birthday_gift_callback(
+    callback_query: &serde_json::Value,
+    callback_data: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let admin_id = callback_query["from"]["id"].as_i64().unwrap_or_default();
+    let parts = callback_data.split('_').collect::<Vec<&str>>();
+    let group_id = parts
+        .get(1)
+        .and_then(|id| id.parse::<i64>().ok())
+        .unwrap_or_default();
+    let user_id = parts
+        .get(2)
+        .and_then(|id| id.parse::<i64>().ok())
+        .unwrap_or_default();
+    let birthday = match parts
+        .get(3)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+    {
+        Some(birthday) => birthday,
+        None => {
+            debug!("Invalid birthday gift callback {callback_data}");
+            return Ok(());
+        }
+    };
+
+    if req
+        .get_dvizh_repo()
+        .await
+        .is_not_admin(admin_id, group_id)?
+    {
+        let text = req.get_translation_for("error_not_admin").await?;
+        req.set_msg_text(&text.expect_text()?);
+        send_msg(offset, req).await?;
+        return Ok(());
+    }
+
+    // The uid lets the other admins find the event instead of creating another one
+    let uid = gift_event_uid(user_id, birthday);
+    let existing = req
+        .get_dvizh_repo()
+        .await
+        .find_event_id_by_uid(group_id, &uid)?;
+    let event = match existing {
+        Some(event_id) => req.get_dvizh_repo().await.get_event(event_id)?,
+        None => {
+            Some(create_gift_event(&req.app, group_id, user_id, birthday, uid, admin_id).await?)
+        }
+    };
+    let event = match event {
+        Some(event) => event,
+        None => {
+            let text = req.get_translation_for("error_event_not_found").await?;
+            req.set_msg_text(&text.expect_text()?);
+            edit_msg_and_remove_keyboard(offset, req).await?;
+            return Ok(());
+        }
+    };
+
+    let header = req.get_translation_for("gift_event_created").await?;
+    let template = chat_translation(&req.app, group_id, "event_template").await?;
+    req.set_msg_text(&format!(
+        "{}\n\n{}",
+        header.expect_text()?,
+        fill_event_template(&template, &event)
+    ));
+    edit_msg_and_remove_keyboard(offset, req).await?;
+    Ok(())
+}
+
+async fn create_gift_event(
+    app: &Application,
+    group_id: i64,
+    user_id: i64,
+    birthday: NaiveDate,
+    uid: String,
+    admin_id: i64,
+) -> Result<Event> {
+    let (user, timezone) = {
+        let dvizh_repo = app.dvizh_repo.lock().await;
+        (
+            dvizh_repo.get_user(user_id)?,
+            dvizh_repo.get_chat_timezone(group_id)?,
+        )
+    };
+    let name = user.map(|user| user.display_name()).unwrap_or_default();
+    let tz = timezone_or_default(&timezone);
+    let time = NaiveTime::from_hms_opt(GIFT_EVENT_HOUR, 0, 0).unwrap();
+    let day_before = birthday - Duration::days(1);
+    let start = local_to_utc(&tz, day_before.and_time(time))
+        .unwrap_or_else(|| day_before.and_time(time).and_utc());
+
+    let mut event = Event::new(
+        group_id,
+        chat_translation(app, group_id, "gift_event_title")
+            .await?
+            .replace("{name}", &name),
+        start,
+        None,
+        tz.name().to_string(),
+        String::new(),
+        chat_translation(app, group_id, "gift_event_description")
+            .await?
+            .replace("{name}", &name),
+    );
+    event.created_by = Some(admin_id);
+    event.uid = Some(uid);
+    // The member having the birthday must not see the event
+    event.admins_only = true;
+    event.id = app.dvizh_repo.lock().await.add_event(event.clone())?;
+    Ok(event)
+}
+
+fn gift_event_uid(user_id: i64, birthday: NaiveDate) -> String {
+    format!("birthday-{user_id}-{}", birthday.format("%Y%m%d"))
+}
+
+/// Returns the member a gift planning event is for, read from its uid.
+pub fn gift_event_user_id(event: &Event) -> Option<i64> {
+    let uid = event.uid.as_deref()?.strip_prefix("birthday-")?;
+    // Placeholder ids of users not seen yet are negative
+    let (user_id, _) = uid.rsplit_once('-')?;
+    user_id.parse().ok()
+}
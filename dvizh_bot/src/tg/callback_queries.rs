@@ -1,9 +1,39 @@
+use crate::db::db_objects::{
+    rsvp_status_from_str, rsvp_status_to_str, Attendance, Event, RsvpStatus, RSVP_STATUSES,
+};
+use crate::event_filter::event_filter_from_code;
+use crate::ics::events_to_ics;
+use crate::tg::birthday_reminders::handle_birthday_gift_callback;
+use crate::tg::csv_import::handle_csv_import_callback;
+use crate::tg::date_picker::{date_picker_keyboard, handle_date_picker_callback, DatePickerTarget};
+use crate::tg::date_polls::handle_date_poll_callback;
+use crate::tg::event_cards::build_event_card;
+use crate::tg::event_import::handle_import_callback;
+use crate::tg::event_list::{
+    append_keyboard_rows, build_event_list_details, build_event_list_page, list_keyboard_rows,
+    EventList,
+};
+use crate::tg::event_media::{handle_media_callback, send_event_card};
+use crate::tg::event_proposals::handle_proposal_callback;
+use crate::tg::event_utils::{
+    event_field_from_str, event_field_to_str, format_event_date, occurrence_key,
+    parse_occurrence_date, EventField,
+};
+use crate::tg::events::notify_waitlist_promotions;
 use crate::tg::language_utils::translate_text;
-use crate::tg::messaging::{edit_msg_and_remove_keyboard, remove_keyboard};
+use crate::tg::messaging::{
+    edit_keyboard_msg, edit_msg_and_remove_keyboard, remove_keyboard, send_document_msg,
+    send_document_request, send_msg, send_request,
+};
 use crate::tg::msg_request::MsgRequest;
+use crate::tg::msg_type_utils::{msg_type_to_str, MsgType};
+use crate::tg::pending_input::PendingInput;
 use crate::tg::tg_utils::get_horoscope;
+use crate::time_utils::DATE_FORMAT;
 use anyhow::Result;
 use log::debug;
+use serde_json::json;
+use std::collections::HashMap;
 
 pub async fn handle_callback_query(
     callback_query: &serde_json::Value,
@@ -60,6 +90,484 @@ pub async fn handle_callback_query(
         }
         req.set_msg_text(&message);
         edit_msg_and_remove_keyboard(offset, req).await?;
+    } else if callback_data.starts_with("event_") {
+        handle_event_callback(callback_query, callback_data, offset, req).await?;
+    } else if callback_data.starts_with("rsvp_") {
+        handle_rsvp_callback(callback_query, callback_data, offset, req).await?;
+    } else if callback_data.starts_with("attendees_") {
+        handle_attendees_callback(callback_query, callback_data, offset, req).await?;
+    } else if callback_data.starts_with("ics_") {
+        handle_ics_callback(callback_query, callback_data, offset, req).await?;
+    } else if callback_data.starts_with("list_") || callback_data.starts_with("listshow_") {
+        handle_list_callback(callback_data, offset, req).await?;
+    } else if callback_data.starts_with("proposal_") {
+        handle_proposal_callback(callback_query, callback_data, offset, req).await?;
+    } else if callback_data.starts_with("poster_") || callback_data.starts_with("venue_") {
+        handle_media_callback(callback_data, offset, req).await?;
+    } else if callback_data.starts_with("datepoll_") {
+        handle_date_poll_callback(callback_query, callback_data, offset, req).await?;
+    } else if callback_data.starts_with("import_") {
+        handle_import_callback(callback_query, callback_data, offset, req).await?;
+    } else if callback_data.starts_with("csvimport_") {
+        handle_csv_import_callback(callback_query, callback_data, offset, req).await?;
+    } else if callback_data.starts_with("bdgift_") {
+        handle_birthday_gift_callback(callback_query, callback_data, offset, req).await?;
+    } else if callback_data.starts_with("cal_") {
+        handle_date_picker_callback(callback_query, callback_data, offset, req).await?;
+    }
+    Ok(())
+}
+
+/// Handles the buttons of the event editor.
+/// Callback data has the form `event_{action}[_{event id}[_{field}]]`.
+async fn handle_event_callback(
+    callback_query: &serde_json::Value,
+    callback_data: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let chat_id = req.get_msg().chat.id;
+    let user_id = callback_query["from"]["id"].as_i64().unwrap_or_default();
+
+    let parts = callback_data.splitn(4, '_').collect::<Vec<&str>>();
+    let action = parts.get(1).copied().unwrap_or_default();
+
+    if action == "cancel" || action == "done" {
+        req.clear_pending_input(chat_id, user_id).await;
+        if action == "cancel" {
+            let text = req.get_translation_for("cancelled").await?;
+            req.set_msg_text(&text.expect_text()?);
+            edit_msg_and_remove_keyboard(offset, req).await?;
+        } else {
+            remove_keyboard(offset, req).await?;
+        }
+        return Ok(());
+    }
+
+    // Proposals sent to admins privately are edited from the private chat
+    let event_id = parts
+        .get(2)
+        .and_then(|id| id.parse::<i64>().ok())
+        .unwrap_or_default();
+    let event = req.get_dvizh_repo().await.get_event(event_id)?;
+    let event = match event {
+        Some(event) if event.group_id == chat_id || chat_id == user_id => event,
+        _ => {
+            let text = req.get_translation_for("error_event_not_found").await?;
+            req.set_msg_text(&text.expect_text()?);
+            edit_msg_and_remove_keyboard(offset, req).await?;
+            return Ok(());
+        }
+    };
+
+    if req
+        .get_dvizh_repo()
+        .await
+        .is_not_admin(user_id, event.group_id)?
+    {
+        let text = req.get_translation_for("error_not_admin").await?;
+        req.set_msg_text(&text.expect_text()?);
+        send_msg(offset, req).await?;
+        return Ok(());
+    }
+
+    match action {
+        "edit" => {
+            let mut buttons = Vec::new();
+            for field in [
+                EventField::Title,
+                EventField::Date,
+                EventField::Location,
+                EventField::Description,
+                EventField::Reminders,
+                EventField::Capacity,
+                EventField::Poster,
+                EventField::Venue,
+            ] {
+                let field = event_field_to_str(&field);
+                let label = req
+                    .get_translation_for(&format!("button_{field}"))
+                    .await?
+                    .expect_text()?;
+                buttons.push(vec![json!({
+                    "text": label,
+                    "callback_data": format!("event_field_{}_{field}", event.id)
+                })]);
+            }
+            let cancel = req
+                .get_translation_for("button_cancel")
+                .await?
+                .expect_text()?;
+            // Cancelling the edit of a proposal goes back to its decision buttons
+            let cancel_data = match event.pending {
+                true => format!("proposal_show_{}", event.id),
+                false => "event_cancel".to_string(),
+            };
+            buttons.push(vec![
+                json!({ "text": cancel, "callback_data": cancel_data }),
+            ]);
+            let keyboard = json!({ "inline_keyboard": buttons }).to_string();
+
+            let text = req.get_translation_for("choose_event_field").await?;
+            req.set_msg_text(&format!("{} {}", text.expect_text()?, event.title));
+            edit_keyboard_msg(&keyboard, offset, req).await?;
+        }
+        "field" => {
+            if let Some(field) = parts.get(3).and_then(|field| event_field_from_str(field)) {
+                req.set_pending_input(
+                    chat_id,
+                    user_id,
+                    PendingInput::EditEventField {
+                        event_id: event.id,
+                        field,
+                        // The prompt replaces the editor message
+                        prompt_id: req.get_msg().message_id,
+                    },
+                )
+                .await;
+                // Dates can also be picked from a calendar
+                if field == EventField::Date {
+                    let keyboard =
+                        date_picker_keyboard(DatePickerTarget::EventDate(event.id), req).await?;
+                    let text = req.get_translation_for("enter_event_date").await?;
+                    req.set_msg_text(&text.expect_text()?);
+                    edit_keyboard_msg(&keyboard, offset, req).await?;
+                    return Ok(());
+                }
+                let prompt_key = match field {
+                    EventField::Reminders => "enter_event_reminders",
+                    EventField::Capacity => "enter_event_capacity",
+                    EventField::Poster => "enter_event_poster",
+                    EventField::Venue => "enter_event_venue",
+                    _ => "enter_event_value",
+                };
+                let text = req.get_translation_for(prompt_key).await?;
+                req.set_msg_text(&text.expect_text()?);
+                edit_msg_and_remove_keyboard(offset, req).await?;
+            }
+        }
+        "delete" => {
+            let delete = req
+                .get_translation_for("button_delete")
+                .await?
+                .expect_text()?;
+            let cancel = req
+                .get_translation_for("button_cancel")
+                .await?
+                .expect_text()?;
+            let occurrence = parts.get(3).copied().and_then(parse_occurrence_date);
+
+            let mut buttons = Vec::new();
+            if let (Some(date), Some(_)) = (occurrence, event.recurrence) {
+                let skip = req
+                    .get_translation_for("button_skip_occurrence")
+                    .await?
+                    .expect_text()?;
+                buttons.push(vec![json!({
+                    "text": format!("{skip} {}", date.format(DATE_FORMAT)),
+                    "callback_data": format!("event_skip_{}_{}", event.id, parts[3])
+                })]);
+            }
+            buttons.push(vec![
+                json!({ "text": delete, "callback_data": format!("event_confirmdelete_{}", event.id) }),
+                json!({ "text": cancel, "callback_data": "event_cancel" }),
+            ]);
+            let keyboard = json!({ "inline_keyboard": buttons }).to_string();
+
+            let text = req.get_translation_for("confirm_event_delete").await?;
+            req.set_msg_text(&format!("{} {}", text.expect_text()?, event.title));
+            edit_keyboard_msg(&keyboard, offset, req).await?;
+        }
+        "skip" => {
+            if let Some(date) = parts.get(3).copied().and_then(parse_occurrence_date) {
+                req.get_dvizh_repo()
+                    .await
+                    .skip_event_occurrence(event.id, date)?;
+                let text = req
+                    .get_translation_for("event_occurrence_cancelled")
+                    .await?;
+                req.set_msg_text(&format!(
+                    "{} {} ({})",
+                    text.expect_text()?,
+                    event.title,
+                    date.format(DATE_FORMAT)
+                ));
+                edit_msg_and_remove_keyboard(offset, req).await?;
+            }
+        }
+        "confirmdelete" => {
+            req.get_dvizh_repo().await.delete_event(event.id)?;
+            let text = req.get_translation_for("event_deleted").await?;
+            req.set_msg_text(&format!("{} {}", text.expect_text()?, event.title));
+            edit_msg_and_remove_keyboard(offset, req).await?;
+        }
+        "notify" => {
+            remove_keyboard(offset, req).await?;
+            req.method = MsgType::SendMessage;
+            let header = req
+                .get_translation_for("event_changed")
+                .await?
+                .expect_text()?;
+            let (card, keyboard) = build_event_card(&req.app, &event).await?;
+            let message = format!("{header}\n\n{card}");
+            send_event_card(&req.app, chat_id, &event, &message, Some(&keyboard)).await?;
+        }
+        _ => debug!("Unknown event callback {callback_data}"),
+    }
+
+    Ok(())
+}
+
+/// Looks up the event occurrence of an RSVP card.
+/// `id` and `date` are the `{event id}[_{YYYYMMDD}]` part of the callback data.
+async fn get_card_event(
+    id: Option<&str>,
+    date: Option<&str>,
+    req: &MsgRequest,
+) -> Result<Option<Event>> {
+    let chat_id = req.get_msg().chat.id;
+    let event_id = id.and_then(|id| id.parse::<i64>().ok()).unwrap_or_default();
+    let event = req
+        .get_dvizh_repo()
+        .await
+        .get_event_occurrence(event_id, date.and_then(parse_occurrence_date))?;
+    Ok(event.filter(|event| event.group_id == chat_id))
+}
+
+/// Stores the RSVP of the user and updates the event card.
+/// Going to a full event puts the user on its waitlist.
+/// Callback data has the form `rsvp_{status}_{event id}[_{YYYYMMDD}]`.
+async fn handle_rsvp_callback(
+    callback_query: &serde_json::Value,
+    callback_data: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let parts = callback_data.splitn(4, '_').collect::<Vec<&str>>();
+    let status = parts
+        .get(1)
+        .and_then(|status| rsvp_status_from_str(status))
+        .filter(|status| RSVP_STATUSES.contains(status));
+    let status = match status {
+        Some(status) => status,
+        None => {
+            debug!("Unknown RSVP callback {callback_data}");
+            return Ok(());
+        }
+    };
+
+    let event = match get_card_event(parts.get(2).copied(), parts.get(3).copied(), req).await? {
+        Some(event) => event,
+        None => {
+            let text = req.get_translation_for("error_event_not_found").await?;
+            req.set_msg_text(&text.expect_text()?);
+            edit_msg_and_remove_keyboard(offset, req).await?;
+            return Ok(());
+        }
+    };
+
+    let from = &callback_query["from"];
+    let attendance = Attendance::new(
+        event.id,
+        occurrence_key(&event),
+        from["id"].as_i64().unwrap_or_default(),
+        from["username"].as_str().unwrap_or_default().to_string(),
+        from["first_name"].as_str().unwrap_or_default().to_string(),
+        status,
+    );
+    let (_, promoted) = req
+        .get_dvizh_repo()
+        .await
+        .set_attendance(&attendance, event.capacity)?;
+    notify_waitlist_promotions(&req.app, &event, &promoted).await?;
+
+    // Keep the way back to the event list when the card was expanded from it
+    let (text, keyboard) = build_event_card(&req.app, &event).await?;
+    let list_rows = list_keyboard_rows(req.get_msg().reply_markup.as_ref());
+    req.set_msg_text(&text);
+    edit_keyboard_msg(&append_keyboard_rows(&keyboard, list_rows), offset, req).await?;
+    Ok(())
+}
+
+/// Pages through an event list and expands events in place.
+/// Callback data has the form `list_{list}_{filter}_{page}`
+/// or `listshow_{list}_{filter}_{page}_{event id}[_{YYYYMMDD}]`.
+async fn handle_list_callback(
+    callback_data: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let chat_id = req.get_msg().chat.id;
+    let message_id = req.get_msg().message_id;
+    let parts = callback_data.splitn(6, '_').collect::<Vec<&str>>();
+    let list = match parts.get(1).copied() {
+        Some("up") => Some(EventList::Upcoming),
+        Some("past") => Some(EventList::Past),
+        Some("search") => {
+            let searches = req.app.event_searches.read().await;
+            match searches.get(&(chat_id, message_id)) {
+                Some(text) => Some(EventList::Search(text.clone())),
+                None => {
+                    drop(searches);
+                    let text = req.get_translation_for("error_search_expired").await?;
+                    req.set_msg_text(&text.expect_text()?);
+                    edit_msg_and_remove_keyboard(offset, req).await?;
+                    return Ok(());
+                }
+            }
+        }
+        _ => None,
+    };
+    let filter = parts.get(2).and_then(|code| event_filter_from_code(code));
+    let (list, filter) = match (list, filter) {
+        (Some(list), Some(filter)) => (list, filter),
+        _ => {
+            debug!("Unknown event list callback {callback_data}");
+            return Ok(());
+        }
+    };
+    let page = parts
+        .get(3)
+        .and_then(|page| page.parse::<usize>().ok())
+        .unwrap_or(1);
+
+    let event = match parts[0] {
+        "listshow" => get_card_event(parts.get(4).copied(), parts.get(5).copied(), req).await?,
+        _ => None,
+    };
+    let (text, keyboard) = match event {
+        Some(event) => build_event_list_details(&req.app, &event, &list, &filter, page).await?,
+        None => build_event_list_page(&req.app, chat_id, &list, &filter, page).await?,
+    };
+    req.set_msg_text(&text);
+    edit_keyboard_msg(&keyboard, offset, req).await?;
+    Ok(())
+}
+
+/// Sends the attendee list of an event to the organizer or an admin in a private chat.
+/// Callback data has the form `attendees_{event id}[_{YYYYMMDD}]`.
+async fn handle_attendees_callback(
+    callback_query: &serde_json::Value,
+    callback_data: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let chat_id = req.get_msg().chat.id;
+    let user_id = callback_query["from"]["id"].as_i64().unwrap_or_default();
+
+    let parts = callback_data.splitn(3, '_').collect::<Vec<&str>>();
+    let event = match get_card_event(parts.get(1).copied(), parts.get(2).copied(), req).await? {
+        Some(event) => event,
+        None => {
+            let text = req.get_translation_for("error_event_not_found").await?;
+            req.set_msg_text(&text.expect_text()?);
+            send_msg(offset, req).await?;
+            return Ok(());
+        }
+    };
+
+    let is_not_admin = req.get_dvizh_repo().await.is_not_admin(user_id, chat_id)?;
+    if event.created_by != Some(user_id) && is_not_admin {
+        let text = req.get_translation_for("error_not_organizer").await?;
+        req.set_msg_text(&text.expect_text()?);
+        send_msg(offset, req).await?;
+        return Ok(());
+    }
+
+    let attendance = req
+        .get_dvizh_repo()
+        .await
+        .get_attendance(event.id, &occurrence_key(&event))?;
+    let header = req
+        .get_translation_for("attendees_list")
+        .await?
+        .expect_text()?;
+    let mut message = format!("{header} {} ({})", event.title, format_event_date(&event));
+    // The waitlist is listed in the order places are given out
+    for status in RSVP_STATUSES.iter().chain(&[RsvpStatus::Waitlisted]) {
+        let label = req
+            .get_translation_for(&format!("rsvp_{}", rsvp_status_to_str(status)))
+            .await?
+            .expect_text()?;
+        let names = attendance
+            .iter()
+            .filter(|attendance| attendance.status == *status)
+            .map(|attendance| match attendance.username.as_str() {
+                "" => format!("- {}", attendance.first_name),
+                username => format!("- {} (@{username})", attendance.first_name),
+            })
+            .collect::<Vec<String>>();
+        let count = match (status, event.capacity) {
+            (RsvpStatus::Going, Some(capacity)) => format!("{}/{capacity}", names.len()),
+            (RsvpStatus::Waitlisted, None) if names.is_empty() => continue,
+            _ => names.len().to_string(),
+        };
+        message = format!("{message}\n\n{label} ({count}):");
+        if !names.is_empty() {
+            message = format!("{message}\n{}", names.join("\n"));
+        }
+    }
+
+    let mut params = HashMap::new();
+    params.insert("chat_id", user_id.to_string());
+    params.insert("text", message);
+    let response = send_request(
+        &req.app.client,
+        &req.app.tg_token,
+        msg_type_to_str(&MsgType::SendMessage),
+        params,
+    )
+    .await?;
+
+    // Bots can only write to users who have started a private chat with them
+    if !response["ok"].as_bool().unwrap_or_default() {
+        let text = req.get_translation_for("error_start_private_chat").await?;
+        req.set_msg_text(&text.expect_text()?);
+        send_msg(offset, req).await?;
+    }
+    Ok(())
+}
+
+/// Sends a single-event `.ics` file to the user privately, or to the chat
+/// when the user has not started a private chat with the bot.
+/// Callback data has the form `ics_{event id}[_{YYYYMMDD}]`.
+async fn handle_ics_callback(
+    callback_query: &serde_json::Value,
+    callback_data: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let user_id = callback_query["from"]["id"].as_i64().unwrap_or_default();
+
+    let parts = callback_data.splitn(3, '_').collect::<Vec<&str>>();
+    let event = match get_card_event(parts.get(1).copied(), parts.get(2).copied(), req).await? {
+        Some(event) => event,
+        None => {
+            let text = req.get_translation_for("error_event_not_found").await?;
+            req.set_msg_text(&text.expect_text()?);
+            send_msg(offset, req).await?;
+            return Ok(());
+        }
+    };
+
+    let content = events_to_ics(std::slice::from_ref(&event)).into_bytes();
+    let file_name = format!("event-{}.ics", event.id);
+
+    let mut params = HashMap::new();
+    params.insert("chat_id", user_id.to_string());
+    params.insert("caption", event.title.clone());
+    let response = send_document_request(
+        &req.app.client,
+        &req.app.tg_token,
+        params,
+        &file_name,
+        content.clone(),
+    )
+    .await?;
+
+    if !response["ok"].as_bool().unwrap_or_default() {
+        req.set_msg_text(&event.title);
+        send_document_msg(&file_name, content, offset, req).await?;
     }
     Ok(())
 }
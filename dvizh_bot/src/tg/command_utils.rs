@@ -5,8 +5,23 @@ pub enum CommandType {
     Help,
     SetBirthdate,
     SetBirthdateFor,
+    ShowAge,
+    Birthdays,
+    Wishlist,
     AddEvent,
+    ProposeEvent,
+    EditEvent,
+    DeleteEvent,
     ListEvents,
+    PastEvents,
+    SearchEvents,
+    DatePoll,
+    Calendar,
+    ImportEvents,
+    Import,
+    Export,
+    SetTimezone,
+    Settings,
     Meme,
     Astro,
     Luck,
@@ -24,8 +39,23 @@ pub fn command_str_to_type(t: &str) -> Option<CommandType> {
         "help" => Some(CommandType::Help),
         "setbirthday" => Some(CommandType::SetBirthdate),
         "setbirthdayfor" => Some(CommandType::SetBirthdateFor),
+        "showage" => Some(CommandType::ShowAge),
+        "birthdays" => Some(CommandType::Birthdays),
+        "wishlist" => Some(CommandType::Wishlist),
         "addevent" => Some(CommandType::AddEvent),
+        "proposeevent" => Some(CommandType::ProposeEvent),
+        "editevent" => Some(CommandType::EditEvent),
+        "deleteevent" => Some(CommandType::DeleteEvent),
         "listevents" => Some(CommandType::ListEvents),
+        "pastevents" => Some(CommandType::PastEvents),
+        "searchevents" => Some(CommandType::SearchEvents),
+        "datepoll" => Some(CommandType::DatePoll),
+        "calendar" => Some(CommandType::Calendar),
+        "importevents" => Some(CommandType::ImportEvents),
+        "import" => Some(CommandType::Import),
+        "export" => Some(CommandType::Export),
+        "settimezone" => Some(CommandType::SetTimezone),
+        "settings" => Some(CommandType::Settings),
         "meme" => Some(CommandType::Meme),
         "astro" => Some(CommandType::Astro),
         "luck" => Some(CommandType::Luck),
@@ -1,16 +1,47 @@
-use crate::db::db_objects::{Chat, Event, User as DbUser};
+use crate::chat_settings::{
+    chat_setting_from_str, chat_setting_to_str, chat_setting_values, validate_chat_setting,
+    ChatSetting, CHAT_SETTINGS,
+};
+use crate::csv::{birthdays_to_csv, events_to_csv, CsvLayout};
+use crate::db::db_objects::{Chat, User as DbUser};
+use crate::event_filter::EventFilter;
+use crate::ics::events_to_ics;
+use crate::natural_dates::is_natural_date;
+use crate::tg::birthday_list::handle_birthdays_command;
 use crate::tg::command_utils::CommandType;
+use crate::tg::csv_import::{handle_csv_import_document, is_csv_document};
+use crate::tg::date_picker::{
+    birthdate_action, date_picker_keyboard, describe_event_date, send_date_confirmation,
+    DatePickerTarget,
+};
+use crate::tg::date_polls::handle_date_poll_command;
+use crate::tg::event_import::{handle_import_document, is_ics_document};
+use crate::tg::event_list::{build_event_list_page, EventList};
+use crate::tg::event_proposals::handle_propose_event_command;
+use crate::tg::event_utils::{event_from_args, events_keyboard};
 use crate::tg::language_utils::translate_text;
 use crate::tg::messaging::{
-    edit_msg, send_keyboard_msg, send_keyboard_reply_msg, send_msg, send_photo_msg, send_reply_msg,
+    edit_msg, send_document_msg, send_document_to_msg, send_keyboard_msg, send_keyboard_reply_msg,
+    send_msg, send_photo_msg, send_reply_msg,
 };
 use crate::tg::msg_request::MsgRequest;
-use crate::validations::{validate_argument_count, validate_date_format};
+use crate::tg::user_args::resolve_user_argument;
+use crate::tg::wishlists::handle_wishlist_command;
+use crate::time_utils::{
+    format_event_datetime, parse_timezone, timezone_or_default, DATE_FORMAT, DEFAULT_TIMEZONE,
+};
+use crate::validations::{
+    validate_argument_count, validate_argument_range, validate_birthdate, validate_event_filter,
+    validate_export_type, validate_search_arguments, validation_error_to_key, EventDateTime,
+    ValidationError,
+};
 use anyhow::Result;
+use chrono::{NaiveDate, Utc};
 use log::debug;
 use rand::prelude::SliceRandom;
 use rand::Rng;
 use serde_json::{json, Value};
+use std::collections::HashSet;
 
 pub async fn handle_command(
     offset: &mut i64,
@@ -22,55 +53,235 @@ pub async fn handle_command(
         Some(CommandType::Start) => handle_start_command(offset, req).await,
         Some(CommandType::Hello) => handle_hello_command(offset, req).await,
         Some(CommandType::Help) => handle_help_command(offset, req).await,
-        Some(CommandType::SetBirthdate) => match validate_argument_count(command_args, 1) {
-            Ok(mut args) => match validate_date_format(&args[0]) {
-                Ok(()) => handle_set_birthdate_command(args.remove(0), offset, req).await,
-                Err(error_key) => {
-                    let text = req.get_translation_for(&error_key).await?;
+        // Without a date the member picks it from a calendar
+        Some(CommandType::SetBirthdate) if command_args.as_ref().is_some_and(Vec::is_empty) => {
+            handle_pick_date_command(DatePickerTarget::Birthdate, "choose_birthdate", offset, req)
+                .await
+        }
+        // `5 марта 1990` may come unquoted as several arguments
+        Some(CommandType::SetBirthdate) => match validate_argument_range(command_args, 1, 3) {
+            Ok(args) => match validate_birthdate(&args.join(" ")) {
+                Ok(date) => handle_set_birthdate_command(&args.join(" "), date, offset, req).await,
+                Err(error) => {
+                    let text = req
+                        .get_translation_for(validation_error_to_key(&error))
+                        .await?;
                     req.set_msg_text(&text.expect_text()?);
                     send_msg(offset, req).await
                 }
             },
-            Err(error_key) => {
-                let text = req.get_translation_for(&error_key).await?;
+            Err(error) => {
+                let text = req
+                    .get_translation_for(validation_error_to_key(&error))
+                    .await?;
                 req.set_msg_text(&text.expect_text()?);
                 send_msg(offset, req).await
             }
         },
-        Some(CommandType::SetBirthdateFor) => match validate_argument_count(command_args, 2) {
-            Ok(mut args) => match validate_date_format(&args[1]) {
-                Ok(()) => {
-                    handle_set_birthdate_for_command(
-                        &args.remove(0),
-                        None,
-                        None,
-                        args.remove(0),
-                        offset,
-                        req,
-                    )
-                    .await
+        // The user is resolved first as a text mention may span several arguments
+        Some(CommandType::SetBirthdateFor) => {
+            match resolve_user_argument(command_args.unwrap_or_default(), req).await? {
+                Some((user, args)) => match validate_argument_range(Some(args), 1, 3) {
+                    Ok(args) => match validate_birthdate(&args.join(" ")) {
+                        Ok(date) => handle_set_birthdate_for_command(user, date, offset, req).await,
+                        Err(error) => {
+                            let text = req
+                                .get_translation_for(validation_error_to_key(&error))
+                                .await?;
+                            req.set_msg_text(&text.expect_text()?);
+                            send_msg(offset, req).await
+                        }
+                    },
+                    Err(error) => {
+                        let text = req
+                            .get_translation_for(validation_error_to_key(&error))
+                            .await?;
+                        req.set_msg_text(&text.expect_text()?);
+                        send_msg(offset, req).await
+                    }
+                },
+                None => {
+                    let text = req.get_translation_for("error_unknown_user").await?;
+                    req.set_msg_text(&text.expect_text()?);
+                    send_msg(offset, req).await
                 }
-                Err(error_key) => {
-                    let text = req.get_translation_for(&error_key).await?;
+            }
+        }
+        Some(CommandType::AddEvent) => match validate_argument_range(command_args, 3, 5) {
+            Ok(args) => handle_add_event_command(args, offset, req).await,
+            Err(error) => {
+                let text = req
+                    .get_translation_for(validation_error_to_key(&error))
+                    .await?;
+                req.set_msg_text(&text.expect_text()?);
+                send_msg(offset, req).await
+            }
+        },
+        Some(CommandType::ProposeEvent) => match validate_argument_range(command_args, 4, 5) {
+            Ok(args) => handle_propose_event_command(args, offset, req).await,
+            Err(error) => {
+                let text = req
+                    .get_translation_for(validation_error_to_key(&error))
+                    .await?;
+                req.set_msg_text(&text.expect_text()?);
+                send_msg(offset, req).await
+            }
+        },
+        Some(CommandType::EditEvent) => {
+            handle_pick_event_command("event_edit", "choose_event_to_edit", offset, req).await
+        }
+        Some(CommandType::DeleteEvent) => {
+            handle_pick_event_command("event_delete", "choose_event_to_delete", offset, req).await
+        }
+        Some(CommandType::ListEvents) => {
+            let filter = validate_argument_range(command_args, 0, 1).and_then(|args| {
+                args.first()
+                    .map_or(Ok(EventFilter::All), |value| validate_event_filter(value))
+            });
+            match filter {
+                Ok(filter) => {
+                    handle_list_events_command(EventList::Upcoming, filter, offset, req).await
+                }
+                Err(error) => {
+                    let text = req
+                        .get_translation_for(validation_error_to_key(&error))
+                        .await?;
                     req.set_msg_text(&text.expect_text()?);
                     send_msg(offset, req).await
                 }
-            },
-            Err(error_key) => {
-                let text = req.get_translation_for(&error_key).await?;
+            }
+        }
+        Some(CommandType::PastEvents) => {
+            let filter = validate_argument_range(command_args, 0, 1).and_then(|args| {
+                args.first()
+                    .map_or(Ok(EventFilter::All), |value| validate_event_filter(value))
+            });
+            match filter {
+                Ok(filter) => {
+                    handle_list_events_command(EventList::Past, filter, offset, req).await
+                }
+                Err(error) => {
+                    let text = req
+                        .get_translation_for(validation_error_to_key(&error))
+                        .await?;
+                    req.set_msg_text(&text.expect_text()?);
+                    send_msg(offset, req).await
+                }
+            }
+        }
+        Some(CommandType::SearchEvents) => match validate_search_arguments(command_args) {
+            Ok((text, filter)) => {
+                handle_list_events_command(EventList::Search(text), filter, offset, req).await
+            }
+            Err(error) => {
+                let text = req
+                    .get_translation_for(validation_error_to_key(&error))
+                    .await?;
                 req.set_msg_text(&text.expect_text()?);
                 send_msg(offset, req).await
             }
         },
-        Some(CommandType::AddEvent) => match validate_argument_count(command_args, 4) {
-            Ok(args) => handle_add_event_command(args, offset, req).await,
-            Err(error_key) => {
-                let text = req.get_translation_for(&error_key).await?;
+        // A title and 2 to 10 dates, the answer limit of Telegram polls
+        Some(CommandType::DatePoll) => match validate_argument_range(command_args, 3, 11) {
+            Ok(args) => handle_date_poll_command(args, offset, req).await,
+            Err(error) => {
+                let text = req
+                    .get_translation_for(validation_error_to_key(&error))
+                    .await?;
+                req.set_msg_text(&text.expect_text()?);
+                send_msg(offset, req).await
+            }
+        },
+        Some(CommandType::Calendar) => handle_calendar_command(offset, req).await,
+        Some(CommandType::ImportEvents) => {
+            let document = req
+                .get_msg()
+                .reply_to_message
+                .as_ref()
+                .and_then(|reply| reply.document.clone())
+                .filter(is_ics_document);
+            match document {
+                Some(document) => handle_import_document(document, offset, req).await,
+                None => {
+                    let text = req.get_translation_for("error_import_no_file").await?;
+                    req.set_msg_text(&text.expect_text()?);
+                    send_msg(offset, req).await
+                }
+            }
+        }
+        Some(CommandType::Import) => {
+            let document = req
+                .get_msg()
+                .reply_to_message
+                .as_ref()
+                .and_then(|reply| reply.document.clone())
+                .filter(is_csv_document);
+            match document {
+                Some(document) => handle_csv_import_document(document, offset, req).await,
+                None => {
+                    let text = req.get_translation_for("error_csv_no_file").await?;
+                    req.set_msg_text(&text.expect_text()?);
+                    send_msg(offset, req).await
+                }
+            }
+        }
+        Some(CommandType::Export) => {
+            let layout = validate_argument_range(command_args, 0, 1)
+                .and_then(|args| validate_export_type(args.first().map(String::as_str)));
+            match layout {
+                Ok(layout) => handle_export_command(layout, offset, req).await,
+                Err(error) => {
+                    let text = req
+                        .get_translation_for(validation_error_to_key(&error))
+                        .await?;
+                    req.set_msg_text(&text.expect_text()?);
+                    send_msg(offset, req).await
+                }
+            }
+        }
+        Some(CommandType::SetTimezone) => match validate_argument_count(command_args, 1) {
+            Ok(args) => handle_set_timezone_command(&args[0], offset, req).await,
+            Err(error) => {
+                let text = req
+                    .get_translation_for(validation_error_to_key(&error))
+                    .await?;
+                req.set_msg_text(&text.expect_text()?);
+                send_msg(offset, req).await
+            }
+        },
+        Some(CommandType::ShowAge) => match validate_argument_count(command_args, 1) {
+            Ok(args) => handle_show_age_command(&args[0], offset, req).await,
+            Err(error) => {
+                let text = req
+                    .get_translation_for(validation_error_to_key(&error))
+                    .await?;
+                req.set_msg_text(&text.expect_text()?);
+                send_msg(offset, req).await
+            }
+        },
+        Some(CommandType::Birthdays) => match validate_argument_range(command_args, 0, 1) {
+            Ok(args) => handle_birthdays_command(args, offset, req).await,
+            Err(error) => {
+                let text = req
+                    .get_translation_for(validation_error_to_key(&error))
+                    .await?;
+                req.set_msg_text(&text.expect_text()?);
+                send_msg(offset, req).await
+            }
+        },
+        Some(CommandType::Wishlist) => {
+            handle_wishlist_command(command_args.unwrap_or_default(), offset, req).await
+        }
+        Some(CommandType::Settings) => match validate_argument_range(command_args, 0, 2) {
+            Ok(args) => handle_settings_command(args, offset, req).await,
+            Err(error) => {
+                let text = req
+                    .get_translation_for(validation_error_to_key(&error))
+                    .await?;
                 req.set_msg_text(&text.expect_text()?);
                 send_msg(offset, req).await
             }
         },
-        Some(CommandType::ListEvents) => handle_list_events_command(offset, req).await,
         Some(CommandType::Meme) => handle_meme_command(offset, req).await,
         Some(CommandType::Astro) => handle_astro_command(offset, req).await,
         Some(CommandType::Luck) => handle_luck_command(offset, req).await,
@@ -78,16 +289,20 @@ pub async fn handle_command(
         Some(CommandType::Joke) => handle_joke_command(offset, req).await,
         Some(CommandType::EightBall) => match validate_argument_count(command_args, 1) {
             Ok(_) => handle_8ball_command(offset, req).await,
-            Err(error_key) => {
-                let text = req.get_translation_for(&error_key).await?;
+            Err(error) => {
+                let text = req
+                    .get_translation_for(validation_error_to_key(&error))
+                    .await?;
                 req.set_msg_text(&text.expect_text()?);
                 send_msg(offset, req).await
             }
         },
         Some(CommandType::Test) => match validate_argument_count(command_args, 1) {
             Ok(args) => handle_test_command(args, offset, req).await,
-            Err(error_key) => {
-                let text = req.get_translation_for(&error_key).await?;
+            Err(error) => {
+                let text = req
+                    .get_translation_for(validation_error_to_key(&error))
+                    .await?;
                 req.set_msg_text(&text.expect_text()?);
                 send_msg(offset, req).await
             }
@@ -108,18 +323,24 @@ pub async fn handle_start_command(
     {
         let dvizh_repo = req.get_dvizh_repo().await;
         let title = chat.title.unwrap_or(chat.first_name.unwrap_or_default());
-        dvizh_repo.add_chat(Chat::new(chat.id, title, "en".to_string()))?;
+        dvizh_repo.add_chat(Chat::new(
+            chat.id,
+            title,
+            "en".to_string(),
+            DEFAULT_TIMEZONE.to_string(),
+        ))?;
         if chat.chat_type == "private" {
             dvizh_repo.add_or_update_user(
                 DbUser::new(
-                    user.username.clone(),
+                    user.id,
+                    user.username,
                     Some(user.first_name),
                     None,
                     user.language_code,
                 ),
                 chat.id,
             )?;
-            dvizh_repo.add_admin(&user.username, chat.id)?;
+            dvizh_repo.add_admin(user.id, chat.id)?;
         }
     }
 
@@ -157,17 +378,38 @@ async fn handle_hello_command(offset: &mut i64, req: &mut MsgRequest) -> Result<
 }
 
 async fn handle_set_birthdate_command(
+    input: &str,
     date: String,
     offset: &mut i64,
     req: &mut MsgRequest,
 ) -> Result<serde_json::Value> {
     debug!("SetBirthdate command was called with {date}");
+    // Dates written with the month name are confirmed before they are saved
+    if is_natural_date(input) {
+        // The calendar opens at the birthday, or today for one without a year
+        let month = NaiveDate::parse_from_str(&date, DATE_FORMAT)
+            .unwrap_or_else(|_| Utc::now().date_naive());
+        return send_date_confirmation(
+            DatePickerTarget::Birthdate,
+            input,
+            &date,
+            &birthdate_action(&date),
+            month,
+            offset,
+            req,
+        )
+        .await;
+    }
+
     let user = req.get_msg().from.clone();
-    handle_set_birthdate_for_command(
-        &user.username,
-        Some(user.first_name),
-        user.language_code,
-        date,
+    save_birthdate(
+        DbUser::new(
+            user.id,
+            user.username,
+            Some(user.first_name),
+            Some(date),
+            user.language_code,
+        ),
         offset,
         req,
     )
@@ -175,59 +417,206 @@ async fn handle_set_birthdate_command(
 }
 
 async fn handle_set_birthdate_for_command(
-    username: &str,
-    first_name: Option<String>,
-    language_code: Option<String>,
+    mut user: DbUser,
     date: String,
     offset: &mut i64,
     req: &mut MsgRequest,
 ) -> Result<serde_json::Value> {
-    debug!("SetBirthdateFor command was called with {date}");
-    let usr = username.replace("@", "");
+    debug!(
+        "SetBirthdateFor command was called for {} with {date}",
+        user.id
+    );
+    user.birthdate = Some(date);
+    save_birthdate(user, offset, req).await
+}
+
+/// Lets the user hide their age from birthday messages, `value` is `on` or `off`.
+async fn handle_show_age_command(
+    value: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    debug!("ShowAge command was called with {value}");
+    let show_age = match value.to_lowercase().as_str() {
+        "on" => true,
+        "off" => false,
+        _ => {
+            let text = req
+                .get_translation_for("error_invalid_setting_value")
+                .await?;
+            req.set_msg_text(&text.expect_text()?);
+            return send_msg(offset, req).await;
+        }
+    };
+
+    let user = req.get_msg().from.clone();
+    let chat_id = req.get_msg().chat.id;
+    {
+        let dvizh_repo = req.get_dvizh_repo().await;
+        dvizh_repo.add_or_update_user(
+            DbUser::new(
+                user.id,
+                user.username,
+                Some(user.first_name),
+                None,
+                user.language_code,
+            ),
+            chat_id,
+        )?;
+        dvizh_repo.set_show_age(user.id, show_age)?;
+    }
+
+    let key = if show_age { "age_shown" } else { "age_hidden" };
+    let text = req.get_translation_for(key).await?;
+    req.set_msg_text(&text.expect_text()?);
+    send_reply_msg(offset, req).await
+}
+
+async fn save_birthdate(
+    user: DbUser,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
     let chat_id = req.get_msg().chat.id;
-    req.get_dvizh_repo().await.add_or_update_user(
-        DbUser::new(usr, first_name, Some(date.to_string()), language_code),
-        chat_id,
-    )?;
+    let date = user.birthdate.clone().unwrap_or_default();
+    req.get_dvizh_repo()
+        .await
+        .add_or_update_user(user, chat_id)?;
     let text = req.get_translation_for("remeber_birthday").await?;
     req.set_msg_text(&format!("{} {}", text.expect_text()?, date));
     send_reply_msg(offset, req).await
 }
 
 async fn handle_add_event_command(
-    args: Vec<String>,
+    mut args: Vec<String>,
     offset: &mut i64,
     req: &mut MsgRequest,
 ) -> Result<serde_json::Value> {
     debug!("AddEvent command was called");
     let chat_id = req.get_msg().chat.id;
-    let user = req.get_msg().from.username.clone();
+    let user_id = req.get_msg().from.id;
 
-    if req.get_dvizh_repo().await.is_not_admin(&user, chat_id)? {
+    if req.get_dvizh_repo().await.is_not_admin(user_id, chat_id)? {
         let text = req.get_translation_for("error_not_admin").await?;
         req.set_msg_text(&text.expect_text()?);
         return send_msg(offset, req).await;
     }
 
-    req.get_dvizh_repo().await.add_or_update_event(Event::new(
-        chat_id,
-        args[0].to_string(),
-        args[1].to_string(),
-        args[2].to_string(),
-        args[3].to_string(),
-    ))?;
+    // `[title] [location] [description]` leave the date to the calendar
+    if args.len() == 3 {
+        args.insert(1, String::new());
+        req.app
+            .event_drafts
+            .write()
+            .await
+            .insert((chat_id, user_id), args);
+        return handle_pick_date_command(
+            DatePickerTarget::AddEvent,
+            "choose_event_date",
+            offset,
+            req,
+        )
+        .await;
+    }
+
+    let timezone = req.get_dvizh_repo().await.get_chat_timezone(chat_id)?;
+    let mut event = match event_from_args(&args, chat_id, &timezone) {
+        Ok(event) => event,
+        Err(error) => {
+            let text = req
+                .get_translation_for(validation_error_to_key(&error))
+                .await?;
+            req.set_msg_text(&text.expect_text()?);
+            return send_msg(offset, req).await;
+        }
+    };
+    event.created_by = Some(user_id);
+
+    // Phrases such as `next friday 19:00` are shown as a date to confirm,
+    // the draft keeps the exact date they were read as
+    if is_natural_date(&args[1]) {
+        let date_time = EventDateTime {
+            start: event.start,
+            end: event.end,
+            timezone: timezone_or_default(&event.timezone),
+        };
+        let exact = format!(
+            "{} {}",
+            format_event_datetime(event.start, event.end, &date_time.timezone),
+            event.timezone
+        );
+        let input = std::mem::replace(&mut args[1], exact);
+        let understood = describe_event_date(&date_time, req).await?;
+        let month = event.start.with_timezone(&date_time.timezone).date_naive();
+        req.app
+            .event_drafts
+            .write()
+            .await
+            .insert((chat_id, user_id), args);
+        return send_date_confirmation(
+            DatePickerTarget::AddEvent,
+            &input,
+            &understood,
+            "s",
+            month,
+            offset,
+            req,
+        )
+        .await;
+    }
+
+    req.get_dvizh_repo().await.add_event(event)?;
     let text = req.get_translation_for("remeber_event").await?;
     req.set_msg_text(&format!("{} {}", text.expect_text()?, args[0]));
     send_msg(offset, req).await
 }
 
+/// Opens the calendar for `target` in reply to the command.
+async fn handle_pick_date_command(
+    target: DatePickerTarget,
+    prompt_key: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    debug!("Pick date command was called for {target:?}");
+    let keyboard = date_picker_keyboard(target, req).await?;
+    let text = req.get_translation_for(prompt_key).await?;
+    req.set_msg_text(&text.expect_text()?);
+    send_keyboard_reply_msg(&keyboard, offset, req).await
+}
+
 async fn handle_list_events_command(
+    list: EventList,
+    filter: EventFilter,
     offset: &mut i64,
     req: &mut MsgRequest,
 ) -> Result<serde_json::Value> {
-    debug!("ListEvents command was called");
+    debug!("List events command was called for {list:?}");
     let chat_id = req.get_msg().chat.id;
-    let events = &req
+    let (text, keyboard) = build_event_list_page(&req.app, chat_id, &list, &filter, 1).await?;
+    req.set_msg_text(&text);
+    let response = send_keyboard_msg(&keyboard, offset, req).await?;
+
+    // Paging through search results needs the search text again
+    if let EventList::Search(text) = list {
+        let message_id = req.get_msg().message_id;
+        req.app
+            .event_searches
+            .write()
+            .await
+            .insert((chat_id, message_id), text);
+    }
+
+    Ok(response)
+}
+
+async fn handle_calendar_command(
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    debug!("Calendar command was called");
+    let chat_id = req.get_msg().chat.id;
+    let events = req
         .get_dvizh_repo()
         .await
         .get_upcoming_events_for_chat(chat_id)?;
@@ -238,29 +627,230 @@ async fn handle_list_events_command(
         return send_msg(offset, req).await;
     }
 
-    let text = req.get_translation_for("upcoming_event").await?;
+    let text = req.get_translation_for("calendar_caption").await?;
     req.set_msg_text(&text.expect_text()?);
-    send_msg(offset, req).await?;
+    send_document_msg(
+        "events.ics",
+        events_to_ics(&events).into_bytes(),
+        offset,
+        req,
+    )
+    .await
+}
 
-    // Retrieve the entire event template from translation
-    let template = req
-        .get_translation_for("event_template")
-        .await?
-        .expect_text()?;
+/// Sends the birthdays and events of the chat as CSV files that `/import` reads back.
+/// `None` sends both, an empty file still shows the columns to fill in.
+async fn handle_export_command(
+    layout: Option<CsvLayout>,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    debug!("Export command was called for {layout:?}");
+    let chat_id = req.get_msg().chat.id;
+    let user_id = req.get_msg().from.id;
+
+    if req.get_dvizh_repo().await.is_not_admin(user_id, chat_id)? {
+        let text = req.get_translation_for("error_not_admin").await?;
+        req.set_msg_text(&text.expect_text()?);
+        return send_msg(offset, req).await;
+    }
+
+    let layouts = match layout {
+        Some(layout) => vec![layout],
+        None => vec![CsvLayout::Birthdays, CsvLayout::Events],
+    };
+    let title = req.get_dvizh_repo().await.get_chat_title(chat_id)?;
+    let mut response = Value::Null;
+    for layout in layouts {
+        let (file_name, content, caption_key) = match layout {
+            CsvLayout::Birthdays => {
+                let users = req.get_dvizh_repo().await.get_chat_birthdays(chat_id)?;
+                (
+                    "birthdays.csv",
+                    birthdays_to_csv(&users),
+                    "csv_export_birthdays",
+                )
+            }
+            CsvLayout::Events => {
+                let events = req.get_dvizh_repo().await.get_chat_event_series(chat_id)?;
+                ("events.csv", events_to_csv(&events), "csv_export_events")
+            }
+        };
+        let text = req.get_translation_for(caption_key).await?;
+        req.set_msg_text(&text.expect_text()?.replace("{chat}", &title));
+        // Birthdates are not shared with the whole chat, so files go to the admin privately
+        response =
+            send_document_to_msg(user_id, file_name, content.into_bytes(), offset, req).await?;
+        if !response["ok"].as_bool().unwrap_or_default() {
+            debug!("Could not send export to {user_id}: {response}");
+            let text = req.get_translation_for("error_csv_export_private").await?;
+            req.set_msg_text(&text.expect_text()?);
+            return send_msg(offset, req).await;
+        }
+    }
+
+    if chat_id == user_id {
+        return Ok(response);
+    }
+    let text = req.get_translation_for("csv_export_sent").await?;
+    req.set_msg_text(&text.expect_text()?);
+    send_msg(offset, req).await
+}
+
+async fn handle_set_timezone_command(
+    timezone: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    debug!("SetTimezone command was called with {timezone}");
+    let chat_id = req.get_msg().chat.id;
+    let user_id = req.get_msg().from.id;
+
+    if req.get_dvizh_repo().await.is_not_admin(user_id, chat_id)? {
+        let text = req.get_translation_for("error_not_admin").await?;
+        req.set_msg_text(&text.expect_text()?);
+        return send_msg(offset, req).await;
+    }
+
+    let tz = match parse_timezone(timezone) {
+        Some(tz) => tz,
+        None => {
+            let text = req.get_translation_for("error_invalid_timezone").await?;
+            req.set_msg_text(&text.expect_text()?);
+            return send_msg(offset, req).await;
+        }
+    };
 
-    // Send each event using the template
-    for event in events {
-        let message = template
-            .replace("{title}", &event.title)
-            .replace("{date}", &event.date)
-            .replace("{location}", &event.location)
-            .replace("{description}", &event.description);
+    req.get_dvizh_repo()
+        .await
+        .update_chat_timezone(chat_id, tz.name())?;
+    let text = req.get_translation_for("timezone_updated").await?;
+    req.set_msg_text(&format!("{} {}", text.expect_text()?, tz.name()));
+    send_msg(offset, req).await
+}
 
-        req.set_msg_text(&message);
-        send_msg(offset, req).await?;
+/// Lists the chat settings without arguments, sets one with `[name] [value]`.
+async fn handle_settings_command(
+    args: Vec<String>,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    debug!("Settings command was called with {args:?}");
+    let chat_id = req.get_msg().chat.id;
+
+    let (name, value) = match args.as_slice() {
+        [] => {
+            let mut lines = Vec::new();
+            for setting in CHAT_SETTINGS {
+                let value = req
+                    .get_dvizh_repo()
+                    .await
+                    .get_chat_setting(chat_id, setting)?;
+                lines.push(format!(
+                    "{} = {value} ({})",
+                    chat_setting_to_str(setting),
+                    chat_setting_values(setting)
+                ));
+            }
+            let text = req.get_translation_for("settings_list").await?;
+            req.set_msg_text(&format!("{}\n{}", text.expect_text()?, lines.join("\n")));
+            return send_msg(offset, req).await;
+        }
+        [name, value] => (name, value),
+        _ => {
+            let text = req
+                .get_translation_for("error_insufficient_arguments")
+                .await?;
+            req.set_msg_text(&text.expect_text()?);
+            return send_msg(offset, req).await;
+        }
+    };
+
+    let user_id = req.get_msg().from.id;
+    if req.get_dvizh_repo().await.is_not_admin(user_id, chat_id)? {
+        let text = req.get_translation_for("error_not_admin").await?;
+        req.set_msg_text(&text.expect_text()?);
+        return send_msg(offset, req).await;
     }
 
-    Ok(serde_json::Value::Null)
+    // `here` stands for the forum topic the command was sent in
+    let topic_id = req.get_msg().message_thread_id;
+    let value = match (chat_setting_from_str(name), topic_id) {
+        (Some(ChatSetting::AdminTopic), Some(topic_id)) if value.eq_ignore_ascii_case("here") => {
+            topic_id.to_string()
+        }
+        _ => value.to_string(),
+    };
+
+    let result = match chat_setting_from_str(name) {
+        Some(setting) => validate_chat_setting(&setting, &value).map(|value| (setting, value)),
+        None => Err(ValidationError::UnknownSetting),
+    };
+    let (setting, value) = match result {
+        Ok(result) => result,
+        Err(error) => {
+            let text = req
+                .get_translation_for(validation_error_to_key(&error))
+                .await?;
+            req.set_msg_text(&text.expect_text()?);
+            return send_msg(offset, req).await;
+        }
+    };
+
+    req.get_dvizh_repo()
+        .await
+        .set_chat_setting(chat_id, &setting, &value)?;
+    let text = req.get_translation_for("setting_updated").await?;
+    req.set_msg_text(&format!(
+        "{} {} = {value}",
+        text.expect_text()?,
+        chat_setting_to_str(&setting)
+    ));
+    send_msg(offset, req).await
+}
+
+/// Shows the upcoming events of the chat as an inline list.
+/// Tapping an event sends `{action}_{event id}` back as callback data.
+async fn handle_pick_event_command(
+    action: &str,
+    prompt_key: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    debug!("Pick event command was called for {action}");
+    let chat_id = req.get_msg().chat.id;
+    let user_id = req.get_msg().from.id;
+
+    if req.get_dvizh_repo().await.is_not_admin(user_id, chat_id)? {
+        let text = req.get_translation_for("error_not_admin").await?;
+        req.set_msg_text(&text.expect_text()?);
+        return send_msg(offset, req).await;
+    }
+
+    let mut events = req
+        .get_dvizh_repo()
+        .await
+        .get_upcoming_events_for_chat(chat_id)?;
+
+    if events.is_empty() {
+        let text = req.get_translation_for("no_upcoming_event").await?;
+        req.set_msg_text(&text.expect_text()?);
+        return send_msg(offset, req).await;
+    }
+
+    // Editing always applies to the whole series, so list a recurring event only once
+    if action == "event_edit" {
+        let mut seen = HashSet::new();
+        events.retain(|event| seen.insert(event.id));
+    }
+
+    let cancel = req
+        .get_translation_for("button_cancel")
+        .await?
+        .expect_text()?;
+    let text = req.get_translation_for(prompt_key).await?;
+    req.set_msg_text(&text.expect_text()?);
+    send_keyboard_msg(&events_keyboard(&events, action, &cancel), offset, req).await
 }
 
 async fn handle_meme_command(offset: &mut i64, req: &mut MsgRequest) -> Result<serde_json::Value> {
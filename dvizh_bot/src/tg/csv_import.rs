@@ -0,0 +1,374 @@
+use crate::csv::{csv_layout, parse_csv, CsvLayout};
+use crate::db::db_objects::{Event, User as DbUser};
+use crate::tg::event_utils::{event_from_args, format_event_date};
+use crate::tg::messaging::{edit_msg_and_remove_keyboard, send_keyboard_msg, send_msg};
+use crate::tg::msg_request::MsgRequest;
+use crate::tg::tg_objects::Document;
+use crate::tg::tg_utils::download_file;
+use crate::validations::{validate_birthdate, validation_error_to_key};
+use anyhow::Result;
+use log::{debug, error};
+use serde_json::json;
+
+/// Largest CSV file the bot downloads, in bytes.
+static MAX_CSV_FILE_SIZE: i64 = 1024 * 1024;
+/// How many rows the import preview lists before summarizing the rest.
+static MAX_PREVIEW_LINES: usize = 30;
+
+/// Rows of a previewed CSV file, saved once the admin confirms.
+#[derive(Debug, Clone)]
+pub enum CsvImport {
+    /// Users known only by username have the id `0`, see `import_birthdays`.
+    Birthdays(Vec<DbUser>),
+    Events(Vec<Event>),
+}
+
+pub fn is_csv_document(document: &Document) -> bool {
+    matches!(
+        document.mime_type.as_deref(),
+        Some("text/csv" | "text/comma-separated-values")
+    ) || document
+        .file_name
+        .as_deref()
+        .is_some_and(|name| name.to_lowercase().ends_with(".csv"))
+}
+
+/// Validates every row of a CSV document of birthdays or events and previews the import,
+/// listing the errors by line. The valid rows are saved in one transaction
+/// once the admin confirms with the `csvimport_confirm` button.
+pub async fn handle_csv_import_document(
+    document: Document,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    debug!("Import CSV from {document:?}");
+    let chat_id = req.get_msg().chat.id;
+    let user_id = req.get_msg().from.id;
+
+    if req.get_dvizh_repo().await.is_not_admin(user_id, chat_id)? {
+        let text = req.get_translation_for("error_not_admin").await?;
+        req.set_msg_text(&text.expect_text()?);
+        return send_msg(offset, req).await;
+    }
+
+    if document.file_size.unwrap_or_default() > MAX_CSV_FILE_SIZE {
+        let text = req.get_translation_for("error_csv_too_large").await?;
+        req.set_msg_text(&text.expect_text()?);
+        return send_msg(offset, req).await;
+    }
+
+    let content = download_file(&req.app.client, &req.app.tg_token, &document.file_id).await?;
+    let mut records = parse_csv(&String::from_utf8_lossy(&content));
+    // The first record names the columns
+    let header = if records.is_empty() {
+        Vec::new()
+    } else {
+        records.remove(0).1
+    };
+    let header = header
+        .iter()
+        .map(|name| name.trim().to_lowercase())
+        .collect::<Vec<String>>();
+    let layout = match csv_layout(&header) {
+        Some(layout) => layout,
+        None => {
+            let text = req.get_translation_for("error_csv_header").await?;
+            req.set_msg_text(&text.expect_text()?);
+            return send_msg(offset, req).await;
+        }
+    };
+
+    let line_error = req
+        .get_translation_for("csv_import_line")
+        .await?
+        .expect_text()?;
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+    let import = match layout {
+        CsvLayout::Birthdays => {
+            let mut users = Vec::new();
+            for (line, fields) in records {
+                match birthday_from_csv(&header, &fields) {
+                    Ok(mut user) => {
+                        // Rows naming a known user by username update that user
+                        if let (0, Some(username)) = (user.id, &user.username) {
+                            let known =
+                                req.get_dvizh_repo().await.find_user_by_username(username)?;
+                            if let Some(known) = known {
+                                user.id = known.id;
+                            }
+                        }
+                        // Rows may give nothing but the id
+                        let name = match user.display_name() {
+                            name if name.is_empty() => user.id.to_string(),
+                            name => name,
+                        };
+                        lines.push(format!(
+                            "➕ {name} — {}",
+                            user.birthdate.clone().unwrap_or_default()
+                        ));
+                        users.push(user);
+                    }
+                    Err(error_key) => {
+                        let error = req.get_translation_for(error_key).await?.expect_text()?;
+                        errors.push(format!(
+                            "⚠️ {}",
+                            line_error
+                                .replace("{line}", &line.to_string())
+                                .replace("{error}", &error)
+                        ));
+                    }
+                }
+            }
+            CsvImport::Birthdays(users)
+        }
+        CsvLayout::Events => {
+            let timezone = req.get_dvizh_repo().await.get_chat_timezone(chat_id)?;
+            // Importing an exported file twice does not duplicate its events
+            let existing = req
+                .get_dvizh_repo()
+                .await
+                .get_upcoming_events_for_chat(chat_id)?;
+            let duplicate = req
+                .get_translation_for("csv_import_duplicate")
+                .await?
+                .expect_text()?;
+            let mut events: Vec<Event> = Vec::new();
+            for (line, fields) in records {
+                match event_from_csv(&header, &fields, chat_id, &timezone) {
+                    Ok(event) => {
+                        let is_duplicate = existing
+                            .iter()
+                            .chain(events.iter())
+                            .any(|other| other.title == event.title && other.start == event.start);
+                        if is_duplicate {
+                            lines.push(format!(
+                                "⏭ {} ({}): {duplicate}",
+                                event.title,
+                                format_event_date(&event)
+                            ));
+                            continue;
+                        }
+                        lines.push(format!(
+                            "➕ {} ({})",
+                            event.title,
+                            format_event_date(&event)
+                        ));
+                        events.push(event);
+                    }
+                    Err(error_key) => {
+                        let error = req.get_translation_for(error_key).await?.expect_text()?;
+                        errors.push(format!(
+                            "⚠️ {}",
+                            line_error
+                                .replace("{line}", &line.to_string())
+                                .replace("{error}", &error)
+                        ));
+                    }
+                }
+            }
+            CsvImport::Events(events)
+        }
+    };
+    let valid = match &import {
+        CsvImport::Birthdays(users) => users.len(),
+        CsvImport::Events(events) => events.len(),
+    };
+    // Errors come first so that long files do not hide them
+    let invalid = errors.len();
+    errors.append(&mut lines);
+    let mut lines = errors;
+
+    if lines.len() > MAX_PREVIEW_LINES {
+        let more = req
+            .get_translation_for("import_more")
+            .await?
+            .expect_text()?
+            .replace("{count}", &(lines.len() - MAX_PREVIEW_LINES).to_string());
+        lines.truncate(MAX_PREVIEW_LINES);
+        lines.push(more);
+    }
+
+    let header = req
+        .get_translation_for("csv_import_preview")
+        .await?
+        .expect_text()?;
+    if valid == 0 {
+        let nothing = req
+            .get_translation_for("import_nothing")
+            .await?
+            .expect_text()?;
+        req.set_msg_text(&format!("{header}\n{}\n\n{nothing}", lines.join("\n")));
+        return send_msg(offset, req).await;
+    }
+    let summary = req
+        .get_translation_for("csv_import_summary")
+        .await?
+        .expect_text()?
+        .replace("{valid}", &valid.to_string())
+        .replace("{invalid}", &invalid.to_string());
+
+    req.app
+        .pending_csv_imports
+        .write()
+        .await
+        .insert((chat_id, user_id), import);
+
+    let import = req
+        .get_translation_for("button_import")
+        .await?
+        .expect_text()?;
+    let cancel = req
+        .get_translation_for("button_cancel")
+        .await?
+        .expect_text()?;
+    let keyboard = json!({
+        "inline_keyboard": [
+            [
+                { "text": import, "callback_data": "csvimport_confirm" },
+                { "text": cancel, "callback_data": "csvimport_cancel" }
+            ]
+        ]
+    })
+    .to_string();
+
+    req.set_msg_text(&format!("{header}\n{}\n\n{summary}", lines.join("\n")));
+    send_keyboard_msg(&keyboard, offset, req).await
+}
+
+/// Saves or drops the CSV import previewed for the user who pressed the button.
+/// Callback data is `csvimport_confirm` or `csvimport_cancel`.
+pub async fn handle_csv_import_callback(
+    callback_query: &serde_json::Value,
+    callback_data: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let chat_id = req.get_msg().chat.id;
+    let user_id = callback_query["from"]["id"].as_i64().unwrap_or_default();
+
+    let pending = req
+        .app
+        .pending_csv_imports
+        .write()
+        .await
+        .remove(&(chat_id, user_id));
+    let import = match pending {
+        Some(import) => import,
+        None => {
+            let text = req.get_translation_for("error_csv_import_expired").await?;
+            req.set_msg_text(&text.expect_text()?);
+            send_msg(offset, req).await?;
+            return Ok(());
+        }
+    };
+
+    if callback_data == "csvimport_cancel" {
+        let text = req.get_translation_for("cancelled").await?;
+        req.set_msg_text(&text.expect_text()?);
+        edit_msg_and_remove_keyboard(offset, req).await?;
+        return Ok(());
+    }
+
+    if req.get_dvizh_repo().await.is_not_admin(user_id, chat_id)? {
+        let text = req.get_translation_for("error_not_admin").await?;
+        req.set_msg_text(&text.expect_text()?);
+        send_msg(offset, req).await?;
+        return Ok(());
+    }
+
+    let (count, result) = match import {
+        CsvImport::Birthdays(users) => (
+            users.len(),
+            req.get_dvizh_repo().await.import_birthdays(users, chat_id),
+        ),
+        CsvImport::Events(mut events) => {
+            for event in events.iter_mut() {
+                event.created_by = Some(user_id);
+            }
+            (
+                events.len(),
+                req.get_dvizh_repo().await.import_events(events),
+            )
+        }
+    };
+
+    let text = match result {
+        Ok(()) => req
+            .get_translation_for("csv_import_done")
+            .await?
+            .expect_text()?
+            .replace("{count}", &count.to_string()),
+        Err(e) => {
+            error!("Failed to import CSV rows in {chat_id}: {e}");
+            req.get_translation_for("error_csv_import_failed")
+                .await?
+                .expect_text()?
+        }
+    };
+    req.set_msg_text(&text);
+    edit_msg_and_remove_keyboard(offset, req).await?;
+    Ok(())
+}
+
+/// Returns the trimmed value of `column` in a row, empty when the row has no such column.
+fn csv_value<'a>(header: &[String], fields: &'a [String], column: &str) -> &'a str {
+    header
+        .iter()
+        .position(|name| name == column)
+        .and_then(|index| fields.get(index))
+        .map_or("", |value| value.trim())
+}
+
+/// Reads a birthday row, returns the translation key of the error otherwise.
+fn birthday_from_csv(header: &[String], fields: &[String]) -> Result<DbUser, &'static str> {
+    if fields.len() > header.len() {
+        return Err("error_csv_columns");
+    }
+    let username = csv_value(header, fields, "username").trim_start_matches('@');
+    let id = match csv_value(header, fields, "user_id") {
+        "" if username.is_empty() => return Err("error_csv_missing_user"),
+        "" => 0,
+        id => id
+            .parse::<i64>()
+            .ok()
+            .filter(|id| *id > 0)
+            .ok_or("error_csv_missing_user")?,
+    };
+    let birthdate = validate_birthdate(csv_value(header, fields, "birthdate"))
+        .map_err(|error| validation_error_to_key(&error))?;
+    let first_name = csv_value(header, fields, "first_name");
+
+    Ok(DbUser::new(
+        id,
+        Some(username.to_string()).filter(|username| !username.is_empty()),
+        Some(first_name.to_string()).filter(|name| !name.is_empty()),
+        Some(birthdate),
+        None,
+    ))
+}
+
+/// Reads an event row like the arguments of `/addevent`,
+/// returns the translation key of the error otherwise.
+fn event_from_csv(
+    header: &[String],
+    fields: &[String],
+    chat_id: i64,
+    timezone: &str,
+) -> Result<Event, &'static str> {
+    if fields.len() > header.len() {
+        return Err("error_csv_columns");
+    }
+    let mut args = ["title", "date", "location", "description"]
+        .iter()
+        .map(|column| csv_value(header, fields, column).to_string())
+        .collect::<Vec<String>>();
+    if args[0].is_empty() {
+        return Err("error_csv_missing_title");
+    }
+    let recurrence = csv_value(header, fields, "recurrence");
+    if !recurrence.is_empty() {
+        args.push(recurrence.to_string());
+    }
+    event_from_args(&args, chat_id, timezone).map_err(|error| validation_error_to_key(&error))
+}
@@ -0,0 +1,602 @@
+use crate::db::db_objects::User as DbUser;
+use crate::tg::event_utils::{event_from_args, fill_event_template, set_event_field, EventField};
+use crate::tg::messaging::{
+    edit_keyboard_msg, edit_msg_and_remove_keyboard, send_keyboard_reply_msg, send_msg,
+};
+use crate::tg::msg_request::MsgRequest;
+use crate::tg::pending_input::updated_event_keyboard;
+use crate::time_utils::{
+    format_event_datetime, timezone_or_default, today_in, DATE_FORMAT, TIME_FORMAT,
+};
+use crate::validations::{validate_birthdate, validation_error_to_key, EventDateTime};
+use anyhow::Result;
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveTime};
+use log::debug;
+use serde_json::{json, Value};
+
+/// Years shown on one page of the year picker.
+static YEARS_PER_PAGE: i32 = 12;
+/// First and last hour offered as the start time of an event.
+static FIRST_TIME_SLOT: u32 = 8;
+static LAST_TIME_SLOT: u32 = 23;
+
+/// What a date picked from the calendar is used for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DatePickerTarget {
+    /// Birthdate of the member who opened the calendar.
+    Birthdate,
+    /// Date of an event whose other `/addevent` arguments wait in `event_drafts`.
+    AddEvent,
+    /// New date of an event opened in the event editor.
+    EventDate(i64),
+}
+
+pub fn date_picker_target_from_str(t: &str) -> Option<DatePickerTarget> {
+    match t {
+        "b" => Some(DatePickerTarget::Birthdate),
+        "a" => Some(DatePickerTarget::AddEvent),
+        _ => t
+            .strip_prefix('e')
+            .and_then(|id| id.parse::<i64>().ok())
+            .map(DatePickerTarget::EventDate),
+    }
+}
+
+pub fn date_picker_target_to_str(target: &DatePickerTarget) -> String {
+    match target {
+        DatePickerTarget::Birthdate => "b".to_string(),
+        DatePickerTarget::AddEvent => "a".to_string(),
+        DatePickerTarget::EventDate(event_id) => format!("e{event_id}"),
+    }
+}
+
+/// Callback data of a calendar button, `cal_{target}_{view}{value}`.
+/// Views are `m{YYYYMM}` for a month, `y{YYYYMM}` for a page of years starting with `YYYY`,
+/// `d{YYYYMMDD}` for a picked day or `d{MMDD}` for a birthday without a year,
+/// `t{YYYYMMDD}[{HHMM}[{HHMM}]]` for a picked time, time range or the whole day,
+/// `s` for saving a typed date kept in the draft, `n` for labels and `x` for cancelling.
+fn calendar_data(target: &DatePickerTarget, action: &str) -> String {
+    format!("cal_{}_{action}", date_picker_target_to_str(target))
+}
+
+fn label(text: &str) -> Value {
+    json!({ "text": text, "callback_data": "cal_n" })
+}
+
+/// Returns the calendar a picker opens with.
+/// Birthdates start with the years up to the current one, events with the month of their date.
+pub async fn date_picker_keyboard(
+    target: DatePickerTarget,
+    req: &mut MsgRequest,
+) -> Result<String> {
+    let chat_id = req.get_msg().chat.id;
+    let timezone = req.get_dvizh_repo().await.get_chat_timezone(chat_id)?;
+    let today = today_in(&timezone_or_default(&timezone));
+
+    let month = match target {
+        DatePickerTarget::Birthdate => {
+            let first_year = today.year() - YEARS_PER_PAGE + 1;
+            return year_keyboard(&target, first_year, 1, req).await;
+        }
+        DatePickerTarget::AddEvent => today,
+        DatePickerTarget::EventDate(event_id) => {
+            match req.get_dvizh_repo().await.get_event(event_id)? {
+                Some(event) => event
+                    .start
+                    .with_timezone(&timezone_or_default(&event.timezone))
+                    .date_naive(),
+                None => today,
+            }
+        }
+    };
+    month_keyboard(&target, month.with_day(1).unwrap_or(month), req).await
+}
+
+/// Replies with how a typed date was understood, with buttons saving it under `save_action`,
+/// picking another date from the calendar opened at `month` or cancelling.
+pub async fn send_date_confirmation(
+    target: DatePickerTarget,
+    input: &str,
+    understood: &str,
+    save_action: &str,
+    month: NaiveDate,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<Value> {
+    let text = req
+        .get_translation_for("date_interpreted")
+        .await?
+        .expect_text()?;
+    let save = req
+        .get_translation_for("button_save")
+        .await?
+        .expect_text()?;
+    let other_date = req
+        .get_translation_for("button_other_date")
+        .await?
+        .expect_text()?;
+    let cancel = req
+        .get_translation_for("button_cancel")
+        .await?
+        .expect_text()?;
+
+    let keyboard = json!({
+        "inline_keyboard": [
+            [
+                { "text": save, "callback_data": calendar_data(&target, save_action) },
+                { "text": other_date, "callback_data": calendar_data(&target, &format!("m{}", month.format("%Y%m"))) }
+            ],
+            [{ "text": cancel, "callback_data": calendar_data(&target, "x") }]
+        ]
+    })
+    .to_string();
+    req.set_msg_text(&text.replace("{input}", input).replace("{date}", understood));
+    send_keyboard_reply_msg(&keyboard, offset, req).await
+}
+
+/// Describes an event date for a confirmation, such as `Fri 23.10.2026 19:00-22:00 Europe/Warsaw`.
+pub async fn describe_event_date(
+    date_time: &EventDateTime,
+    req: &mut MsgRequest,
+) -> Result<String> {
+    let weekdays = req
+        .get_translation_for("calendar_weekdays")
+        .await?
+        .expect_array()?;
+    let local_start = date_time.start.with_timezone(&date_time.timezone);
+    Ok(format!(
+        "{} {} {}",
+        weekdays[local_start.weekday().num_days_from_monday() as usize],
+        format_event_datetime(date_time.start, date_time.end, &date_time.timezone),
+        date_time.timezone.name()
+    ))
+}
+
+/// Picker action saving a validated `DD.MM[.YYYY]` birthdate as `d{YYYYMMDD}` or `d{MMDD}`.
+pub fn birthdate_action(birthdate: &str) -> String {
+    format!("d{}", birthdate.rsplit('.').collect::<String>())
+}
+
+/// Picker action saving an event date in its own timezone, see `calendar_data`.
+pub fn event_date_action(date_time: &EventDateTime) -> String {
+    let start = date_time.start.with_timezone(&date_time.timezone);
+    let mut action = format!("t{}", start.format("%Y%m%d"));
+    if start.time() != NaiveTime::MIN || date_time.end.is_some() {
+        action = format!("{action}{}", start.format("%H%M"));
+    }
+    if let Some(end) = date_time.end {
+        action = format!(
+            "{action}{}",
+            end.with_timezone(&date_time.timezone).format("%H%M")
+        );
+    }
+    action
+}
+
+/// Day grid of `month` starting on Monday, with month and year navigation.
+async fn month_keyboard(
+    target: &DatePickerTarget,
+    month: NaiveDate,
+    req: &mut MsgRequest,
+) -> Result<String> {
+    let months = req
+        .get_translation_for("month_names")
+        .await?
+        .expect_array()?;
+    let weekdays = req
+        .get_translation_for("calendar_weekdays")
+        .await?
+        .expect_array()?;
+    let cancel = req
+        .get_translation_for("button_cancel")
+        .await?
+        .expect_text()?;
+
+    let month_data = |date: Option<NaiveDate>| match date {
+        Some(date) => calendar_data(target, &format!("m{}", date.format("%Y%m"))),
+        None => "cal_n".to_string(),
+    };
+    let title = format!("{} {}", months[month.month0() as usize], month.year());
+    // The title opens the years around the shown one
+    let first_year = month.year() - YEARS_PER_PAGE / 2 + 1;
+    let mut rows = vec![
+        vec![
+            json!({ "text": "«", "callback_data": month_data(month.checked_sub_months(Months::new(12))) }),
+            json!({ "text": "‹", "callback_data": month_data(month.checked_sub_months(Months::new(1))) }),
+            json!({ "text": title, "callback_data": calendar_data(target, &format!("y{first_year:04}{:02}", month.month())) }),
+            json!({ "text": "›", "callback_data": month_data(month.checked_add_months(Months::new(1))) }),
+            json!({ "text": "»", "callback_data": month_data(month.checked_add_months(Months::new(12))) }),
+        ],
+        weekdays.iter().map(|weekday| label(weekday)).collect(),
+    ];
+
+    let mut week = vec![label(" "); month.weekday().num_days_from_monday() as usize];
+    let mut day = month;
+    while day.month() == month.month() {
+        week.push(json!({
+            "text": day.day().to_string(),
+            "callback_data": calendar_data(target, &format!("d{}", day.format("%Y%m%d")))
+        }));
+        if week.len() == 7 {
+            rows.push(std::mem::take(&mut week));
+        }
+        day += Duration::days(1);
+    }
+    if !week.is_empty() {
+        week.resize(7, label(" "));
+        rows.push(week);
+    }
+    rows.push(vec![
+        json!({ "text": cancel, "callback_data": calendar_data(target, "x") }),
+    ]);
+
+    Ok(json!({ "inline_keyboard": rows }).to_string())
+}
+
+/// Page of years starting with `first_year`, picking one opens `month` of that year.
+async fn year_keyboard(
+    target: &DatePickerTarget,
+    first_year: i32,
+    month: u32,
+    req: &mut MsgRequest,
+) -> Result<String> {
+    let cancel = req
+        .get_translation_for("button_cancel")
+        .await?
+        .expect_text()?;
+    let last_year = first_year + YEARS_PER_PAGE - 1;
+    let page_data = |year: i32| calendar_data(target, &format!("y{year:04}{month:02}"));
+
+    let mut rows = vec![vec![
+        json!({ "text": "‹", "callback_data": page_data(first_year - YEARS_PER_PAGE) }),
+        label(&format!("{first_year}–{last_year}")),
+        json!({ "text": "›", "callback_data": page_data(first_year + YEARS_PER_PAGE) }),
+    ]];
+    let years = (first_year..=last_year)
+        .map(|year| {
+            json!({
+                "text": year.to_string(),
+                "callback_data": calendar_data(target, &format!("m{year:04}{month:02}"))
+            })
+        })
+        .collect::<Vec<Value>>();
+    rows.extend(years.chunks(4).map(|row| row.to_vec()));
+    rows.push(vec![
+        json!({ "text": cancel, "callback_data": calendar_data(target, "x") }),
+    ]);
+
+    Ok(json!({ "inline_keyboard": rows }).to_string())
+}
+
+/// Start times of an event on `date`, or the whole day.
+async fn time_keyboard(
+    target: &DatePickerTarget,
+    date: NaiveDate,
+    req: &mut MsgRequest,
+) -> Result<String> {
+    let weekdays = req
+        .get_translation_for("calendar_weekdays")
+        .await?
+        .expect_array()?;
+    let all_day = req
+        .get_translation_for("button_all_day")
+        .await?
+        .expect_text()?;
+    let cancel = req
+        .get_translation_for("button_cancel")
+        .await?
+        .expect_text()?;
+
+    let day = date.format("%Y%m%d");
+    let mut rows = vec![vec![label(&format!(
+        "{} {}",
+        weekdays[date.weekday().num_days_from_monday() as usize],
+        date.format(DATE_FORMAT)
+    ))]];
+    let slots = (FIRST_TIME_SLOT..=LAST_TIME_SLOT)
+        .map(|hour| {
+            json!({
+                "text": format!("{hour:02}:00"),
+                "callback_data": calendar_data(target, &format!("t{day}{hour:02}00"))
+            })
+        })
+        .collect::<Vec<Value>>();
+    rows.extend(slots.chunks(4).map(|row| row.to_vec()));
+    rows.push(vec![
+        json!({ "text": "‹", "callback_data": calendar_data(target, &format!("m{}", date.format("%Y%m"))) }),
+        json!({ "text": all_day, "callback_data": calendar_data(target, &format!("t{day}")) }),
+    ]);
+    rows.push(vec![
+        json!({ "text": cancel, "callback_data": calendar_data(target, "x") }),
+    ]);
+
+    Ok(json!({ "inline_keyboard": rows }).to_string())
+}
+
+/// Handles the buttons of the calendar, see `calendar_data` for the callback data.
+pub async fn handle_date_picker_callback(
+    callback_query: &serde_json::Value,
+    callback_data: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let parts = callback_data.splitn(3, '_').collect::<Vec<&str>>();
+    let target = match parts
+        .get(1)
+        .and_then(|target| date_picker_target_from_str(target))
+    {
+        Some(target) => target,
+        None => return Ok(()),
+    };
+    let action = parts.get(2).copied().unwrap_or_default();
+    if action.is_empty() {
+        return Ok(());
+    }
+    let (view, value) = action.split_at(1);
+    debug!("Date picker {target:?} shows {view} {value}");
+
+    // Calendars sent in reply to a command answer its sender only
+    let user_id = callback_query["from"]["id"].as_i64().unwrap_or_default();
+    let opened_by = req
+        .get_msg()
+        .reply_to_message
+        .as_ref()
+        .and_then(|reply| reply.from.as_ref())
+        .map(|user| user.id);
+    let from_command = matches!(
+        target,
+        DatePickerTarget::Birthdate | DatePickerTarget::AddEvent
+    );
+    if from_command && opened_by != Some(user_id) {
+        return Ok(());
+    }
+
+    match view {
+        "m" => {
+            if let Ok(month) = NaiveDate::parse_from_str(&format!("{value}01"), "%Y%m%d") {
+                let keyboard = month_keyboard(&target, month, req).await?;
+                edit_keyboard_msg(&keyboard, offset, req).await?;
+            }
+        }
+        "y" => {
+            let first_year = value.get(..4).and_then(|year| year.parse::<i32>().ok());
+            let month = value.get(4..).and_then(|month| month.parse::<u32>().ok());
+            if let (Some(first_year), Some(month @ 1..=12)) = (first_year, month) {
+                let keyboard = year_keyboard(&target, first_year, month, req).await?;
+                edit_keyboard_msg(&keyboard, offset, req).await?;
+            }
+        }
+        // Birthdays confirmed without a year come as `MMDD`
+        "d" if target == DatePickerTarget::Birthdate && value.len() == 4 => {
+            let (month, day) = value.split_at(2);
+            let birthdate = format!("{day}.{month}");
+            save_picked_birthdate(callback_query, &birthdate, offset, req).await?;
+        }
+        "d" => {
+            if let Ok(day) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+                match target {
+                    DatePickerTarget::Birthdate => {
+                        let birthdate = day.format(DATE_FORMAT).to_string();
+                        save_picked_birthdate(callback_query, &birthdate, offset, req).await?;
+                    }
+                    _ => {
+                        let keyboard = time_keyboard(&target, day, req).await?;
+                        edit_keyboard_msg(&keyboard, offset, req).await?;
+                    }
+                }
+            }
+        }
+        "t" => {
+            let day = value
+                .get(..8)
+                .and_then(|day| NaiveDate::parse_from_str(day, "%Y%m%d").ok());
+            let time = value
+                .get(8..12)
+                .and_then(|time| NaiveTime::parse_from_str(time, "%H%M").ok());
+            let end = value
+                .get(12..)
+                .and_then(|time| NaiveTime::parse_from_str(time, "%H%M").ok());
+            if let Some(day) = day {
+                let mut date = day.format(DATE_FORMAT).to_string();
+                if let Some(time) = time {
+                    date = format!("{date} {}", time.format(TIME_FORMAT));
+                }
+                if let Some(end) = end {
+                    date = format!("{date}-{}", end.format(TIME_FORMAT));
+                }
+                save_event_date(target, user_id, Some(date), offset, req).await?;
+            }
+        }
+        "s" if target == DatePickerTarget::AddEvent => {
+            save_event_date(target, user_id, None, offset, req).await?;
+        }
+        "x" => {
+            let chat_id = req.get_msg().chat.id;
+            match target {
+                DatePickerTarget::AddEvent => {
+                    req.app
+                        .event_drafts
+                        .write()
+                        .await
+                        .remove(&(chat_id, user_id));
+                }
+                DatePickerTarget::EventDate(_) => req.clear_pending_input(chat_id, user_id).await,
+                DatePickerTarget::Birthdate => {}
+            }
+            let text = req.get_translation_for("cancelled").await?;
+            req.set_msg_text(&text.expect_text()?);
+            edit_msg_and_remove_keyboard(offset, req).await?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+async fn save_picked_birthdate(
+    callback_query: &serde_json::Value,
+    birthdate: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let date = match validate_birthdate(birthdate) {
+        Ok(date) => date,
+        Err(error) => {
+            let text = req
+                .get_translation_for(validation_error_to_key(&error))
+                .await?;
+            req.set_msg_text(&text.expect_text()?);
+            send_msg(offset, req).await?;
+            return Ok(());
+        }
+    };
+
+    let from = &callback_query["from"];
+    let user = DbUser::new(
+        from["id"].as_i64().unwrap_or_default(),
+        from["username"]
+            .as_str()
+            .map(|username| username.to_string()),
+        from["first_name"].as_str().map(|name| name.to_string()),
+        Some(date.clone()),
+        from["language_code"].as_str().map(|code| code.to_string()),
+    );
+    let chat_id = req.get_msg().chat.id;
+    req.get_dvizh_repo()
+        .await
+        .add_or_update_user(user, chat_id)?;
+
+    let text = req.get_translation_for("remeber_birthday").await?;
+    req.set_msg_text(&format!("{} {date}", text.expect_text()?));
+    edit_msg_and_remove_keyboard(offset, req).await?;
+    Ok(())
+}
+
+/// Creates the drafted event or moves the edited one to the picked `DD.MM.YYYY [HH:MM[-HH:MM]]`.
+/// Drafts keep their date as the second argument, it is replaced by the picked one if any.
+async fn save_event_date(
+    target: DatePickerTarget,
+    user_id: i64,
+    value: Option<String>,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let chat_id = req.get_msg().chat.id;
+
+    match target {
+        DatePickerTarget::AddEvent => {
+            let draft = req
+                .app
+                .event_drafts
+                .write()
+                .await
+                .remove(&(chat_id, user_id));
+            let mut args = match draft {
+                Some(args) => args,
+                None => {
+                    let text = req.get_translation_for("error_date_picker_expired").await?;
+                    req.set_msg_text(&text.expect_text()?);
+                    send_msg(offset, req).await?;
+                    return Ok(());
+                }
+            };
+
+            if req.get_dvizh_repo().await.is_not_admin(user_id, chat_id)? {
+                let text = req.get_translation_for("error_not_admin").await?;
+                req.set_msg_text(&text.expect_text()?);
+                send_msg(offset, req).await?;
+                return Ok(());
+            }
+
+            if let Some(value) = value {
+                args[1] = value;
+            }
+            let timezone = req.get_dvizh_repo().await.get_chat_timezone(chat_id)?;
+            let mut event = match event_from_args(&args, chat_id, &timezone) {
+                Ok(event) => event,
+                Err(error) => {
+                    // The organizer may pick another time
+                    req.app
+                        .event_drafts
+                        .write()
+                        .await
+                        .insert((chat_id, user_id), args);
+                    let text = req
+                        .get_translation_for(validation_error_to_key(&error))
+                        .await?;
+                    req.set_msg_text(&text.expect_text()?);
+                    send_msg(offset, req).await?;
+                    return Ok(());
+                }
+            };
+            event.created_by = Some(user_id);
+
+            req.get_dvizh_repo().await.add_event(event)?;
+            let text = req.get_translation_for("remeber_event").await?;
+            req.set_msg_text(&format!("{} {}", text.expect_text()?, args[0]));
+            edit_msg_and_remove_keyboard(offset, req).await?;
+        }
+        DatePickerTarget::EventDate(event_id) => {
+            // Proposals sent to admins privately are edited from the private chat
+            let event = req.get_dvizh_repo().await.get_event(event_id)?;
+            let mut event = match event {
+                Some(event) if event.group_id == chat_id || chat_id == user_id => event,
+                _ => {
+                    let text = req.get_translation_for("error_event_not_found").await?;
+                    req.set_msg_text(&text.expect_text()?);
+                    edit_msg_and_remove_keyboard(offset, req).await?;
+                    return Ok(());
+                }
+            };
+
+            if req
+                .get_dvizh_repo()
+                .await
+                .is_not_admin(user_id, event.group_id)?
+            {
+                let text = req.get_translation_for("error_not_admin").await?;
+                req.set_msg_text(&text.expect_text()?);
+                send_msg(offset, req).await?;
+                return Ok(());
+            }
+
+            // An event keeps its duration when moved to another time
+            let mut value = value.unwrap_or_default();
+            let duration = event.end.map(|end| end - event.start);
+            let time = value
+                .split_once(' ')
+                .and_then(|(_, time)| NaiveTime::parse_from_str(time, TIME_FORMAT).ok());
+            if let (Some(time), Some(duration)) = (time, duration) {
+                if duration < Duration::days(1) {
+                    value = format!("{value}-{}", (time + duration).format(TIME_FORMAT));
+                }
+            }
+            if let Err(error) = set_event_field(&mut event, &EventField::Date, value) {
+                let text = req
+                    .get_translation_for(validation_error_to_key(&error))
+                    .await?;
+                req.set_msg_text(&text.expect_text()?);
+                send_msg(offset, req).await?;
+                return Ok(());
+            }
+            req.get_dvizh_repo().await.update_event(&event)?;
+            req.clear_pending_input(chat_id, user_id).await;
+
+            let header = req
+                .get_translation_for("event_updated")
+                .await?
+                .expect_text()?;
+            let template = req
+                .get_translation_for("event_template")
+                .await?
+                .expect_text()?;
+            req.set_msg_text(&format!(
+                "{header}\n\n{}",
+                fill_event_template(&template, &event)
+            ));
+            let keyboard = updated_event_keyboard(&event, req).await?;
+            edit_keyboard_msg(&keyboard, offset, req).await?;
+        }
+        DatePickerTarget::Birthdate => {}
+    }
+    Ok(())
+}
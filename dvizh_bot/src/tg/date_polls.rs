@@ -0,0 +1,298 @@
+use crate::application::Application;
+use crate::db::db_objects::{DatePoll, Event};
+use crate::natural_dates::is_natural_date;
+use crate::tg::event_cards::build_event_card;
+use crate::tg::messaging::{
+    edit_keyboard_msg, edit_msg_and_remove_keyboard, send_keyboard_msg, send_msg, send_poll_msg,
+    stop_poll,
+};
+use crate::tg::msg_request::MsgRequest;
+use crate::tg::msg_type_utils::MsgType;
+use crate::time_utils::{format_event_datetime, timezone_or_default};
+use crate::validations::{
+    validate_date_poll_options, validate_event_datetime, validation_error_to_key, ValidationError,
+};
+use anyhow::Result;
+use log::debug;
+use serde_json::json;
+
+/// Posts a poll on the dates given after the title, members may pick several.
+/// Admins close it with the button below the poll.
+pub async fn handle_date_poll_command(
+    mut args: Vec<String>,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    debug!("DatePoll command was called");
+    let chat_id = req.get_msg().chat.id;
+    let user_id = req.get_msg().from.id;
+    let title = args.remove(0);
+
+    let timezone = req.get_dvizh_repo().await.get_chat_timezone(chat_id)?;
+    if let Err(error) = validate_date_poll_options(&args, &timezone) {
+        let text = req
+            .get_translation_for(validation_error_to_key(&error))
+            .await?;
+        req.set_msg_text(&text.expect_text()?);
+        return send_msg(offset, req).await;
+    }
+    // Options such as `friday 19:00` are posted as the dates they were read as
+    let args = args
+        .into_iter()
+        .map(|option| match validate_event_datetime(&option, &timezone) {
+            Ok(date_time) if is_natural_date(&option) => {
+                let date =
+                    format_event_datetime(date_time.start, date_time.end, &date_time.timezone);
+                if date_time.timezone == timezone_or_default(&timezone) {
+                    date
+                } else {
+                    format!("{date} {}", date_time.timezone.name())
+                }
+            }
+            _ => option,
+        })
+        .collect::<Vec<String>>();
+
+    let close = req.get_translation_for("button_close_poll").await?;
+    let keyboard = json!({
+        "inline_keyboard": [[
+            { "text": close.expect_text()?, "callback_data": "datepoll_close" }
+        ]]
+    })
+    .to_string();
+
+    req.set_msg_text(&title);
+    let response = send_poll_msg(&args, &keyboard, offset, req).await?;
+    req.method = MsgType::SendMessage;
+    let poll_id = match response["result"]["poll"]["id"].as_str() {
+        Some(poll_id) => poll_id.to_string(),
+        None => {
+            debug!("Could not send date poll: {response}");
+            let text = req.get_translation_for("error_date_poll_failed").await?;
+            req.set_msg_text(&text.expect_text()?);
+            return send_msg(offset, req).await;
+        }
+    };
+
+    let poll = DatePoll {
+        id: 0,
+        poll_id,
+        group_id: chat_id,
+        message_id: req.get_msg().message_id,
+        title,
+        timezone,
+        options: args,
+        created_by: user_id,
+        closed: false,
+        event_id: None,
+    };
+    req.get_dvizh_repo().await.add_date_poll(&poll)?;
+    Ok(response)
+}
+
+/// Keeps the votes of a date poll up to date from a `poll_answer` update.
+pub async fn handle_poll_answer(app: &Application, poll_answer: &serde_json::Value) -> Result<()> {
+    let poll_id = poll_answer["poll_id"].as_str().unwrap_or_default();
+    // Answers of anonymous chats have no user
+    let user_id = match poll_answer["user"]["id"].as_i64() {
+        Some(user_id) => user_id,
+        None => return Ok(()),
+    };
+    let positions = poll_answer["option_ids"]
+        .as_array()
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|id| id.as_i64())
+                .collect::<Vec<i64>>()
+        })
+        .unwrap_or_default();
+
+    let repo = app.dvizh_repo.lock().await;
+    match repo.find_date_poll_by_poll_id(poll_id)? {
+        Some(poll) if !poll.closed => repo.set_date_poll_votes(poll.id, user_id, &positions)?,
+        _ => debug!("Ignoring answer to poll {poll_id}"),
+    }
+    Ok(())
+}
+
+/// Handles the buttons of date polls.
+/// Callback data is `datepoll_close` below the poll, which stops it and offers
+/// the dates with the most votes, and `datepoll_create_{poll id}_{position}`
+/// creating the event on the chosen date.
+pub async fn handle_date_poll_callback(
+    callback_query: &serde_json::Value,
+    callback_data: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let user_id = callback_query["from"]["id"].as_i64().unwrap_or_default();
+    let chat_id = req.get_msg().chat.id;
+    let parts = callback_data.split('_').collect::<Vec<&str>>();
+    let action = parts.get(1).copied().unwrap_or_default();
+
+    let poll = match action {
+        "close" => {
+            let message_id = req.get_msg().message_id;
+            req.get_dvizh_repo()
+                .await
+                .find_date_poll_by_message(chat_id, message_id)?
+                .filter(|poll| !poll.closed)
+        }
+        "create" => {
+            let id = parts
+                .get(2)
+                .and_then(|id| id.parse::<i64>().ok())
+                .unwrap_or_default();
+            req.get_dvizh_repo()
+                .await
+                .get_date_poll(id)?
+                .filter(|poll| poll.event_id.is_none())
+        }
+        _ => {
+            debug!("Unknown date poll callback {callback_data}");
+            return Ok(());
+        }
+    };
+    // Another admin may have handled the poll already
+    let poll = match poll {
+        Some(poll) => poll,
+        None => {
+            let text = req.get_translation_for("error_date_poll_done").await?;
+            req.set_msg_text(&text.expect_text()?);
+            send_msg(offset, req).await?;
+            return Ok(());
+        }
+    };
+
+    if req
+        .get_dvizh_repo()
+        .await
+        .is_not_admin(user_id, poll.group_id)?
+    {
+        let text = req.get_translation_for("error_not_admin").await?;
+        req.set_msg_text(&text.expect_text()?);
+        send_msg(offset, req).await?;
+        return Ok(());
+    }
+
+    if action == "close" {
+        return close_date_poll(&poll, offset, req).await;
+    }
+    let position = parts
+        .get(3)
+        .and_then(|position| position.parse::<usize>().ok())
+        .unwrap_or_default();
+    create_poll_event(&poll, position, offset, req).await
+}
+
+/// Stops the poll and sends the vote counts with a button for every date
+/// that got the most votes.
+async fn close_date_poll(poll: &DatePoll, offset: &mut i64, req: &mut MsgRequest) -> Result<()> {
+    req.get_dvizh_repo().await.close_date_poll(poll.id)?;
+    stop_poll(offset, req).await?;
+    req.method = MsgType::SendMessage;
+
+    let counts = req.get_dvizh_repo().await.get_date_poll_vote_counts(poll)?;
+    let votes = poll
+        .options
+        .iter()
+        .zip(&counts)
+        .map(|(option, count)| format!("{option} — {count}"))
+        .collect::<Vec<String>>();
+    let closed = req.get_translation_for("date_poll_closed").await?;
+    let text = format!(
+        "{}\n{}",
+        closed.expect_text()?.replace("{title}", &poll.title),
+        votes.join("\n")
+    );
+
+    let most_votes = counts.iter().copied().max().unwrap_or_default();
+    if most_votes == 0 {
+        let no_votes = req.get_translation_for("date_poll_no_votes").await?;
+        req.set_msg_text(&format!("{text}\n\n{}", no_votes.expect_text()?));
+        send_msg(offset, req).await?;
+        return Ok(());
+    }
+
+    // Ties are left to the admin
+    let winners = counts
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count == most_votes)
+        .map(|(position, _)| (position, poll.options[position].as_str()))
+        .collect::<Vec<(usize, &str)>>();
+    let create = req
+        .get_translation_for("button_create_event")
+        .await?
+        .expect_text()?;
+    let rows = winners
+        .iter()
+        .map(|(position, option)| {
+            json!([{
+                "text": create.replace("{date}", option),
+                "callback_data": format!("datepoll_create_{}_{position}", poll.id)
+            }])
+        })
+        .collect::<Vec<_>>();
+    let dates = winners
+        .iter()
+        .map(|(_, option)| *option)
+        .collect::<Vec<&str>>();
+
+    let winner = req.get_translation_for("date_poll_winner").await?;
+    req.set_msg_text(&format!(
+        "{text}\n\n{}",
+        winner.expect_text()?.replace("{dates}", &dates.join(", "))
+    ));
+    send_keyboard_msg(&json!({ "inline_keyboard": rows }).to_string(), offset, req).await?;
+    Ok(())
+}
+
+/// Creates the event of the poll on the date at `position` and shows its card
+/// in place of the offer.
+async fn create_poll_event(
+    poll: &DatePoll,
+    position: usize,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let date_time = match poll
+        .options
+        .get(position)
+        .ok_or(ValidationError::InvalidEventDate)
+        .and_then(|option| validate_event_datetime(option, &poll.timezone))
+    {
+        Ok(date_time) => date_time,
+        Err(error) => {
+            let text = req
+                .get_translation_for(validation_error_to_key(&error))
+                .await?;
+            req.set_msg_text(&text.expect_text()?);
+            edit_msg_and_remove_keyboard(offset, req).await?;
+            return Ok(());
+        }
+    };
+
+    let mut event = Event::new(
+        poll.group_id,
+        poll.title.clone(),
+        date_time.start,
+        date_time.end,
+        date_time.timezone.name().to_string(),
+        String::new(),
+        String::new(),
+    );
+    event.created_by = Some(poll.created_by);
+    event.id = {
+        let repo = req.get_dvizh_repo().await;
+        let event_id = repo.add_event(event.clone())?;
+        repo.set_date_poll_event(poll.id, event_id)?;
+        event_id
+    };
+
+    let header = req.get_translation_for("event_new").await?;
+    let (card, keyboard) = build_event_card(&req.app, &event).await?;
+    req.set_msg_text(&format!("{}\n\n{card}", header.expect_text()?));
+    edit_keyboard_msg(&keyboard, offset, req).await?;
+    Ok(())
+}
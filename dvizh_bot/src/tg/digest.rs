@@ -0,0 +1,206 @@
+use crate::application::Application;
+use crate::chat_settings::{is_enabled, ChatSetting};
+use crate::db::db_objects::{RsvpStatus, User};
+use crate::tg::birthday_list::upcoming_birthdays;
+use crate::tg::event_utils::{format_event_start, occurrence_key};
+use crate::tg::language_utils::chat_translation;
+use crate::tg::messaging::send_request;
+use crate::tg::msg_type_utils::{msg_type_to_str, MsgType};
+use crate::time_utils::{day_bounds_utc, timezone_or_default, DATE_FORMAT};
+use crate::validations::validate_digest_schedule;
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use log::{debug, error};
+use std::collections::HashMap;
+
+/// Number of days covered by the weekly digest, starting with the day it is posted.
+static DIGEST_DAYS: i64 = 7;
+
+/// Posts the weekly digest in every chat whose `digest` setting is due.
+/// A digest missed while the bot was offline is still posted later the same day.
+pub async fn perform_weekly_digest(app: &Application) -> Result<()> {
+    let now = Utc::now();
+    let chats = app.dvizh_repo.lock().await.get_all_chat_ids()?;
+    for chat_id in chats {
+        if let Err(e) = post_weekly_digest(app, chat_id, now).await {
+            error!("Failed to post weekly digest in {chat_id}: {e}");
+        }
+    }
+    Ok(())
+}
+
+/// Posts the weekly digest of the chat when it is due.
+/// The digest counts as sent only once Telegram accepts it, otherwise it is tried again.
+async fn post_weekly_digest(app: &Application, chat_id: i64, now: DateTime<Utc>) -> Result<()> {
+    let (schedule, pin, timezone) = {
+        let dvizh_repo = app.dvizh_repo.lock().await;
+        (
+            dvizh_repo.get_chat_setting(chat_id, &ChatSetting::Digest)?,
+            dvizh_repo.get_chat_setting(chat_id, &ChatSetting::DigestPin)?,
+            dvizh_repo.get_chat_timezone(chat_id)?,
+        )
+    };
+    // `off` is not a schedule
+    let (weekday, time) = match validate_digest_schedule(&schedule) {
+        Ok(schedule) => schedule,
+        Err(_) => return Ok(()),
+    };
+
+    let local = now.with_timezone(&timezone_or_default(&timezone));
+    let today = local.date_naive();
+    if today.weekday() != weekday
+        || local.time() < time
+        || app.dvizh_repo.lock().await.is_digest_sent(chat_id, today)?
+    {
+        return Ok(());
+    }
+
+    let text = build_weekly_digest(app, chat_id, today).await?;
+    let mut params = HashMap::new();
+    params.insert("chat_id", chat_id.to_string());
+    params.insert("text", text);
+    let response = send_request(
+        &app.client,
+        &app.tg_token,
+        msg_type_to_str(&MsgType::SendMessage),
+        params,
+    )
+    .await?;
+    let message_id = match response["result"]["message_id"].as_i64() {
+        Some(message_id) if response["ok"].as_bool().unwrap_or_default() => message_id,
+        _ => {
+            debug!("Could not send weekly digest to {chat_id}: {response}");
+            return Ok(());
+        }
+    };
+
+    let previous = {
+        let dvizh_repo = app.dvizh_repo.lock().await;
+        let previous = dvizh_repo.get_last_digest_message_id(chat_id)?;
+        dvizh_repo.mark_digest_sent(chat_id, today, message_id)?;
+        previous
+    };
+    if is_enabled(&pin) {
+        pin_digest(app, chat_id, previous, message_id).await?;
+    }
+    Ok(())
+}
+
+/// Builds the digest of the events and birthdays of the chat in the week starting `today`.
+/// Events show how many users are going and may go.
+pub async fn build_weekly_digest(
+    app: &Application,
+    chat_id: i64,
+    today: NaiveDate,
+) -> Result<String> {
+    let last_day = today + Duration::days(DIGEST_DAYS - 1);
+    let (events, timezone) = {
+        let dvizh_repo = app.dvizh_repo.lock().await;
+        (
+            dvizh_repo.get_upcoming_events_for_chat(chat_id)?,
+            dvizh_repo.get_chat_timezone(chat_id)?,
+        )
+    };
+    let (_, week_end) = day_bounds_utc(&timezone_or_default(&timezone), last_day);
+
+    let going = chat_translation(app, chat_id, "rsvp_going").await?;
+    let going_icon = going.split_whitespace().next().unwrap_or_default();
+    let maybe = chat_translation(app, chat_id, "rsvp_maybe").await?;
+    let maybe_icon = maybe.split_whitespace().next().unwrap_or_default();
+
+    let mut event_lines = Vec::new();
+    for event in events.iter().filter(|event| event.start < week_end) {
+        let attendance = app
+            .dvizh_repo
+            .lock()
+            .await
+            .get_attendance(event.id, &occurrence_key(event))?;
+        let count = |status: RsvpStatus| {
+            attendance
+                .iter()
+                .filter(|attendance| attendance.status == status)
+                .count()
+        };
+        let going_count = match event.capacity {
+            Some(capacity) => format!("{}/{capacity}", count(RsvpStatus::Going)),
+            None => count(RsvpStatus::Going).to_string(),
+        };
+        event_lines.push(format!(
+            "• {} — {} · {going_icon} {going_count} · {maybe_icon} {}",
+            format_event_start(event),
+            event.title,
+            count(RsvpStatus::Maybe)
+        ));
+    }
+
+    let header = chat_translation(app, chat_id, "weekly_digest")
+        .await?
+        .replace("{from}", &today.format(DATE_FORMAT).to_string())
+        .replace("{to}", &last_day.format(DATE_FORMAT).to_string());
+    let events_text = if event_lines.is_empty() {
+        chat_translation(app, chat_id, "digest_no_events").await?
+    } else {
+        format!(
+            "{}\n{}",
+            chat_translation(app, chat_id, "digest_events").await?,
+            event_lines.join("\n")
+        )
+    };
+    let mut text = format!("{header}\n\n{events_text}");
+
+    let birthdays = upcoming_birthdays(app, chat_id, today)
+        .await?
+        .into_iter()
+        .filter(|(date, _)| *date <= last_day)
+        .collect::<Vec<(NaiveDate, User)>>();
+    if !birthdays.is_empty() {
+        let birthday_lines = birthdays
+            .iter()
+            .map(|(date, user)| format!("• {} {}", date.format("%d.%m"), user.display_name()))
+            .collect::<Vec<String>>();
+        text = format!(
+            "{text}\n\n{}\n{}",
+            chat_translation(app, chat_id, "digest_birthdays").await?,
+            birthday_lines.join("\n")
+        );
+    }
+    Ok(text)
+}
+
+/// Pins the new digest silently and unpins the `previous` one.
+/// Failures are only logged, the bot may lack the right to pin messages.
+async fn pin_digest(
+    app: &Application,
+    chat_id: i64,
+    previous: Option<i64>,
+    message_id: i64,
+) -> Result<()> {
+    if let Some(previous) = previous.filter(|previous| *previous != 0) {
+        let mut params = HashMap::new();
+        params.insert("chat_id", chat_id.to_string());
+        params.insert("message_id", previous.to_string());
+        send_request(
+            &app.client,
+            &app.tg_token,
+            msg_type_to_str(&MsgType::UnpinChatMessage),
+            params,
+        )
+        .await?;
+    }
+
+    let mut params = HashMap::new();
+    params.insert("chat_id", chat_id.to_string());
+    params.insert("message_id", message_id.to_string());
+    params.insert("disable_notification", "true".to_string());
+    let response = send_request(
+        &app.client,
+        &app.tg_token,
+        msg_type_to_str(&MsgType::PinChatMessage),
+        params,
+    )
+    .await?;
+    if !response["ok"].as_bool().unwrap_or_default() {
+        debug!("Could not pin weekly digest in {chat_id}: {response}");
+    }
+    Ok(())
+}
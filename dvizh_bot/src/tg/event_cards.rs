@@ -0,0 +1,78 @@
+use crate::application::Application;
+use crate::chat_settings::{is_enabled, ChatSetting};
+use crate::db::db_objects::{rsvp_status_to_str, Event, RsvpStatus, RSVP_STATUSES};
+use crate::tg::event_utils::{event_callback_data, fill_event_template, occurrence_key};
+use crate::tg::language_utils::chat_translation;
+use anyhow::Result;
+use serde_json::json;
+
+/// Builds the text and the inline keyboard of an event card.
+/// The text is the filled `event_template` followed by the RSVP counts and,
+/// when the `rsvp_names` chat setting is on, the names of the attendees.
+/// Events with a capacity also show the number of places and the waitlist.
+pub async fn build_event_card(app: &Application, event: &Event) -> Result<(String, String)> {
+    let template = chat_translation(app, event.group_id, "event_template").await?;
+    let attendance = app
+        .dvizh_repo
+        .lock()
+        .await
+        .get_attendance(event.id, &occurrence_key(event))?;
+    let show_names = is_enabled(
+        &app.dvizh_repo
+            .lock()
+            .await
+            .get_chat_setting(event.group_id, &ChatSetting::RsvpNames)?,
+    );
+
+    let mut summary = Vec::new();
+    let mut buttons = Vec::new();
+    for status in RSVP_STATUSES.iter().chain(&[RsvpStatus::Waitlisted]) {
+        let action = format!("rsvp_{}", rsvp_status_to_str(status));
+        let label = chat_translation(app, event.group_id, &action).await?;
+        let names = attendance
+            .iter()
+            .filter(|attendance| attendance.status == *status)
+            .map(|attendance| attendance.first_name.as_str())
+            .collect::<Vec<&str>>();
+        let count = match (status, event.capacity) {
+            (RsvpStatus::Going, Some(capacity)) => format!("{}/{capacity}", names.len()),
+            (RsvpStatus::Waitlisted, None) => continue,
+            _ => names.len().to_string(),
+        };
+
+        if show_names && !names.is_empty() {
+            summary.push(format!("{label} ({count}): {}", names.join(", ")));
+        } else {
+            summary.push(format!("{label}: {count}"));
+        }
+        // Users are waitlisted by the bot, not by a button
+        if RSVP_STATUSES.contains(status) {
+            buttons.push(json!({
+                "text": label,
+                "callback_data": event_callback_data(&action, event)
+            }));
+        }
+    }
+
+    let separator = if show_names { "\n" } else { " · " };
+    let text = format!(
+        "{}\n{}",
+        fill_event_template(&template, event),
+        summary.join(separator)
+    );
+
+    let attendees = chat_translation(app, event.group_id, "button_attendees").await?;
+    let calendar = chat_translation(app, event.group_id, "button_add_to_calendar").await?;
+    let keyboard = json!({
+        "inline_keyboard": [
+            buttons,
+            [
+                { "text": attendees, "callback_data": event_callback_data("attendees", event) },
+                { "text": calendar, "callback_data": event_callback_data("ics", event) }
+            ]
+        ]
+    })
+    .to_string();
+
+    Ok((text, keyboard))
+}
@@ -0,0 +1,255 @@
+use crate::db::db_objects::Event;
+use crate::db::repository::DvizhRepository;
+use crate::ics::{parse_ics, parse_own_uid, ImportedEvent};
+use crate::tg::event_utils::format_event_date;
+use crate::tg::messaging::{edit_msg_and_remove_keyboard, send_keyboard_msg, send_msg};
+use crate::tg::msg_request::MsgRequest;
+use crate::tg::tg_objects::Document;
+use crate::tg::tg_utils::download_file;
+use anyhow::Result;
+use log::{debug, error};
+use serde_json::json;
+
+/// Largest iCalendar file the bot downloads, in bytes.
+static MAX_ICS_FILE_SIZE: i64 = 1024 * 1024;
+/// How many entries the import preview lists before summarizing the rest.
+static MAX_PREVIEW_LINES: usize = 30;
+
+pub fn is_ics_document(document: &Document) -> bool {
+    document.mime_type.as_deref() == Some("text/calendar")
+        || document
+            .file_name
+            .as_deref()
+            .is_some_and(|name| name.to_lowercase().ends_with(".ics"))
+}
+
+/// Reads the VEVENTs of an `.ics` document and previews which events it creates or updates.
+/// The events are saved once the admin confirms with the `import_confirm` button.
+pub async fn handle_import_document(
+    document: Document,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    debug!("Import events from {document:?}");
+    let chat_id = req.get_msg().chat.id;
+    let user_id = req.get_msg().from.id;
+
+    if req.get_dvizh_repo().await.is_not_admin(user_id, chat_id)? {
+        let text = req.get_translation_for("error_not_admin").await?;
+        req.set_msg_text(&text.expect_text()?);
+        return send_msg(offset, req).await;
+    }
+
+    if document.file_size.unwrap_or_default() > MAX_ICS_FILE_SIZE {
+        let text = req.get_translation_for("error_ics_too_large").await?;
+        req.set_msg_text(&text.expect_text()?);
+        return send_msg(offset, req).await;
+    }
+
+    let content = download_file(&req.app.client, &req.app.tg_token, &document.file_id).await?;
+    let timezone = req.get_dvizh_repo().await.get_chat_timezone(chat_id)?;
+    let entries = parse_ics(&String::from_utf8_lossy(&content), chat_id, &timezone);
+
+    let mut lines = Vec::new();
+    let mut events = Vec::new();
+    for entry in entries {
+        match entry {
+            Ok(imported) => {
+                let existing =
+                    find_existing_event(&*req.get_dvizh_repo().await, chat_id, &imported.uid)?;
+                let mark = if existing.is_some() { "✏️" } else { "➕" };
+                lines.push(format!(
+                    "{mark} {} ({})",
+                    imported.event.title,
+                    format_event_date(&imported.event)
+                ));
+                events.push(imported);
+            }
+            Err(import_error) => {
+                let text = req
+                    .get_translation_for(&import_error.error_key)
+                    .await?
+                    .expect_text()?;
+                lines.push(format!("⚠️ {}: {text}", import_error.entry));
+            }
+        }
+    }
+
+    if lines.len() > MAX_PREVIEW_LINES {
+        let more = req
+            .get_translation_for("import_more")
+            .await?
+            .expect_text()?
+            .replace("{count}", &(lines.len() - MAX_PREVIEW_LINES).to_string());
+        lines.truncate(MAX_PREVIEW_LINES);
+        lines.push(more);
+    }
+
+    let header = req
+        .get_translation_for("import_preview")
+        .await?
+        .expect_text()?;
+    if events.is_empty() {
+        let nothing = req
+            .get_translation_for("import_nothing")
+            .await?
+            .expect_text()?;
+        req.set_msg_text(&format!("{header}\n{}\n\n{nothing}", lines.join("\n")));
+        return send_msg(offset, req).await;
+    }
+
+    req.app
+        .pending_imports
+        .write()
+        .await
+        .insert((chat_id, user_id), events);
+
+    let import = req
+        .get_translation_for("button_import")
+        .await?
+        .expect_text()?;
+    let cancel = req
+        .get_translation_for("button_cancel")
+        .await?
+        .expect_text()?;
+    let keyboard = json!({
+        "inline_keyboard": [
+            [
+                { "text": import, "callback_data": "import_confirm" },
+                { "text": cancel, "callback_data": "import_cancel" }
+            ]
+        ]
+    })
+    .to_string();
+
+    req.set_msg_text(&format!("{header}\n{}", lines.join("\n")));
+    send_keyboard_msg(&keyboard, offset, req).await
+}
+
+/// Saves or drops the import previewed for the user who pressed the button.
+/// Callback data is `import_confirm` or `import_cancel`.
+pub async fn handle_import_callback(
+    callback_query: &serde_json::Value,
+    callback_data: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let chat_id = req.get_msg().chat.id;
+    let user_id = callback_query["from"]["id"].as_i64().unwrap_or_default();
+
+    let pending = req
+        .app
+        .pending_imports
+        .write()
+        .await
+        .remove(&(chat_id, user_id));
+    let events = match pending {
+        Some(events) => events,
+        None => {
+            let text = req.get_translation_for("error_import_expired").await?;
+            req.set_msg_text(&text.expect_text()?);
+            send_msg(offset, req).await?;
+            return Ok(());
+        }
+    };
+
+    if callback_data == "import_cancel" {
+        let text = req.get_translation_for("cancelled").await?;
+        req.set_msg_text(&text.expect_text()?);
+        edit_msg_and_remove_keyboard(offset, req).await?;
+        return Ok(());
+    }
+
+    if req.get_dvizh_repo().await.is_not_admin(user_id, chat_id)? {
+        let text = req.get_translation_for("error_not_admin").await?;
+        req.set_msg_text(&text.expect_text()?);
+        send_msg(offset, req).await?;
+        return Ok(());
+    }
+
+    let mut created = 0;
+    let mut updated = 0;
+    let mut failures = Vec::new();
+    for imported in events {
+        let title = imported.event.title.clone();
+        let result = save_imported_event(&*req.get_dvizh_repo().await, imported, chat_id, user_id);
+        match result {
+            Ok(true) => created += 1,
+            Ok(false) => updated += 1,
+            Err(e) => {
+                error!("Failed to import event {title}: {e}");
+                let text = req
+                    .get_translation_for("error_ics_save_failed")
+                    .await?
+                    .expect_text()?;
+                failures.push(format!("⚠️ {title}: {text}"));
+            }
+        }
+    }
+
+    let mut text = req
+        .get_translation_for("import_done")
+        .await?
+        .expect_text()?
+        .replace("{created}", &created.to_string())
+        .replace("{updated}", &updated.to_string());
+    if !failures.is_empty() {
+        text = format!("{text}\n{}", failures.join("\n"));
+    }
+    req.set_msg_text(&text);
+    edit_msg_and_remove_keyboard(offset, req).await?;
+    Ok(())
+}
+
+/// Returns the event of the chat that `uid` was exported from or imported as before.
+fn find_existing_event(
+    dvizh_repo: &DvizhRepository,
+    chat_id: i64,
+    uid: &str,
+) -> Result<Option<Event>> {
+    if let Some(event_id) = parse_own_uid(uid) {
+        let event = dvizh_repo.get_event(event_id)?;
+        if let Some(event) = event.filter(|event| event.group_id == chat_id) {
+            return Ok(Some(event));
+        }
+    }
+
+    match dvizh_repo.find_event_id_by_uid(chat_id, uid)? {
+        Some(event_id) => dvizh_repo.get_event(event_id),
+        None => Ok(None),
+    }
+}
+
+/// Creates the imported event or updates the existing one with the same UID.
+/// Returns whether a new event was created.
+fn save_imported_event(
+    dvizh_repo: &DvizhRepository,
+    imported: ImportedEvent,
+    chat_id: i64,
+    user_id: i64,
+) -> Result<bool> {
+    let new_event = imported.event;
+    match find_existing_event(dvizh_repo, chat_id, &imported.uid)? {
+        Some(mut event) => {
+            event.title = new_event.title;
+            event.start = new_event.start;
+            event.end = new_event.end;
+            event.timezone = new_event.timezone;
+            event.location = new_event.location;
+            event.description = new_event.description;
+            event.recurrence = new_event.recurrence;
+            event.recurrence_until = new_event.recurrence_until;
+            dvizh_repo.update_event(&event)?;
+            for date in new_event.skipped_dates {
+                dvizh_repo.skip_event_occurrence(event.id, date)?;
+            }
+            Ok(false)
+        }
+        None => {
+            let mut event = new_event;
+            event.created_by = Some(user_id);
+            dvizh_repo.add_event(event)?;
+            Ok(true)
+        }
+    }
+}
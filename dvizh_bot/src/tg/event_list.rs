@@ -0,0 +1,219 @@
+use crate::application::Application;
+use crate::db::db_objects::{Event, RsvpStatus};
+use crate::event_filter::{event_filter_to_code, EventFilter};
+use crate::tg::event_cards::build_event_card;
+use crate::tg::event_utils::{event_callback_data, format_event_start, occurrence_key};
+use crate::tg::language_utils::chat_translation;
+use crate::time_utils::{timezone_or_default, today_in, DATE_FORMAT};
+use anyhow::Result;
+use serde_json::{json, Value};
+
+/// How many events a page of the event list shows.
+static PAGE_SIZE: usize = 5;
+
+/// Which events an event list pages through.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventList {
+    Upcoming,
+    Past,
+    /// Events matching the words of the search text.
+    Search(String),
+}
+
+/// Returns the code of `list` used in callback data.
+/// The text of a search is kept in `Application::event_searches` instead.
+pub fn event_list_to_code(list: &EventList) -> &'static str {
+    match list {
+        EventList::Upcoming => "up",
+        EventList::Past => "past",
+        EventList::Search(_) => "search",
+    }
+}
+
+/// Builds the text and the inline keyboard of one page of the event list.
+/// Every event gets a compact line and a button expanding its details,
+/// `page` is clamped to the existing pages.
+pub async fn build_event_list_page(
+    app: &Application,
+    chat_id: i64,
+    list: &EventList,
+    filter: &EventFilter,
+    page: usize,
+) -> Result<(String, String)> {
+    let (events, timezone) = {
+        let repo = app.dvizh_repo.lock().await;
+        let events = match list {
+            EventList::Upcoming => repo.get_events_for_filter(chat_id, filter)?,
+            EventList::Past => repo.get_past_events(chat_id, filter)?,
+            EventList::Search(text) => repo.search_events(chat_id, text, filter)?,
+        };
+        (events, repo.get_chat_timezone(chat_id)?)
+    };
+
+    let (title_key, empty_key) = match list {
+        EventList::Upcoming => ("upcoming_event", "no_upcoming_event"),
+        EventList::Past => ("past_events", "no_past_events"),
+        EventList::Search(_) => ("search_results", "no_search_results"),
+    };
+    let mut text = chat_translation(app, chat_id, title_key).await?;
+    if let EventList::Search(query) = list {
+        text = text.replace("{query}", query);
+    }
+
+    let today = today_in(&timezone_or_default(&timezone));
+    let bounds = match list {
+        EventList::Past => filter.past_bounds(today),
+        _ => filter.bounds(today),
+    };
+    if let Some((from, to)) = bounds {
+        text = format!(
+            "{text}\n{} – {}",
+            from.format(DATE_FORMAT),
+            to.format(DATE_FORMAT)
+        );
+    }
+
+    if events.is_empty() {
+        let empty = chat_translation(app, chat_id, empty_key).await?;
+        let keyboard = json!({ "inline_keyboard": [] }).to_string();
+        return Ok((format!("{text}\n\n{empty}"), keyboard));
+    }
+
+    let pages = events.len().div_ceil(PAGE_SIZE);
+    let page = page.clamp(1, pages);
+    let going = chat_translation(app, chat_id, "rsvp_going").await?;
+    let going_icon = going.split_whitespace().next().unwrap_or_default();
+
+    let mut lines = Vec::new();
+    let mut rows = Vec::new();
+    for (index, event) in events
+        .iter()
+        .enumerate()
+        .skip((page - 1) * PAGE_SIZE)
+        .take(PAGE_SIZE)
+    {
+        let going_count = app
+            .dvizh_repo
+            .lock()
+            .await
+            .get_attendance(event.id, &occurrence_key(event))?
+            .iter()
+            .filter(|attendance| attendance.status == RsvpStatus::Going)
+            .count();
+        lines.push(format!(
+            "{}. {} — {} · {going_icon} {going_count}",
+            index + 1,
+            event.title,
+            format_event_start(event)
+        ));
+        rows.push(vec![json!({
+            "text": format!("{}. {}", index + 1, event.title),
+            "callback_data": event_callback_data(
+                &format!(
+                    "listshow_{}_{}_{page}",
+                    event_list_to_code(list),
+                    event_filter_to_code(filter)
+                ),
+                event
+            )
+        })]);
+    }
+
+    let mut navigation = Vec::new();
+    if page > 1 {
+        let prev = chat_translation(app, chat_id, "button_prev").await?;
+        navigation.push(
+            json!({ "text": prev, "callback_data": list_callback_data(list, filter, page - 1) }),
+        );
+    }
+    if page < pages {
+        let next = chat_translation(app, chat_id, "button_next").await?;
+        navigation.push(
+            json!({ "text": next, "callback_data": list_callback_data(list, filter, page + 1) }),
+        );
+    }
+    if !navigation.is_empty() {
+        rows.push(navigation);
+    }
+
+    let page_text = chat_translation(app, chat_id, "events_page")
+        .await?
+        .replace("{page}", &page.to_string())
+        .replace("{pages}", &pages.to_string());
+    let text = format!("{text}\n\n{}\n\n{page_text}", lines.join("\n"));
+
+    Ok((text, json!({ "inline_keyboard": rows }).to_string()))
+}
+
+/// Builds the event card shown in place of the list, with buttons sending
+/// its poster and venue and a button going back to `page` of the list.
+pub async fn build_event_list_details(
+    app: &Application,
+    event: &Event,
+    list: &EventList,
+    filter: &EventFilter,
+    page: usize,
+) -> Result<(String, String)> {
+    let (text, keyboard) = build_event_card(app, event).await?;
+    let mut rows = Vec::new();
+
+    // The list is a text message, so the poster and the venue are sent on demand
+    let mut media = Vec::new();
+    if event.poster.is_some() {
+        let poster = chat_translation(app, event.group_id, "button_show_poster").await?;
+        media
+            .push(json!({ "text": poster, "callback_data": event_callback_data("poster", event) }));
+    }
+    if event.coordinates.is_some() {
+        let venue = chat_translation(app, event.group_id, "button_show_venue").await?;
+        media.push(json!({ "text": venue, "callback_data": event_callback_data("venue", event) }));
+    }
+    if !media.is_empty() {
+        rows.push(json!(media));
+    }
+
+    let back = chat_translation(app, event.group_id, "button_back").await?;
+    rows.push(json!([{ "text": back, "callback_data": list_callback_data(list, filter, page) }]));
+
+    Ok((text, append_keyboard_rows(&keyboard, rows)))
+}
+
+/// Builds `list_{list}_{filter}_{page}` callback data.
+pub fn list_callback_data(list: &EventList, filter: &EventFilter, page: usize) -> String {
+    format!(
+        "list_{}_{}_{page}",
+        event_list_to_code(list),
+        event_filter_to_code(filter)
+    )
+}
+
+/// Returns the rows of `reply_markup` navigating the event list,
+/// so they survive when an expanded event card is rebuilt.
+pub fn list_keyboard_rows(reply_markup: Option<&Value>) -> Vec<Value> {
+    reply_markup
+        .and_then(|markup| markup["inline_keyboard"].as_array())
+        .map(|rows| {
+            rows.iter()
+                .filter(|row| {
+                    row.as_array().is_some_and(|buttons| {
+                        buttons.iter().any(|button| {
+                            button["callback_data"]
+                                .as_str()
+                                .is_some_and(|data| data.starts_with("list_"))
+                        })
+                    })
+                })
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Appends `rows` to the serialized inline keyboard `keyboard`.
+pub fn append_keyboard_rows(keyboard: &str, rows: Vec<Value>) -> String {
+    let mut keyboard = serde_json::from_str::<Value>(keyboard).unwrap_or_default();
+    if let Some(inline_keyboard) = keyboard["inline_keyboard"].as_array_mut() {
+        inline_keyboard.extend(rows);
+    }
+    keyboard.to_string()
+}
@@ -0,0 +1,215 @@
+use crate::application::Application;
+use crate::db::db_objects::Event;
+use crate::tg::event_cards::build_event_card;
+use crate::tg::event_utils::{parse_occurrence_date, EventField};
+use crate::tg::messaging::{send_msg, send_reply_msg, send_request};
+use crate::tg::msg_request::MsgRequest;
+use crate::tg::msg_type_utils::{msg_type_to_str, MsgType};
+use crate::tg::pending_input::{handle_pending_input, PendingInput};
+use crate::tg::tg_objects::PhotoSize;
+use anyhow::Result;
+use log::debug;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Sends the card of `event` to `chat_id` as the caption of its poster,
+/// followed by its venue when the event has coordinates.
+/// Returns the response of the card message.
+pub async fn send_event_card(
+    app: &Application,
+    chat_id: i64,
+    event: &Event,
+    text: &str,
+    keyboard: Option<&str>,
+) -> Result<Value> {
+    let response = send_card_message(app, chat_id, event, text, keyboard).await?;
+    if response["ok"].as_bool().unwrap_or_default() {
+        send_event_venue(app, chat_id, event).await?;
+    }
+    Ok(response)
+}
+
+/// Sends the venue of `event` so that it opens in a maps app.
+/// Events without coordinates have no venue.
+pub async fn send_event_venue(app: &Application, chat_id: i64, event: &Event) -> Result<()> {
+    let (latitude, longitude) = match event.coordinates {
+        Some(coordinates) => coordinates,
+        None => return Ok(()),
+    };
+    let address = match event.location.as_str() {
+        "" => format!("{latitude}, {longitude}"),
+        location => location.to_string(),
+    };
+
+    let mut params = HashMap::new();
+    params.insert("chat_id", chat_id.to_string());
+    params.insert("latitude", latitude.to_string());
+    params.insert("longitude", longitude.to_string());
+    params.insert("title", event.title.clone());
+    params.insert("address", address);
+    let response = send_request(
+        &app.client,
+        &app.tg_token,
+        msg_type_to_str(&MsgType::SendVenue),
+        params,
+    )
+    .await?;
+    if !response["ok"].as_bool().unwrap_or_default() {
+        debug!("Could not send venue of event {}: {response}", event.id);
+    }
+    Ok(())
+}
+
+/// Returns the file id of the largest size of a photo.
+pub fn largest_photo_id(photo: &[PhotoSize]) -> String {
+    photo
+        .iter()
+        .max_by_key(|size| size.width * size.height)
+        .map(|size| size.file_id.clone())
+        .unwrap_or_default()
+}
+
+/// Uses a photo as the poster of an event, either when the event editor waits for it
+/// or when an admin replies with it to the card of the event.
+/// Other photos are left alone.
+pub async fn handle_event_photo(
+    photo: Vec<PhotoSize>,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<Value> {
+    let chat_id = req.get_msg().chat.id;
+    let user_id = req.get_msg().from.id;
+
+    match req.take_pending_input().await {
+        Some(
+            pending @ PendingInput::EditEventField {
+                field: EventField::Poster,
+                ..
+            },
+        ) => return handle_pending_input(pending, offset, req).await,
+        // Other fields keep waiting for their text
+        Some(pending) => req.set_pending_input(chat_id, user_id, pending).await,
+        None => {}
+    }
+
+    let event_id = req
+        .get_msg()
+        .reply_to_message
+        .as_ref()
+        .and_then(|reply| card_event_id(reply.reply_markup.as_ref()));
+    let event = match event_id {
+        Some(event_id) => req.get_dvizh_repo().await.get_event(event_id)?,
+        None => None,
+    };
+    let mut event = match event {
+        Some(event) if event.group_id == chat_id => event,
+        _ => return Ok(Value::Null),
+    };
+
+    let user_id = req.get_msg().from.id;
+    if req
+        .get_dvizh_repo()
+        .await
+        .is_not_admin(user_id, event.group_id)?
+    {
+        return Ok(Value::Null);
+    }
+
+    event.poster = Some(largest_photo_id(&photo));
+    req.get_dvizh_repo().await.update_event(&event)?;
+    let text = req.get_translation_for("poster_saved").await?;
+    req.set_msg_text(&text.expect_text()?.replace("{title}", &event.title));
+    send_reply_msg(offset, req).await
+}
+
+/// Sends the poster or the venue of an event shown in a list.
+/// Callback data has the form `{poster|venue}_{event id}[_{YYYYMMDD}]`.
+pub async fn handle_media_callback(
+    callback_data: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let chat_id = req.get_msg().chat.id;
+    let parts = callback_data.splitn(3, '_').collect::<Vec<&str>>();
+    let event_id = parts
+        .get(1)
+        .and_then(|id| id.parse::<i64>().ok())
+        .unwrap_or_default();
+    let date = parts.get(2).copied().and_then(parse_occurrence_date);
+
+    let event = req
+        .get_dvizh_repo()
+        .await
+        .get_event_occurrence(event_id, date)?;
+    let event = match event {
+        Some(event) if event.group_id == chat_id => event,
+        _ => {
+            let text = req.get_translation_for("error_event_not_found").await?;
+            req.set_msg_text(&text.expect_text()?);
+            send_msg(offset, req).await?;
+            return Ok(());
+        }
+    };
+
+    match parts[0] {
+        "poster" => {
+            let (card, keyboard) = build_event_card(&req.app, &event).await?;
+            send_card_message(&req.app, chat_id, &event, &card, Some(&keyboard)).await?;
+        }
+        _ => send_event_venue(&req.app, chat_id, &event).await?,
+    }
+    Ok(())
+}
+
+/// Sends the card with the poster of the event, or as text when it has none.
+/// Captions are limited to 1024 characters, so longer cards are sent as text too.
+async fn send_card_message(
+    app: &Application,
+    chat_id: i64,
+    event: &Event,
+    text: &str,
+    keyboard: Option<&str>,
+) -> Result<Value> {
+    let mut params = HashMap::new();
+    params.insert("chat_id", chat_id.to_string());
+    if let Some(keyboard) = keyboard {
+        params.insert("reply_markup", keyboard.to_string());
+    }
+
+    if let Some(poster) = &event.poster {
+        let mut photo_params = params.clone();
+        photo_params.insert("photo", poster.clone());
+        photo_params.insert("caption", text.to_string());
+        let response = send_request(
+            &app.client,
+            &app.tg_token,
+            msg_type_to_str(&MsgType::SendPhoto),
+            photo_params,
+        )
+        .await?;
+        if response["ok"].as_bool().unwrap_or_default() {
+            return Ok(response);
+        }
+        debug!("Could not send poster of event {}: {response}", event.id);
+    }
+
+    params.insert("text", text.to_string());
+    send_request(
+        &app.client,
+        &app.tg_token,
+        msg_type_to_str(&MsgType::SendMessage),
+        params,
+    )
+    .await
+}
+
+/// Returns the event of an event card from the RSVP buttons of its keyboard.
+fn card_event_id(reply_markup: Option<&Value>) -> Option<i64> {
+    reply_markup?["inline_keyboard"]
+        .as_array()?
+        .iter()
+        .filter_map(|row| row.as_array())
+        .flatten()
+        .filter_map(|button| button["callback_data"].as_str())
+        .find_map(|data| data.strip_prefix("rsvp_")?.split('_').nth(1)?.parse().ok())
+}
@@ -0,0 +1,203 @@
+use crate::application::Application;
+use crate::chat_settings::ChatSetting;
+use crate::db::db_objects::Event;
+use crate::tg::event_cards::build_event_card;
+use crate::tg::event_media::send_event_card;
+use crate::tg::event_utils::{event_from_args, fill_event_template};
+use crate::tg::language_utils::chat_translation;
+use crate::tg::messaging::{edit_keyboard_msg, edit_msg_and_remove_keyboard, send_msg, send_text};
+use crate::tg::msg_request::MsgRequest;
+use crate::tg::tg_utils::get_chat_administrator_ids;
+use crate::validations::validation_error_to_key;
+use anyhow::Result;
+use log::debug;
+use serde_json::json;
+
+/// Stores the event proposed by a member as pending and sends it to the admins.
+/// The proposal goes to the `admin_topic` of the chat when it is set, otherwise
+/// privately to every admin, and to the chat itself when no admin can be reached.
+pub async fn handle_propose_event_command(
+    args: Vec<String>,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    debug!("ProposeEvent command was called");
+    let chat = req.get_msg().chat.clone();
+    let from = req.get_msg().from.clone();
+
+    if chat.chat_type == "private" {
+        let text = req.get_translation_for("error_group_only").await?;
+        req.set_msg_text(&text.expect_text()?);
+        return send_msg(offset, req).await;
+    }
+
+    let timezone = req.get_dvizh_repo().await.get_chat_timezone(chat.id)?;
+    let mut event = match event_from_args(&args, chat.id, &timezone) {
+        Ok(event) => event,
+        Err(error) => {
+            let text = req
+                .get_translation_for(validation_error_to_key(&error))
+                .await?;
+            req.set_msg_text(&text.expect_text()?);
+            return send_msg(offset, req).await;
+        }
+    };
+    event.created_by = Some(from.id);
+    event.pending = true;
+    event.id = req.get_dvizh_repo().await.add_event(event.clone())?;
+
+    let proposer = match &from.username {
+        Some(username) => format!("{} (@{username})", from.first_name),
+        None => from.first_name.clone(),
+    };
+    let header = chat_translation(&req.app, chat.id, "proposal_header")
+        .await?
+        .replace("{name}", &proposer)
+        .replace("{chat}", chat.title.as_deref().unwrap_or_default());
+    let template = chat_translation(&req.app, chat.id, "event_template").await?;
+    let message = format!("{header}\n\n{}", fill_event_template(&template, &event));
+    let keyboard = proposal_keyboard(&req.app, &event).await?;
+
+    let topic = req
+        .get_dvizh_repo()
+        .await
+        .get_chat_setting(chat.id, &ChatSetting::AdminTopic)?;
+    let mut delivered = false;
+    if let Ok(topic_id) = topic.parse::<i64>() {
+        delivered = send_text(&req.app, chat.id, Some(topic_id), &message, Some(&keyboard)).await?;
+    } else {
+        let admin_ids =
+            get_chat_administrator_ids(&req.app.client, &req.app.tg_token, chat.id).await?;
+        for admin_id in admin_ids {
+            // Admins who never started a private chat with the bot are skipped
+            if send_text(&req.app, admin_id, None, &message, Some(&keyboard)).await? {
+                delivered = true;
+            }
+        }
+    }
+    if !delivered {
+        send_text(&req.app, chat.id, None, &message, Some(&keyboard)).await?;
+    }
+
+    let text = req.get_translation_for("proposal_sent").await?;
+    req.set_msg_text(&text.expect_text()?);
+    send_msg(offset, req).await
+}
+
+/// Builds the Approve / Reject / Edit buttons of a proposed event.
+/// Edit opens the regular event editor.
+pub async fn proposal_keyboard(app: &Application, event: &Event) -> Result<String> {
+    let approve = chat_translation(app, event.group_id, "button_approve").await?;
+    let reject = chat_translation(app, event.group_id, "button_reject").await?;
+    let edit = chat_translation(app, event.group_id, "button_edit").await?;
+
+    Ok(json!({
+        "inline_keyboard": [
+            [
+                { "text": approve, "callback_data": format!("proposal_approve_{}", event.id) },
+                { "text": reject, "callback_data": format!("proposal_reject_{}", event.id) }
+            ],
+            [
+                { "text": edit, "callback_data": format!("event_edit_{}", event.id) }
+            ]
+        ]
+    })
+    .to_string())
+}
+
+/// Approves or rejects a proposed event and notifies the proposer of the decision.
+/// Approved events are announced in the chat, rejected ones are deleted.
+/// Callback data has the form `proposal_{approve|reject|show}_{event id}`,
+/// `show` brings back the decision buttons after the editor was cancelled.
+pub async fn handle_proposal_callback(
+    callback_query: &serde_json::Value,
+    callback_data: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<()> {
+    let user_id = callback_query["from"]["id"].as_i64().unwrap_or_default();
+    let decided_by = match callback_query["from"]["username"].as_str() {
+        Some(username) => format!("@{username}"),
+        None => callback_query["from"]["first_name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+    };
+    let parts = callback_data.splitn(3, '_').collect::<Vec<&str>>();
+    let action = parts.get(1).copied().unwrap_or_default();
+    let event_id = parts
+        .get(2)
+        .and_then(|id| id.parse::<i64>().ok())
+        .unwrap_or_default();
+
+    // Another admin may have decided already
+    let event = req.get_dvizh_repo().await.get_event(event_id)?;
+    let event = match event {
+        Some(event) if event.pending => event,
+        _ => {
+            let text = req.get_translation_for("error_proposal_decided").await?;
+            req.set_msg_text(&text.expect_text()?);
+            edit_msg_and_remove_keyboard(offset, req).await?;
+            return Ok(());
+        }
+    };
+
+    if req
+        .get_dvizh_repo()
+        .await
+        .is_not_admin(user_id, event.group_id)?
+    {
+        let text = req.get_translation_for("error_not_admin").await?;
+        req.set_msg_text(&text.expect_text()?);
+        send_msg(offset, req).await?;
+        return Ok(());
+    }
+
+    let (decision_key, notice_key) = match action {
+        "show" => {
+            let template = chat_translation(&req.app, event.group_id, "event_template").await?;
+            let keyboard = proposal_keyboard(&req.app, &event).await?;
+            req.set_msg_text(&fill_event_template(&template, &event));
+            edit_keyboard_msg(&keyboard, offset, req).await?;
+            return Ok(());
+        }
+        "approve" => {
+            req.get_dvizh_repo().await.approve_event(event.id)?;
+            ("proposal_approved", "proposal_approved_notice")
+        }
+        "reject" => {
+            req.get_dvizh_repo().await.delete_event(event.id)?;
+            ("proposal_rejected", "proposal_rejected_notice")
+        }
+        _ => {
+            debug!("Unknown proposal callback {callback_data}");
+            return Ok(());
+        }
+    };
+
+    let decision = req.get_translation_for(decision_key).await?;
+    req.set_msg_text(&format!(
+        "{} {} ({decided_by})",
+        decision.expect_text()?,
+        event.title
+    ));
+    edit_msg_and_remove_keyboard(offset, req).await?;
+
+    let notice = chat_translation(&req.app, event.group_id, notice_key)
+        .await?
+        .replace("{title}", &event.title);
+    let notified = match event.created_by {
+        Some(user_id) => send_text(&req.app, user_id, None, &notice, None).await?,
+        None => false,
+    };
+
+    if action == "approve" {
+        let header = chat_translation(&req.app, event.group_id, "event_new").await?;
+        let (card, keyboard) = build_event_card(&req.app, &event).await?;
+        let message = format!("{header}\n\n{card}");
+        send_event_card(&req.app, event.group_id, &event, &message, Some(&keyboard)).await?;
+    } else if !notified {
+        send_text(&req.app, event.group_id, None, &notice, None).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,210 @@
+use crate::db::db_objects::Event;
+use crate::time_utils::{format_reminder_offsets, timezone_or_default, DATE_FORMAT, TIME_FORMAT};
+use crate::validations::{
+    validate_capacity, validate_coordinates, validate_event_datetime, validate_recurrence,
+    validate_reminder_offsets, ValidationError,
+};
+use chrono::NaiveDate;
+use serde_json::json;
+
+static OCCURRENCE_FORMAT: &str = "%Y%m%d";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventField {
+    Title,
+    Date,
+    Location,
+    Description,
+    Reminders,
+    Capacity,
+    Poster,
+    Venue,
+}
+
+pub fn event_field_from_str(t: &str) -> Option<EventField> {
+    match t {
+        "title" => Some(EventField::Title),
+        "date" => Some(EventField::Date),
+        "location" => Some(EventField::Location),
+        "description" => Some(EventField::Description),
+        "reminders" => Some(EventField::Reminders),
+        "capacity" => Some(EventField::Capacity),
+        "poster" => Some(EventField::Poster),
+        "venue" => Some(EventField::Venue),
+        _ => None,
+    }
+}
+
+pub fn event_field_to_str(field: &EventField) -> &'static str {
+    match field {
+        EventField::Title => "title",
+        EventField::Date => "date",
+        EventField::Location => "location",
+        EventField::Description => "description",
+        EventField::Reminders => "reminders",
+        EventField::Capacity => "capacity",
+        EventField::Poster => "poster",
+        EventField::Venue => "venue",
+    }
+}
+
+/// Builds an event of the chat from the `[title] [date] [location] [description] [recurrence]`
+/// arguments of `/addevent` and `/proposeevent`, the recurrence is optional.
+pub fn event_from_args(
+    args: &[String],
+    chat_id: i64,
+    timezone: &str,
+) -> Result<Event, ValidationError> {
+    let date_time = validate_event_datetime(&args[1], timezone)?;
+    let event = Event::new(
+        chat_id,
+        args[0].to_string(),
+        date_time.start,
+        date_time.end,
+        date_time.timezone.name().to_string(),
+        args[2].to_string(),
+        args[3].to_string(),
+    );
+
+    match args.get(4) {
+        Some(rule) => {
+            let rule = validate_recurrence(rule)?;
+            Ok(event.with_recurrence(rule.recurrence, rule.until, rule.skipped_dates))
+        }
+        None => Ok(event),
+    }
+}
+
+/// Replaces a single field of `event` with `value`.
+pub fn set_event_field(
+    event: &mut Event,
+    field: &EventField,
+    value: String,
+) -> Result<(), ValidationError> {
+    match field {
+        EventField::Title => event.title = value,
+        EventField::Date => {
+            let date_time = validate_event_datetime(&value, &event.timezone)?;
+            event.start = date_time.start;
+            event.end = date_time.end;
+            event.timezone = date_time.timezone.name().to_string();
+        }
+        EventField::Location => event.location = value,
+        EventField::Description => event.description = value,
+        EventField::Reminders => {
+            event.reminders = match value.trim().to_lowercase().as_str() {
+                "default" => None,
+                value => Some(format_reminder_offsets(&validate_reminder_offsets(value)?)),
+            };
+        }
+        EventField::Capacity => event.capacity = validate_capacity(&value)?,
+        // The file id of a photo, see `handle_edit_event_field_input`
+        EventField::Poster => {
+            event.poster = match value.trim().to_lowercase().as_str() {
+                "off" => None,
+                _ => Some(value),
+            };
+        }
+        EventField::Venue => event.coordinates = validate_coordinates(&value)?,
+    }
+    Ok(())
+}
+
+/// Formats the local start date of `event` as `DD.MM.YYYY`.
+pub fn format_event_date(event: &Event) -> String {
+    let tz = timezone_or_default(&event.timezone);
+    event
+        .start
+        .with_timezone(&tz)
+        .format(DATE_FORMAT)
+        .to_string()
+}
+
+/// Formats the local start of `event` as `DD.MM.YYYY HH:MM`.
+pub fn format_event_start(event: &Event) -> String {
+    let tz = timezone_or_default(&event.timezone);
+    format!(
+        "{} {}",
+        format_event_date(event),
+        event.start.with_timezone(&tz).format(TIME_FORMAT)
+    )
+}
+
+/// Formats the local time span of `event`, e.g. `19:00 - 22:00`.
+/// The end date is included when the event ends on another day.
+pub fn format_event_time(event: &Event) -> String {
+    let tz = timezone_or_default(&event.timezone);
+    let start = event.start.with_timezone(&tz);
+    let mut time = start.format(TIME_FORMAT).to_string();
+
+    if let Some(end) = event.end {
+        let end = end.with_timezone(&tz);
+        let end_format = if end.date_naive() == start.date_naive() {
+            TIME_FORMAT.to_string()
+        } else {
+            format!("{DATE_FORMAT} {TIME_FORMAT}")
+        };
+        time = format!("{time} - {}", end.format(&end_format));
+    }
+
+    format!("{time} ({})", tz.name())
+}
+
+/// Fills the `event_template` translation with the values of `event`.
+pub fn fill_event_template(template: &str, event: &Event) -> String {
+    template
+        .replace("{title}", &event.title)
+        .replace("{date}", &format_event_date(event))
+        .replace("{time}", &format_event_time(event))
+        .replace("{location}", &event.location)
+        .replace("{description}", &event.description)
+}
+
+/// Builds an inline keyboard with one button per event.
+/// Every button carries `{action}_{event id}` as callback data,
+/// occurrences of recurring events add their local date as `_{YYYYMMDD}`.
+pub fn events_keyboard(events: &[Event], action: &str, cancel_text: &str) -> String {
+    let mut rows = events
+        .iter()
+        .map(|event| {
+            vec![json!({
+                "text": format!("{} ({})", event.title, format_event_date(event)),
+                "callback_data": event_callback_data(action, event)
+            })]
+        })
+        .collect::<Vec<_>>();
+    rows.push(vec![
+        json!({ "text": cancel_text, "callback_data": "event_cancel" }),
+    ]);
+
+    json!({ "inline_keyboard": rows }).to_string()
+}
+
+/// Builds `{action}_{event id}[_{YYYYMMDD}]` callback data for `event`.
+pub fn event_callback_data(action: &str, event: &Event) -> String {
+    match occurrence_key(event).as_str() {
+        "" => format!("{action}_{}", event.id),
+        date => format!("{action}_{}_{date}", event.id),
+    }
+}
+
+/// Returns the local date of an occurrence of a recurring event as `YYYYMMDD`,
+/// or an empty string for a single event.
+pub fn occurrence_key(event: &Event) -> String {
+    match event.recurrence {
+        Some(_) => {
+            let tz = timezone_or_default(&event.timezone);
+            event
+                .start
+                .with_timezone(&tz)
+                .format(OCCURRENCE_FORMAT)
+                .to_string()
+        }
+        None => String::new(),
+    }
+}
+
+/// Parses the occurrence date encoded by `events_keyboard`.
+pub fn parse_occurrence_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, OCCURRENCE_FORMAT).ok()
+}
@@ -1,95 +1,266 @@
 use crate::application::Application;
-use crate::db::db_objects::{Event, User};
-use crate::tg::messaging::send_request;
+use crate::birthdays::{age_on, celebrated_birthdays};
+use crate::chat_settings::ChatSetting;
+use crate::db::db_objects::{Attendance, Event, RsvpStatus, User};
+use crate::tg::birthday_reminders::gift_event_user_id;
+use crate::tg::event_cards::build_event_card;
+use crate::tg::event_media::send_event_card;
+use crate::tg::event_utils::{format_event_date, occurrence_key, parse_occurrence_date};
+use crate::tg::language_utils::chat_translation;
+use crate::tg::messaging::{send_request, send_text};
 use crate::tg::msg_type_utils::{msg_type_to_str, MsgType};
+use crate::tg::tg_utils::get_chat_administrator_ids;
+use crate::time_utils::{DATE_FORMAT, MAX_REMINDER_DAYS};
+use crate::validations::validate_reminder_offsets;
 use anyhow::Result;
-use chrono::{Datelike, NaiveDate, Utc};
-use log::debug;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use log::{debug, error};
 use std::collections::HashMap;
 
-pub async fn perform_happy_birthday(app: &Application, birthday: &str) -> Result<()> {
-    let users = app
-        .dvizh_repo
-        .lock()
-        .await
-        .get_users_by_birthday(birthday)?;
-    for user in users {
-        let chats = app
-            .dvizh_repo
-            .lock()
-            .await
-            .get_chats_for_user(&user.username)?;
-        for chat in chats {
-            send_happy_birthday(&app, &user, chat).await?;
+pub async fn perform_happy_birthday(app: &Application, today: NaiveDate) -> Result<()> {
+    let chats = app.dvizh_repo.lock().await.get_all_chat_ids()?;
+    for chat_id in chats {
+        let users = {
+            let dvizh_repo = app.dvizh_repo.lock().await;
+            let leap_birthday = dvizh_repo.get_chat_setting(chat_id, &ChatSetting::LeapBirthday)?;
+            let mut users = Vec::new();
+            for birthday in celebrated_birthdays(today, &leap_birthday) {
+                users.extend(dvizh_repo.get_users_by_birthday(chat_id, &birthday)?);
+            }
+            users
+        };
+        for user in users {
+            send_happy_birthday(app, &user, chat_id, today).await?;
         }
     }
     Ok(())
 }
 
+/// Sends the reminders that are due for upcoming events of every chat.
+/// Offsets come from the event or the `reminders` chat setting, and sent reminders
+/// are recorded so that they are not repeated after a restart.
 pub async fn perform_events_reminder(app: &Application) -> Result<()> {
-    let events = app.dvizh_repo.lock().await.get_today_events()?;
+    let now = Utc::now();
+    let chats = app.dvizh_repo.lock().await.get_all_chat_ids()?;
+    for chat_id in chats {
+        if let Err(e) = remind_chat_events(app, chat_id, now).await {
+            error!("Failed to send event reminders in {chat_id}: {e}");
+        }
+    }
+    Ok(())
+}
+
+async fn remind_chat_events(app: &Application, chat_id: i64, now: DateTime<Utc>) -> Result<()> {
+    let (chat_reminders, mode, events) = {
+        let dvizh_repo = app.dvizh_repo.lock().await;
+        (
+            dvizh_repo.get_chat_setting(chat_id, &ChatSetting::Reminders)?,
+            dvizh_repo.get_chat_setting(chat_id, &ChatSetting::ReminderMode)?,
+            dvizh_repo.get_events_to_remind(
+                chat_id,
+                now,
+                now + Duration::days(MAX_REMINDER_DAYS),
+            )?,
+        )
+    };
+
     for event in events {
-        reminde_events(&app, event).await?;
+        let reminders = event.reminders.as_deref().unwrap_or(&chat_reminders);
+        let due = validate_reminder_offsets(reminders)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|offset| event.start - Duration::minutes(*offset) <= now)
+            .collect::<Vec<i64>>();
+
+        let mut unsent = Vec::new();
+        for offset in due {
+            if !app
+                .dvizh_repo
+                .lock()
+                .await
+                .is_reminder_sent(event.id, event.start, offset)?
+            {
+                unsent.push(offset);
+            }
+        }
+        if unsent.is_empty() {
+            continue;
+        }
+
+        // Reminders missed while the bot was offline are folded into a single message
+        reminde_events(app, &event, &mode, &unsent).await?;
     }
     Ok(())
 }
 
-pub async fn reminde_events(app: &Application, event: Event) -> Result<serde_json::Value> {
-    let template = app
+/// Posts the reminder of `event` to the chat or, depending on the `reminder_mode`
+/// chat setting, sends it privately to users who are going or may go.
+/// The chat still gets the post when some attendee can not be reached.
+/// Reminders of events only admins see go privately to the admins, but not to
+/// the member a gift is planned for.
+/// The reminder `offsets` are marked as sent with the first message that goes out,
+/// so that a failed send does not repeat the reminder to those who already got it.
+pub async fn reminde_events(
+    app: &Application,
+    event: &Event,
+    mode: &str,
+    offsets: &[i64],
+) -> Result<()> {
+    let header = app
         .language_cache
         .write()
         .await
-        .get_translation_for_chat(&app.dvizh_repo, event.group_id, "event_template")
-        .await?;
+        .get_translation_for_chat(&app.dvizh_repo, event.group_id, "event_reminder")
+        .await?
+        .expect_text()?;
+    let (card, keyboard) = build_event_card(app, event).await?;
+    let message = format!("{header}\n\n{card}");
 
-    let message = template
-        .expect_text()?
-        .replace("{title}", &event.title)
-        .replace("{date}", &event.date)
-        .replace("{location}", &event.location)
-        .replace("{description}", &event.description);
+    let (recipients, mut post_to_chat) = if event.admins_only {
+        let admin_ids =
+            get_chat_administrator_ids(&app.client, &app.tg_token, event.group_id).await?;
+        let birthday_user_id = gift_event_user_id(event);
+        let admin_ids = admin_ids
+            .into_iter()
+            .filter(|admin_id| Some(*admin_id) != birthday_user_id)
+            .collect::<Vec<i64>>();
+        (admin_ids, false)
+    } else if mode == "group" {
+        (Vec::new(), true)
+    } else {
+        let attendance = app
+            .dvizh_repo
+            .lock()
+            .await
+            .get_attendance(event.id, &occurrence_key(event))?;
+        let attendees = attendance
+            .iter()
+            .filter(|attendance| matches!(attendance.status, RsvpStatus::Going | RsvpStatus::Maybe))
+            .map(|attendance| attendance.user_id)
+            .collect::<Vec<i64>>();
+        let post_to_chat = mode != "dm" || attendees.is_empty();
+        (attendees, post_to_chat)
+    };
 
-    // Formatting the message for the user
-    let mut params = HashMap::new();
-    params.insert("chat_id", event.group_id.to_string());
-    params.insert("text", message);
+    let mut marked = false;
+    for user_id in recipients {
+        match send_event_card(app, user_id, event, &message, None).await {
+            Ok(response) if response["ok"].as_bool().unwrap_or_default() => {
+                if !marked {
+                    mark_reminders_sent(app, event, offsets).await?;
+                    marked = true;
+                }
+            }
+            Ok(response) => {
+                debug!("Could not send reminder to {user_id}: {response}");
+                post_to_chat = true;
+            }
+            Err(e) => {
+                error!(
+                    "Failed to send reminder of event {} to {user_id}: {e}",
+                    event.id
+                );
+                post_to_chat = true;
+            }
+        }
+    }
 
-    // Sending a message to Telegram
-    send_request(
-        &app.client,
-        &app.tg_token,
-        msg_type_to_str(&MsgType::SendMessage),
-        params,
-    )
-    .await
+    // Admins who can not be reached privately would not be reached on the next run either
+    if event.admins_only {
+        if !marked {
+            mark_reminders_sent(app, event, offsets).await?;
+        }
+        return Ok(());
+    }
+
+    if post_to_chat {
+        match send_event_card(app, event.group_id, event, &message, Some(&keyboard)).await {
+            Ok(response) if response["ok"].as_bool().unwrap_or_default() => {
+                if !marked {
+                    mark_reminders_sent(app, event, offsets).await?;
+                }
+            }
+            Ok(response) => error!(
+                "Could not post reminder of event {} to {}: {response}",
+                event.id, event.group_id
+            ),
+            Err(e) => error!(
+                "Failed to post reminder of event {} to {}: {e}",
+                event.id, event.group_id
+            ),
+        }
+    }
+    Ok(())
 }
 
+async fn mark_reminders_sent(app: &Application, event: &Event, offsets: &[i64]) -> Result<()> {
+    let dvizh_repo = app.dvizh_repo.lock().await;
+    for offset in offsets {
+        dvizh_repo.mark_reminder_sent(event.id, event.start, *offset)?;
+    }
+    Ok(())
+}
+
+/// Tells users promoted from the waitlist of `event` that they are going now.
+/// Users who have not started a private chat with the bot are told in the chat.
+pub async fn notify_waitlist_promotions(
+    app: &Application,
+    event: &Event,
+    promoted: &[Attendance],
+) -> Result<()> {
+    if promoted.is_empty() {
+        return Ok(());
+    }
+    let template = chat_translation(app, event.group_id, "waitlist_promoted").await?;
+
+    for attendance in promoted {
+        let date = match parse_occurrence_date(&attendance.occurrence) {
+            Some(date) => date.format(DATE_FORMAT).to_string(),
+            None => format_event_date(event),
+        };
+        let message = template
+            .replace("{title}", &event.title)
+            .replace("{date}", &date);
+
+        if !send_text(app, attendance.user_id, None, &message, None).await? {
+            let message = format!("{}, {message}", attendance.first_name);
+            send_text(app, event.group_id, None, &message, None).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Congratulates the user in the chat, without the age when it is hidden or unknown.
 pub async fn send_happy_birthday(
     app: &Application,
     user: &User,
     chat_id: i64,
+    today: NaiveDate,
 ) -> Result<serde_json::Value> {
+    let age = user
+        .birthdate
+        .as_deref()
+        .filter(|_| user.show_age)
+        .and_then(|birthdate| age_on(birthdate, today));
+    let template_key = match age {
+        Some(_) => "birthday_template",
+        None => "birthday_template_no_age",
+    };
     let template = app
         .language_cache
         .write()
         .await
-        .get_translation_for_chat(&app.dvizh_repo, chat_id, "birthday_template")
+        .get_translation_for_chat(&app.dvizh_repo, chat_id, template_key)
         .await?;
 
-    let birth_date = NaiveDate::parse_from_str(user.birthdate.as_ref().unwrap(), "%d.%m.%Y")?;
-    let today = Utc::now().date_naive();
-    let age = today.year() - birth_date.year();
-
+    let name = match user.display_name() {
+        name if name.is_empty() => "unknown 🙁".to_string(),
+        name => name,
+    };
     let message = template
         .expect_text()?
-        .replace(
-            "{first_name}",
-            user.first_name
-                .as_ref()
-                .unwrap_or(&"unknown 🙁".to_string()),
-        )
-        .replace("{username}", &user.username)
-        .replace("{age}", &age.to_string());
+        .replace("{name}", &name)
+        .replace("{age}", &age.unwrap_or_default().to_string());
 
     // Formatting the message for the user
     let mut params = HashMap::new();
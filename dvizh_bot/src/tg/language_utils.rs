@@ -21,3 +21,13 @@ pub async fn translate_text(app: &Application, text: &str, target_lang: &str) ->
 
     Ok(tanlation.join(";"))
 }
+
+/// Returns the text translation of `key` in the language of the chat.
+pub async fn chat_translation(app: &Application, chat_id: i64, key: &str) -> Result<String> {
+    app.language_cache
+        .write()
+        .await
+        .get_translation_for_chat(&app.dvizh_repo, chat_id, key)
+        .await?
+        .expect_text()
+}
@@ -4,8 +4,12 @@ use crate::spam::is_spam_by_score;
 use crate::tg::callback_queries::handle_callback_query;
 use crate::tg::command_utils::{command_str_to_type, parse_command_arguments};
 use crate::tg::commands::{handle_command, handle_start_command};
+use crate::tg::date_polls::handle_poll_answer;
+use crate::tg::event_import::{handle_import_document, is_ics_document};
+use crate::tg::event_media::handle_event_photo;
 use crate::tg::messaging::{ban_chat_member, send_error_msg, send_msg};
 use crate::tg::msg_request::{create_msg_request, MsgRequest};
+use crate::tg::pending_input::handle_pending_input;
 use crate::tg::tg_objects::User;
 use crate::tg::tg_utils::get_chat_administrators;
 use anyhow::Result;
@@ -30,6 +34,8 @@ pub async fn handle_message(
                     handle_callback_query(callback_query, offset, &mut req).await?;
                 }
             }
+        } else if let Some(poll_answer) = res.get("poll_answer") {
+            handle_poll_answer(app, poll_answer).await?;
         } else if let Some(message) = res.get("message") {
             // Ensure `create_msg_request` handles the `photo` check
             if let Some(mut req) =
@@ -40,6 +46,7 @@ pub async fn handle_message(
                     handle_new_member(new_member.clone(), offset, &mut req).await?;
                     continue;
                 }
+                remember_sender(&mut req).await?;
 
                 let req_msg_text = req.get_msg_text();
 
@@ -60,6 +67,19 @@ pub async fn handle_message(
                     debug!("Handle {} command", command);
                     handle_command(offset, command_str_to_type(command), Some(args), &mut req)
                         .await?;
+                } else if let Some(photo) = req.get_msg().photo.clone() {
+                    handle_event_photo(photo, offset, &mut req).await?;
+                } else if let Some(document) =
+                    req.get_msg().document.clone().filter(is_ics_document)
+                {
+                    // Calendar files shared by other members are not imported
+                    let user_id = req.get_msg().from.id;
+                    let chat_id = req.get_msg().chat.id;
+                    if !req.get_dvizh_repo().await.is_not_admin(user_id, chat_id)? {
+                        handle_import_document(document, offset, &mut req).await?;
+                    }
+                } else if let Some(pending) = req.take_pending_input().await {
+                    handle_pending_input(pending, offset, &mut req).await?;
                 }
             }
         }
@@ -82,6 +102,26 @@ pub async fn handle_error(
     send_error_msg(offset, req.get_msg().chat.id, req).await
 }
 
+/// Keeps the ids, usernames and names of group members up to date.
+async fn remember_sender(req: &mut MsgRequest) -> Result<()> {
+    let from = req.get_msg().from.clone();
+    let chat = req.get_msg().chat.clone();
+    if from.is_bot || chat.chat_type == "private" {
+        return Ok(());
+    }
+
+    req.get_dvizh_repo().await.add_or_update_user(
+        DbUser::new(
+            from.id,
+            from.username,
+            Some(from.first_name),
+            None,
+            from.language_code,
+        ),
+        chat.id,
+    )
+}
+
 async fn handle_new_member(
     member: User,
     offset: &mut i64,
@@ -89,14 +129,15 @@ async fn handle_new_member(
 ) -> Result<serde_json::Value> {
     debug!("Handle new member: {member:#?}");
     let chat_id = req.get_msg().chat.id;
-    if member.is_bot && member.username == "dvizh_wroclaw_bot" {
+    if member.is_bot && member.username.as_deref() == Some("dvizh_wroclaw_bot") {
         handle_start_command(offset, req).await?;
         let admins = get_chat_administrators(&req.app.client, &req.app.tg_token, chat_id).await?;
         debug!("List of {} admins: {:#?}", chat_id, admins);
         for admin in admins {
             req.get_dvizh_repo().await.add_or_update_user(
                 DbUser::new(
-                    admin.username.clone(),
+                    admin.id,
+                    admin.username,
                     admin.first_name,
                     None,
                     admin.language_code,
@@ -104,13 +145,12 @@ async fn handle_new_member(
                 chat_id,
             )?;
 
-            req.get_dvizh_repo()
-                .await
-                .add_admin(&admin.username, chat_id)?;
+            req.get_dvizh_repo().await.add_admin(admin.id, chat_id)?;
         }
     } else {
         req.get_dvizh_repo().await.add_or_update_user(
             DbUser::new(
+                member.id,
                 member.username,
                 Some(member.first_name.clone()),
                 None,
@@ -1,10 +1,46 @@
+use crate::application::Application;
 use crate::tg::msg_request::MsgRequest;
 use crate::tg::msg_type_utils::{msg_type_to_str, MsgType};
+use crate::tg::tg_objects::Message;
 use anyhow::Result;
 use log::debug;
+use reqwest::multipart::{Form, Part};
 use reqwest::Client;
+use serde_json::json;
 use std::collections::HashMap;
 
+/// Sends `text` to a chat or a forum topic of it.
+/// Returns whether Telegram accepted the message.
+pub async fn send_text(
+    app: &Application,
+    chat_id: i64,
+    topic_id: Option<i64>,
+    text: &str,
+    keyboard: Option<&str>,
+) -> Result<bool> {
+    let mut params = HashMap::new();
+    params.insert("chat_id", chat_id.to_string());
+    params.insert("text", text.to_string());
+    if let Some(topic_id) = topic_id {
+        params.insert("message_thread_id", topic_id.to_string());
+    }
+    if let Some(keyboard) = keyboard {
+        params.insert("reply_markup", keyboard.to_string());
+    }
+
+    let response = send_request(
+        &app.client,
+        &app.tg_token,
+        msg_type_to_str(&MsgType::SendMessage),
+        params,
+    )
+    .await?;
+    if !response["ok"].as_bool().unwrap_or_default() {
+        debug!("Could not send message to {chat_id}: {response}");
+    }
+    Ok(response["ok"].as_bool().unwrap_or_default())
+}
+
 pub async fn send_error_msg(
     offset: &mut i64,
     chat_id: i64,
@@ -31,8 +67,26 @@ pub async fn edit_msg(offset: &mut i64, req: &mut MsgRequest) -> Result<serde_js
     let mut params = HashMap::new();
     params.insert("chat_id", msg.chat.id.to_string());
     params.insert("message_id", msg.message_id.to_string());
-    params.insert("text", req.get_msg_text().to_string());
-    req.method = MsgType::EditMessageText;
+    let (method, text_param) = edit_text_method(msg);
+    params.insert(text_param, req.get_msg_text().to_string());
+    req.method = method;
+
+    send_msg_internal(offset, req, params).await
+}
+
+pub async fn edit_keyboard_msg(
+    keyboard: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    let msg = req.get_msg();
+    let mut params = HashMap::new();
+    params.insert("chat_id", msg.chat.id.to_string());
+    params.insert("message_id", msg.message_id.to_string());
+    let (method, text_param) = edit_text_method(msg);
+    params.insert(text_param, req.get_msg_text().to_string());
+    params.insert("reply_markup", keyboard.to_string());
+    req.method = method;
 
     send_msg_internal(offset, req, params).await
 }
@@ -92,6 +146,80 @@ pub async fn send_photo_msg(
     send_msg_internal(offset, req, params).await
 }
 
+pub async fn send_document_msg(
+    file_name: &str,
+    content: Vec<u8>,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    let chat_id = req.get_msg().chat.id;
+    send_document_to_msg(chat_id, file_name, content, offset, req).await
+}
+
+/// Sends a document with the message text as caption to `chat_id`,
+/// such as a private chat with the sender.
+pub async fn send_document_to_msg(
+    chat_id: i64,
+    file_name: &str,
+    content: Vec<u8>,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    let mut params = HashMap::new();
+    params.insert("chat_id", chat_id.to_string());
+    params.insert("caption", req.get_msg_text());
+    debug!("Send document {file_name}: {:?}", params);
+
+    let response = send_document_request(
+        &req.app.client,
+        &req.app.tg_token,
+        params,
+        file_name,
+        content,
+    )
+    .await?;
+
+    *offset = req.update_id + 1;
+    debug!("Updated offset: {}", offset);
+    Ok(response)
+}
+
+/// Sends a non-anonymous poll asking the message text, several answers can be chosen.
+pub async fn send_poll_msg(
+    options: &[String],
+    keyboard: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    let msg = req.get_msg();
+    let options = options
+        .iter()
+        .map(|option| json!({ "text": option }))
+        .collect::<Vec<_>>();
+    let mut params = HashMap::new();
+    params.insert("chat_id", msg.chat.id.to_string());
+    params.insert("question", req.get_msg_text());
+    params.insert("options", json!(options).to_string());
+    params.insert("is_anonymous", "false".to_string());
+    params.insert("allows_multiple_answers", "true".to_string());
+    params.insert("reply_markup", keyboard.to_string());
+    req.method = MsgType::SendPoll;
+
+    send_msg_internal(offset, req, params).await
+}
+
+/// Closes the poll of the message and removes its keyboard.
+pub async fn stop_poll(offset: &mut i64, req: &mut MsgRequest) -> Result<serde_json::Value> {
+    let msg = req.get_msg();
+    let mut params = HashMap::new();
+    params.insert("chat_id", msg.chat.id.to_string());
+    params.insert("message_id", msg.message_id.to_string());
+    params.insert("reply_markup", "{}".to_string());
+    req.method = MsgType::StopPoll;
+
+    send_msg_internal(offset, req, params).await
+}
+
 pub async fn edit_msg_and_remove_keyboard(
     offset: &mut i64,
     req: &mut MsgRequest,
@@ -100,9 +228,10 @@ pub async fn edit_msg_and_remove_keyboard(
     let mut params = HashMap::new();
     params.insert("chat_id", msg.chat.id.to_string());
     params.insert("message_id", msg.message_id.to_string());
-    params.insert("text", req.get_msg_text().to_string());
+    let (method, text_param) = edit_text_method(msg);
+    params.insert(text_param, req.get_msg_text().to_string());
     params.insert("reply_markup", "{}".to_string());
-    req.method = MsgType::EditMessageText;
+    req.method = method;
 
     send_msg_internal(offset, req, params).await
 }
@@ -141,6 +270,40 @@ pub async fn send_request(
     Ok(response.json().await?)
 }
 
+/// Uploads `content` as a file named `file_name` with `sendDocument`.
+pub async fn send_document_request(
+    client: &Client,
+    api_token: &str,
+    params: HashMap<&str, String>,
+    file_name: &str,
+    content: Vec<u8>,
+) -> Result<serde_json::Value> {
+    let url = format!(
+        "https://api.telegram.org/bot{}/{}",
+        api_token,
+        msg_type_to_str(&MsgType::SendDocument)
+    );
+
+    let mut form = Form::new().part(
+        "document",
+        Part::bytes(content).file_name(file_name.to_string()),
+    );
+    for (key, value) in params {
+        form = form.text(key.to_string(), value);
+    }
+
+    let response = client.post(&url).multipart(form).send().await?;
+    Ok(response.json().await?)
+}
+
+/// Messages with a photo, such as event cards with a poster, are edited by their caption.
+fn edit_text_method(msg: &Message) -> (MsgType, &'static str) {
+    match msg.photo {
+        Some(_) => (MsgType::EditMessageCaption, "caption"),
+        None => (MsgType::EditMessageText, "text"),
+    }
+}
+
 async fn send_msg_internal(
     offset: &mut i64,
     req: &mut MsgRequest,
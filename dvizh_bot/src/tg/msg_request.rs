@@ -2,6 +2,7 @@ use crate::application::Application;
 use crate::db::repository::DvizhRepository;
 use crate::tg::message_handler::handle_error;
 use crate::tg::msg_type_utils::MsgType;
+use crate::tg::pending_input::PendingInput;
 use crate::tg::tg_objects::Message;
 use crate::translations::translation_value::TranslationValue;
 use anyhow::Result;
@@ -53,6 +54,40 @@ impl MsgRequest {
         Ok(())
     }
 
+    /// Remembers the input the bot expects as the next message of `user_id` in `chat_id`.
+    pub async fn set_pending_input(&self, chat_id: i64, user_id: i64, input: PendingInput) {
+        self.app
+            .pending_inputs
+            .write()
+            .await
+            .insert((chat_id, user_id), input);
+    }
+
+    /// Removes and returns the input expected from the sender of the current message.
+    /// Only a reply to the prompt is taken as the input, other messages leave it waiting.
+    pub async fn take_pending_input(&self) -> Option<PendingInput> {
+        let msg = self.get_msg();
+        let reply_id = msg.reply_to_message.as_ref().map(|reply| reply.message_id);
+        let key = (msg.chat.id, msg.from.id);
+        let mut pending_inputs = self.app.pending_inputs.write().await;
+        match pending_inputs.get(&key) {
+            Some(PendingInput::EditEventField { prompt_id, .. })
+                if Some(*prompt_id) == reply_id =>
+            {
+                pending_inputs.remove(&key)
+            }
+            _ => None,
+        }
+    }
+
+    pub async fn clear_pending_input(&self, chat_id: i64, user_id: i64) {
+        self.app
+            .pending_inputs
+            .write()
+            .await
+            .remove(&(chat_id, user_id));
+    }
+
     pub fn get_msg(&self) -> &Message {
         self.msg.as_ref().unwrap()
     }
@@ -70,9 +105,9 @@ pub async fn create_msg_request(
     update_id: i64,
     offset: &mut i64,
 ) -> Result<Option<MsgRequest>> {
-    // Check if "message" is an object and does not contain "photo"
-    if !message.is_object() || message.as_object().and_then(|m| m.get("photo")).is_some() {
-        return Ok(None); // Return `None` if message is invalid or contains a photo
+    // Check if "message" is an object
+    if !message.is_object() {
+        return Ok(None); // Return `None` if message is invalid
     }
 
     // Parse `msg_obj` from `res` and retrieve `chat_id`
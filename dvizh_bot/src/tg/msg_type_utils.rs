@@ -4,10 +4,18 @@ pub enum MsgType {
     GetUpdates,
     SendMessage,
     SendPhoto,
+    SendDocument,
+    SendPoll,
+    SendVenue,
+    StopPoll,
     EditMessageText,
+    EditMessageCaption,
     EditMessageReplyMarkup,
     GetChatAdministrators,
+    GetFile,
     BanChatMember,
+    PinChatMessage,
+    UnpinChatMessage,
 }
 
 pub fn msg_type_to_str(t: &MsgType) -> &'static str {
@@ -16,9 +24,17 @@ pub fn msg_type_to_str(t: &MsgType) -> &'static str {
         MsgType::GetUpdates => "getUpdates",
         MsgType::SendMessage => "sendMessage",
         MsgType::SendPhoto => "sendPhoto",
+        MsgType::SendDocument => "sendDocument",
+        MsgType::SendPoll => "sendPoll",
+        MsgType::SendVenue => "sendVenue",
+        MsgType::StopPoll => "stopPoll",
         MsgType::EditMessageText => "editMessageText",
+        MsgType::EditMessageCaption => "editMessageCaption",
         MsgType::EditMessageReplyMarkup => "editMessageReplyMarkup",
         MsgType::GetChatAdministrators => "getChatAdministrators",
+        MsgType::GetFile => "getFile",
         MsgType::BanChatMember => "banChatMember",
+        MsgType::PinChatMessage => "pinChatMessage",
+        MsgType::UnpinChatMessage => "unpinChatMessage",
     }
 }
@@ -0,0 +1,178 @@
+use crate::db::db_objects::Event;
+use crate::natural_dates::is_natural_date;
+use crate::tg::date_picker::{
+    describe_event_date, event_date_action, send_date_confirmation, DatePickerTarget,
+};
+use crate::tg::event_media::largest_photo_id;
+use crate::tg::event_proposals::proposal_keyboard;
+use crate::tg::event_utils::{fill_event_template, set_event_field, EventField};
+use crate::tg::events::notify_waitlist_promotions;
+use crate::tg::messaging::{send_keyboard_reply_msg, send_reply_msg};
+use crate::tg::msg_request::MsgRequest;
+use crate::time_utils::timezone_or_default;
+use crate::validations::{validate_event_datetime, validation_error_to_key, EventDateTime};
+use anyhow::Result;
+use log::debug;
+use serde_json::json;
+
+/// Input the bot expects from a user in a chat as a reply to the prompt message `prompt_id`.
+#[derive(Debug, Clone)]
+pub enum PendingInput {
+    EditEventField {
+        event_id: i64,
+        field: EventField,
+        prompt_id: i64,
+    },
+}
+
+pub async fn handle_pending_input(
+    pending: PendingInput,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    debug!("Handle pending input {pending:?}");
+    match pending {
+        PendingInput::EditEventField {
+            event_id, field, ..
+        } => handle_edit_event_field_input(event_id, field, offset, req).await,
+    }
+}
+
+async fn handle_edit_event_field_input(
+    event_id: i64,
+    field: EventField,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    // Posters are sent as photos and venues may be shared as a location
+    let msg = req.get_msg();
+    let value = match (field, &msg.photo, &msg.location) {
+        (EventField::Poster, Some(photo), _) => largest_photo_id(photo),
+        (EventField::Poster, None, _) if !req.get_msg_text().trim().eq_ignore_ascii_case("off") => {
+            let text = req.get_translation_for("error_poster_photo").await?;
+            req.set_msg_text(&text.expect_text()?);
+            return send_reply_msg(offset, req).await;
+        }
+        (EventField::Venue, _, Some(location)) => {
+            format!("{},{}", location.latitude, location.longitude)
+        }
+        _ => req.get_msg_text().trim().to_string(),
+    };
+
+    // The event or the admin rights may have changed since the editor was opened
+    let chat_id = req.get_msg().chat.id;
+    let user_id = req.get_msg().from.id;
+    let event = req.get_dvizh_repo().await.get_event(event_id)?;
+    let mut event = match event {
+        Some(event) if event.group_id == chat_id || chat_id == user_id => event,
+        _ => {
+            let text = req.get_translation_for("error_event_not_found").await?;
+            req.set_msg_text(&text.expect_text()?);
+            return send_reply_msg(offset, req).await;
+        }
+    };
+    if req
+        .get_dvizh_repo()
+        .await
+        .is_not_admin(user_id, event.group_id)?
+    {
+        let text = req.get_translation_for("error_not_admin").await?;
+        req.set_msg_text(&text.expect_text()?);
+        return send_reply_msg(offset, req).await;
+    }
+
+    // Phrases such as `tomorrow 19:00` are confirmed before the event is moved
+    if field == EventField::Date && is_natural_date(&value) {
+        let date_time = match validate_event_datetime(&value, &event.timezone) {
+            Ok(date_time) => date_time,
+            Err(error) => {
+                let text = req
+                    .get_translation_for(validation_error_to_key(&error))
+                    .await?;
+                req.set_msg_text(&text.expect_text()?);
+                return send_reply_msg(offset, req).await;
+            }
+        };
+        let understood = describe_event_date(&date_time, req).await?;
+        // The picked date is saved in the timezone of the event
+        let action = event_date_action(&EventDateTime {
+            timezone: timezone_or_default(&event.timezone),
+            ..date_time.clone()
+        });
+        let month = date_time
+            .start
+            .with_timezone(&date_time.timezone)
+            .date_naive();
+        return send_date_confirmation(
+            DatePickerTarget::EventDate(event.id),
+            &value,
+            &understood,
+            &action,
+            month,
+            offset,
+            req,
+        )
+        .await;
+    }
+
+    if let Err(error) = set_event_field(&mut event, &field, value) {
+        let text = req
+            .get_translation_for(validation_error_to_key(&error))
+            .await?;
+        req.set_msg_text(&text.expect_text()?);
+        return send_reply_msg(offset, req).await;
+    }
+    req.get_dvizh_repo().await.update_event(&event)?;
+
+    // A raised or removed capacity gives the freed places to the waitlist
+    if field == EventField::Capacity {
+        let promoted = req
+            .get_dvizh_repo()
+            .await
+            .promote_waitlisted(event.id, event.capacity)?;
+        notify_waitlist_promotions(&req.app, &event, &promoted).await?;
+    }
+
+    let header = req
+        .get_translation_for("event_updated")
+        .await?
+        .expect_text()?;
+    let template = req
+        .get_translation_for("event_template")
+        .await?
+        .expect_text()?;
+    req.set_msg_text(&format!(
+        "{header}\n\n{}",
+        fill_event_template(&template, &event)
+    ));
+
+    let keyboard = updated_event_keyboard(&event, req).await?;
+    send_keyboard_reply_msg(&keyboard, offset, req).await
+}
+
+/// Buttons shown below an edited event, announcing it or finishing the edit.
+pub async fn updated_event_keyboard(event: &Event, req: &mut MsgRequest) -> Result<String> {
+    // A proposal keeps its decision buttons while it is being edited
+    if event.pending {
+        return proposal_keyboard(&req.app, event).await;
+    }
+
+    let notify = req
+        .get_translation_for("button_notify")
+        .await?
+        .expect_text()?;
+    let done = req
+        .get_translation_for("button_done")
+        .await?
+        .expect_text()?;
+    let keyboard = json!({
+        "inline_keyboard": [
+            [
+                { "text": notify, "callback_data": format!("event_notify_{}", event.id) },
+                { "text": done, "callback_data": "event_done" }
+            ]
+        ]
+    })
+    .to_string();
+    Ok(keyboard)
+}
@@ -1,4 +1,7 @@
 use crate::application::Application;
+use crate::tg::birthday_list::perform_monthly_birthdays;
+use crate::tg::birthday_reminders::perform_birthday_reminders;
+use crate::tg::digest::perform_weekly_digest;
 use crate::tg::events::{perform_events_reminder, perform_happy_birthday, send_greeting};
 use crate::tg::message_handler::handle_message;
 use crate::tg::messaging::send_request;
@@ -8,7 +11,12 @@ use anyhow::Result;
 use chrono::{Datelike, Local};
 use log::{debug, error};
 use std::collections::HashMap;
-use tokio::time::{interval_at, Duration, Instant};
+use tokio::time::{interval, interval_at, Duration, Instant};
+
+/// How often due event reminders are looked up.
+static REMINDER_CHECK_SECONDS: u64 = 60;
+/// How often chats are checked for a due weekly digest.
+static DIGEST_CHECK_SECONDS: u64 = 60;
 
 pub async fn run(app: Application, t: MsgType) -> Result<()> {
     debug!("Bot run");
@@ -64,28 +72,58 @@ pub async fn check_and_perform_daily_operations(app: Application) -> Result<()>
         Duration::from_secs(24 * 3600),
     );
 
+    let mut reminder_interval = interval(Duration::from_secs(REMINDER_CHECK_SECONDS));
+    let mut digest_interval = interval(Duration::from_secs(DIGEST_CHECK_SECONDS));
+
+    // Errors are logged so that one failed run does not stop the scheduler
     loop {
         tokio::select! {
+            _ = reminder_interval.tick() => {
+                if let Err(e) = perform_events_reminder(&app).await {
+                    error!("Failed to send event reminders: {e}");
+                }
+            }
+
+            _ = digest_interval.tick() => {
+                if let Err(e) = perform_weekly_digest(&app).await {
+                    error!("Failed to send weekly digests: {e}");
+                }
+            }
+
             _ = midnight_interval.tick() => {
                 debug!("Performing daily operations at midnight.");
                 let current_day = Local::now().date_naive();
-                let day = format!("{:02}.{:02}", current_day.day(), current_day.month());
 
-                perform_happy_birthday(&app, &day).await?;
-                perform_events_reminder(&app).await?;
+                if let Err(e) = perform_happy_birthday(&app, current_day).await {
+                    error!("Failed to send birthday greetings: {e}");
+                }
+                if let Err(e) = perform_birthday_reminders(&app, current_day).await {
+                    error!("Failed to send birthday reminders: {e}");
+                }
+                if current_day.day() == 1 {
+                    if let Err(e) = perform_monthly_birthdays(&app, current_day).await {
+                        error!("Failed to send monthly birthdays: {e}");
+                    }
+                }
 
                 // Check if it's January 1st for Happy New Year gathering
                 if current_day.day() == 1 && current_day.month() == 1 {
-                    send_greeting(&app, "heppy_new_year").await?;
+                    if let Err(e) = send_greeting(&app, "heppy_new_year").await {
+                        error!("Failed to send the New Year greeting: {e}");
+                    }
                 }
             }
 
             _ = morning_interval.tick() => {
-                send_greeting(&app, "morning").await?
+                if let Err(e) = send_greeting(&app, "morning").await {
+                    error!("Failed to send the morning greeting: {e}");
+                }
             }
 
             _ = evening_interval.tick() => {
-                send_greeting(&app, "night").await?
+                if let Err(e) = send_greeting(&app, "night").await {
+                    error!("Failed to send the night greeting: {e}");
+                }
             }
         }
     }
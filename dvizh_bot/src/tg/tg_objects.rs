@@ -7,9 +7,18 @@ pub struct Message {
     pub date: i64,
     pub from: User,
     pub message_id: i64,
+    /// Forum topic of the message in supergroups with topics.
+    pub message_thread_id: Option<i64>,
     pub text: Option<String>,
+    /// Mentions, commands and links found in the text.
+    pub entities: Option<Vec<MessageEntity>>,
     pub reply_markup: Option<Value>,
     pub new_chat_member: Option<User>,
+    pub document: Option<Document>,
+    /// Sizes of a sent photo, the largest comes last.
+    pub photo: Option<Vec<PhotoSize>>,
+    pub location: Option<Location>,
+    pub reply_to_message: Option<ReplyMessage>,
 }
 
 impl Message {
@@ -28,20 +37,81 @@ impl Message {
                 id: chat_id,
                 is_bot: false,
                 language_code: Some("".to_string()),
-                username: "".to_string(),
+                username: None,
             },
             message_id: 0,
+            message_thread_id: None,
             text: Some("".to_string()),
+            entities: None,
             reply_markup: Some(json!({})),
             new_chat_member: Some(User {
                 first_name: "".to_string(),
                 id: chat_id,
                 is_bot: false,
                 language_code: Some("".to_string()),
-                username: "".to_string(),
+                username: None,
             }),
+            document: None,
+            photo: None,
+            location: None,
+            reply_to_message: None,
         }
     }
+
+    /// Text covered by the entity.
+    pub fn entity_text(&self, entity: &MessageEntity) -> String {
+        let text = self
+            .text
+            .as_deref()
+            .unwrap_or_default()
+            .encode_utf16()
+            .skip(entity.offset)
+            .take(entity.length)
+            .collect::<Vec<u16>>();
+        String::from_utf16_lossy(&text)
+    }
+
+    /// Text with the part covered by the entity cut out.
+    pub fn text_without(&self, entity: &MessageEntity) -> String {
+        let text = self
+            .text
+            .as_deref()
+            .unwrap_or_default()
+            .encode_utf16()
+            .enumerate()
+            .filter(|(i, _)| *i < entity.offset || *i >= entity.offset + entity.length)
+            .map(|(_, c)| c)
+            .collect::<Vec<u16>>();
+        String::from_utf16_lossy(&text)
+    }
+}
+
+/// Special part of a message text.
+/// `offset` and `length` are counted in UTF-16 code units.
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+pub struct MessageEntity {
+    #[serde(rename = "type")]
+    pub entity_type: MessageEntityType,
+    pub offset: usize,
+    pub length: usize,
+    /// Link opened by a `text_link`.
+    pub url: Option<String>,
+    /// Mentioned user of a `text_mention`, used for users without a username.
+    pub user: Option<User>,
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageEntityType {
+    /// `@username`
+    Mention,
+    TextMention,
+    BotCommand,
+    Url,
+    TextLink,
+    #[default]
+    #[serde(other)]
+    Other,
 }
 
 #[derive(Default, Serialize, Deserialize, Debug, Clone)]
@@ -60,7 +130,37 @@ pub struct User {
     pub id: i64,
     pub is_bot: bool,
     pub language_code: Option<String>,
-    pub username: String,
+    pub username: Option<String>,
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+pub struct Document {
+    pub file_id: String,
+    pub file_name: Option<String>,
+    pub mime_type: Option<String>,
+    pub file_size: Option<i64>,
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+pub struct PhotoSize {
+    pub file_id: String,
+    pub width: i64,
+    pub height: i64,
+}
+
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// The message a message replies to, with only the fields the bot uses.
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+pub struct ReplyMessage {
+    pub message_id: i64,
+    pub from: Option<User>,
+    pub document: Option<Document>,
+    pub reply_markup: Option<Value>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
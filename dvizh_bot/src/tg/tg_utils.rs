@@ -2,7 +2,7 @@ use crate::db::db_objects::User;
 use crate::tg::messaging::send_request;
 use crate::tg::msg_type_utils::msg_type_to_str;
 use crate::MsgType;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chrono::Local;
 use headless_chrome::{Browser, LaunchOptions};
 use log::debug;
@@ -115,13 +115,61 @@ pub async fn get_chat_administrators(
         .filter_map(|admin| {
             let user = &admin["user"];
 
-            Some(User {
-                username: user["username"].as_str()?.to_string(),
-                first_name: user["first_name"].as_str().map(|s| s.to_string()),
-                birthdate: None,
-                language_code: user["language_code"].as_str().map(|s| s.to_string()),
-            })
+            Some(User::new(
+                user["id"].as_i64()?,
+                user["username"].as_str().map(|s| s.to_string()),
+                user["first_name"].as_str().map(|s| s.to_string()),
+                None,
+                user["language_code"].as_str().map(|s| s.to_string()),
+            ))
         })
         .collect();
     Ok(admins)
 }
+
+/// Returns the Telegram ids of the administrators of the chat, bots excluded.
+pub async fn get_chat_administrator_ids(
+    client: &Client,
+    api_token: &str,
+    chat_id: i64,
+) -> Result<Vec<i64>> {
+    let mut params = HashMap::new();
+    params.insert("chat_id", chat_id.to_string());
+
+    let response = send_request(
+        client,
+        api_token,
+        msg_type_to_str(&MsgType::GetChatAdministrators),
+        params,
+    )
+    .await?;
+
+    let admin_ids = response["result"]
+        .as_array()
+        .unwrap_or(&vec![])
+        .iter()
+        .filter(|admin| !admin["user"]["is_bot"].as_bool().unwrap_or_default())
+        .filter_map(|admin| admin["user"]["id"].as_i64())
+        .collect();
+    Ok(admin_ids)
+}
+
+/// Downloads a file sent to the bot by its `file_id`.
+pub async fn download_file(client: &Client, api_token: &str, file_id: &str) -> Result<Vec<u8>> {
+    let mut params = HashMap::new();
+    params.insert("file_id", file_id.to_string());
+
+    let response = send_request(
+        client,
+        api_token,
+        msg_type_to_str(&MsgType::GetFile),
+        params,
+    )
+    .await?;
+
+    let file_path = response["result"]["file_path"]
+        .as_str()
+        .ok_or_else(|| anyhow!("File {file_id} is not available: {response}"))?;
+    let url = format!("https://api.telegram.org/file/bot{api_token}/{file_path}");
+    Ok(client.get(&url).send().await?.bytes().await?.to_vec())
+}
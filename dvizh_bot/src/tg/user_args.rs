@@ -0,0 +1,72 @@
+use crate::db::db_objects::User as DbUser;
+use crate::tg::command_utils::parse_command_arguments;
+use crate::tg::msg_request::MsgRequest;
+use crate::tg::tg_objects::{MessageEntityType, User};
+use anyhow::Result;
+use log::debug;
+
+/// Finds the user a command is about and returns the remaining arguments.
+/// The user is mentioned (`@username` or a text mention of a user without a username),
+/// given by a known `@username` or a Telegram id as the first argument,
+/// or is the author of the message the command replies to.
+/// Returns `None` when no known user is given.
+pub async fn resolve_user_argument(
+    mut args: Vec<String>,
+    req: &MsgRequest,
+) -> Result<Option<(DbUser, Vec<String>)>> {
+    let msg = req.get_msg();
+
+    let mention = msg.entities.iter().flatten().find(|entity| {
+        entity.entity_type == MessageEntityType::Mention
+            || entity.entity_type == MessageEntityType::TextMention
+    });
+    if let Some(mention) = mention {
+        // The mention of a user without a username may span several words
+        let mut rest = parse_command_arguments(&msg.text_without(mention));
+        if !rest.is_empty() {
+            rest.remove(0);
+        }
+        let user = match &mention.user {
+            Some(user) => Some(user_from_tg(user)),
+            None => req
+                .get_dvizh_repo()
+                .await
+                .find_user_by_username(&msg.entity_text(mention))?,
+        };
+        debug!("Resolved mention to {user:?}");
+        return Ok(user.map(|user| (user, rest)));
+    }
+
+    if let Some(first) = args.first() {
+        if first.starts_with('@') {
+            let user = req.get_dvizh_repo().await.find_user_by_username(first)?;
+            args.remove(0);
+            return Ok(user.map(|user| (user, args)));
+        }
+        if let Ok(id) = first.parse::<i64>() {
+            let user = req.get_dvizh_repo().await.get_user(id)?;
+            args.remove(0);
+            let user = user.unwrap_or_else(|| DbUser::new(id, None, None, None, None));
+            return Ok(Some((user, args)));
+        }
+    }
+
+    // In forum topics every message replies to the message that opened the topic
+    let replied_to = msg
+        .reply_to_message
+        .as_ref()
+        .filter(|reply| Some(reply.message_id) != msg.message_thread_id)
+        .and_then(|reply| reply.from.as_ref())
+        .filter(|user| !user.is_bot);
+    Ok(replied_to.map(|user| (user_from_tg(user), args)))
+}
+
+fn user_from_tg(user: &User) -> DbUser {
+    DbUser::new(
+        user.id,
+        user.username.clone(),
+        Some(user.first_name.clone()),
+        None,
+        user.language_code.clone(),
+    )
+}
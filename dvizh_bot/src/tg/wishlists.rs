@@ -0,0 +1,194 @@
+use crate::db::db_objects::User as DbUser;
+use crate::tg::messaging::send_msg;
+use crate::tg::msg_request::MsgRequest;
+use crate::tg::user_args::resolve_user_argument;
+use crate::validations::{
+    validate_wish, validate_wish_number, validation_error_to_key, ValidationError,
+};
+use anyhow::Result;
+use log::debug;
+
+/// Largest number of wishes kept per user.
+static MAX_WISHES: usize = 20;
+
+/// `/wishlist add [text]`, `/wishlist remove [number]` and `/wishlist show` manage the
+/// wishlist of the sender, `/wishlist [user]` shows the wishlist of another member.
+pub async fn handle_wishlist_command(
+    mut args: Vec<String>,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    debug!("Wishlist command was called with {args:?}");
+    let sender = req.get_msg().from.clone();
+    let sender = DbUser::new(
+        sender.id,
+        sender.username,
+        Some(sender.first_name),
+        None,
+        sender.language_code,
+    );
+
+    match args.first().map(|arg| arg.to_lowercase()).as_deref() {
+        // The wish may come unquoted as several arguments
+        Some("add") => {
+            args.remove(0);
+            handle_add_wish(sender, &args.join(" "), offset, req).await
+        }
+        Some("remove") => {
+            let number = args
+                .get(1)
+                .map_or(Err(ValidationError::InvalidWishNumber), |arg| {
+                    validate_wish_number(arg)
+                });
+            let number = match number {
+                Ok(number) => number,
+                Err(error) => {
+                    let text = req
+                        .get_translation_for(validation_error_to_key(&error))
+                        .await?;
+                    req.set_msg_text(&text.expect_text()?);
+                    return send_msg(offset, req).await;
+                }
+            };
+            handle_remove_wish(sender.id, number, offset, req).await
+        }
+        Some("show") => handle_show_wishlist(sender, true, offset, req).await,
+        _ => {
+            let without_user = args.is_empty();
+            match resolve_user_argument(args, req).await? {
+                Some((user, _)) => {
+                    let own = user.id == sender.id;
+                    handle_show_wishlist(user, own, offset, req).await
+                }
+                None if without_user => handle_show_wishlist(sender, true, offset, req).await,
+                None => {
+                    let text = req.get_translation_for("error_unknown_user").await?;
+                    req.set_msg_text(&text.expect_text()?);
+                    send_msg(offset, req).await
+                }
+            }
+        }
+    }
+}
+
+async fn handle_add_wish(
+    user: DbUser,
+    value: &str,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    let wish = match validate_wish(value) {
+        Ok(wish) => wish,
+        Err(error) => {
+            let text = req
+                .get_translation_for(validation_error_to_key(&error))
+                .await?;
+            req.set_msg_text(&text.expect_text()?);
+            return send_msg(offset, req).await;
+        }
+    };
+
+    let chat_id = req.get_msg().chat.id;
+    let user_id = user.id;
+    let count = {
+        let dvizh_repo = req.get_dvizh_repo().await;
+        dvizh_repo.add_or_update_user(user, chat_id)?;
+        dvizh_repo.get_wishlist(user_id)?.len()
+    };
+    if count >= MAX_WISHES {
+        let text = req
+            .get_translation_for("error_wishlist_full")
+            .await?
+            .expect_text()?
+            .replace("{count}", &MAX_WISHES.to_string());
+        req.set_msg_text(&text);
+        return send_msg(offset, req).await;
+    }
+
+    req.get_dvizh_repo().await.add_wish(user_id, &wish)?;
+    let text = req
+        .get_translation_for("wish_added")
+        .await?
+        .expect_text()?
+        .replace("{number}", &(count + 1).to_string());
+    req.set_msg_text(&text);
+    send_msg(offset, req).await
+}
+
+async fn handle_remove_wish(
+    user_id: i64,
+    number: usize,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    let removed = req.get_dvizh_repo().await.remove_wish(user_id, number)?;
+    let key = if removed {
+        "wish_removed"
+    } else {
+        "error_wish_not_found"
+    };
+    let text = req.get_translation_for(key).await?;
+    req.set_msg_text(&text.expect_text()?.replace("{number}", &number.to_string()));
+    send_msg(offset, req).await
+}
+
+/// Shows the wishlist of `owner`. Other members only see it when they share a group
+/// with the owner, and in a group only when the owner is a member of it.
+async fn handle_show_wishlist(
+    owner: DbUser,
+    own: bool,
+    offset: &mut i64,
+    req: &mut MsgRequest,
+) -> Result<serde_json::Value> {
+    let viewer_id = req.get_msg().from.id;
+    let chat = req.get_msg().chat.clone();
+    if !own {
+        let visible = {
+            let dvizh_repo = req.get_dvizh_repo().await;
+            if chat.chat_type == "private" {
+                dvizh_repo.share_group(viewer_id, owner.id)?
+            } else {
+                dvizh_repo.is_member(owner.id, chat.id)?
+            }
+        };
+        if !visible {
+            let text = req.get_translation_for("error_wishlist_hidden").await?;
+            req.set_msg_text(&text.expect_text()?);
+            return send_msg(offset, req).await;
+        }
+    }
+
+    let wishes = req.get_dvizh_repo().await.get_wishlist(owner.id)?;
+    let name = owner.display_name();
+    let text = match (wishes.is_empty(), own) {
+        (true, true) => req
+            .get_translation_for("wishlist_empty_own")
+            .await?
+            .expect_text()?,
+        (true, false) => req
+            .get_translation_for("wishlist_empty")
+            .await?
+            .expect_text()?
+            .replace("{name}", &name),
+        (false, _) => {
+            let header = req
+                .get_translation_for("wishlist_header")
+                .await?
+                .expect_text()?
+                .replace("{name}", &name);
+            format!("{header}\n{}", format_wishlist(&wishes))
+        }
+    };
+    req.set_msg_text(&text);
+    send_msg(offset, req).await
+}
+
+/// Lists the wishes as numbered lines, the numbers `/wishlist remove` takes.
+pub fn format_wishlist(wishes: &[String]) -> String {
+    wishes
+        .iter()
+        .enumerate()
+        .map(|(index, wish)| format!("{}. {wish}", index + 1))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
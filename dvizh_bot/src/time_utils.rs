@@ -0,0 +1,82 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+
+pub static DEFAULT_TIMEZONE: &str = "Europe/Warsaw";
+pub static DATE_FORMAT: &str = "%d.%m.%Y";
+pub static TIME_FORMAT: &str = "%H:%M";
+/// Reminders can be sent at most this many days before an event.
+pub static MAX_REMINDER_DAYS: i64 = 30;
+
+/// Parses an IANA timezone name such as `Europe/Warsaw`.
+pub fn parse_timezone(name: &str) -> Option<Tz> {
+    name.parse::<Tz>().ok()
+}
+
+/// Parses `name` as a timezone, falling back to `DEFAULT_TIMEZONE`.
+pub fn timezone_or_default(name: &str) -> Tz {
+    parse_timezone(name).unwrap_or(chrono_tz::Europe::Warsaw)
+}
+
+/// Converts a wall clock time in `tz` to UTC.
+/// Returns `None` for times skipped by a daylight saving transition.
+pub fn local_to_utc(tz: &Tz, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+    tz.from_local_datetime(&local)
+        .earliest()
+        .map(|date_time| date_time.with_timezone(&Utc))
+}
+
+/// Returns the current date in `tz`.
+pub fn today_in(tz: &Tz) -> NaiveDate {
+    Utc::now().with_timezone(tz).date_naive()
+}
+
+/// Returns the UTC bounds `[start, end)` of the local `date` in `tz`.
+pub fn day_bounds_utc(tz: &Tz, date: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start_of = |date: NaiveDate| {
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+        // Some zones switch DST at midnight, so the day may start an hour later
+        local_to_utc(tz, midnight)
+            .or_else(|| local_to_utc(tz, midnight + Duration::hours(1)))
+            .unwrap_or_else(|| midnight.and_utc())
+    };
+    (start_of(date), start_of(date + Duration::days(1)))
+}
+
+/// Formats reminder offsets given in minutes as `7d,1d,2h,30m`, or `off` when empty.
+pub fn format_reminder_offsets(offsets: &[i64]) -> String {
+    if offsets.is_empty() {
+        return "off".to_string();
+    }
+    offsets
+        .iter()
+        .map(|minutes| match minutes {
+            m if m % (24 * 60) == 0 => format!("{}d", m / (24 * 60)),
+            m if m % 60 == 0 => format!("{}h", m / 60),
+            m => format!("{m}m"),
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Formats the weekly digest schedule as `mon 10:00`.
+pub fn format_digest_schedule(weekday: Weekday, time: NaiveTime) -> String {
+    format!(
+        "{} {}",
+        weekday.to_string().to_lowercase(),
+        time.format(TIME_FORMAT)
+    )
+}
+
+/// Formats an event date the way it is typed, `DD.MM.YYYY [HH:MM[-HH:MM]]` in `tz`.
+/// Events starting at midnight without an end take the whole day.
+pub fn format_event_datetime(start: DateTime<Utc>, end: Option<DateTime<Utc>>, tz: &Tz) -> String {
+    let local_start = start.with_timezone(tz);
+    let mut value = local_start.format(DATE_FORMAT).to_string();
+    if local_start.time() != NaiveTime::MIN || end.is_some() {
+        value = format!("{value} {}", local_start.format(TIME_FORMAT));
+    }
+    if let Some(end) = end {
+        value = format!("{value}-{}", end.with_timezone(tz).format(TIME_FORMAT));
+    }
+    value
+}
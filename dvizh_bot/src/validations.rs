@@ -1,20 +1,505 @@
-use chrono::NaiveDate;
+use crate::csv::CsvLayout;
+use crate::event_filter::EventFilter;
+use crate::natural_dates::{is_natural_date, parse_natural_datetime, parse_natural_day_month};
+use crate::recurrence::Recurrence;
+use crate::time_utils::{
+    local_to_utc, parse_timezone, timezone_or_default, today_in, DATE_FORMAT, MAX_REMINDER_DAYS,
+    TIME_FORMAT,
+};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+
+/// Largest number of places an event can be limited to.
+static MAX_CAPACITY: i64 = 10000;
+/// Largest number of birthdays listed by `/birthdays`.
+static MAX_BIRTHDAY_COUNT: usize = 50;
+
+/// Longest date range event lists can be filtered by.
+static MAX_FILTER_DAYS: i64 = 366;
+/// Birthdates can go back at most this many years.
+static MAX_AGE_YEARS: i32 = 120;
+/// Longest wishlist entry, in characters.
+static MAX_WISH_LENGTH: usize = 200;
+
+/// Reason a command argument was rejected, shown to the user as its translation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValidationError {
+    MissingArguments,
+    ArgumentCount,
+    InvalidDate,
+    /// A date with numbers in the right places that does not exist, e.g. `31.02.2025`.
+    ImpossibleDate,
+    InvalidBirthdate,
+    BirthdateInFuture,
+    BirthdateTooOld,
+    InvalidBirthdayCount,
+    InvalidEventDate,
+    EventInPast,
+    InvalidRecurrence,
+    InvalidReminders,
+    InvalidCapacity,
+    InvalidCoordinates,
+    DuplicatePollDate,
+    UnknownSetting,
+    InvalidSettingValue,
+    InvalidEventFilter,
+    MissingSearchText,
+    InvalidExportType,
+    InvalidWish,
+    InvalidWishNumber,
+}
+
+pub fn validation_error_to_key(error: &ValidationError) -> &'static str {
+    match error {
+        ValidationError::MissingArguments => "error_missing_arguments",
+        ValidationError::ArgumentCount => "error_insufficient_arguments",
+        ValidationError::InvalidDate => "error_invalid_date",
+        ValidationError::ImpossibleDate => "error_impossible_date",
+        ValidationError::InvalidBirthdate => "error_birthday",
+        ValidationError::BirthdateInFuture => "error_birthdate_in_future",
+        ValidationError::BirthdateTooOld => "error_birthdate_too_old",
+        ValidationError::InvalidBirthdayCount => "error_invalid_birthday_count",
+        ValidationError::InvalidEventDate => "error_invalid_event_date",
+        ValidationError::EventInPast => "error_event_in_past",
+        ValidationError::InvalidRecurrence => "error_invalid_recurrence",
+        ValidationError::InvalidReminders => "error_invalid_reminders",
+        ValidationError::InvalidCapacity => "error_invalid_capacity",
+        ValidationError::InvalidCoordinates => "error_invalid_coordinates",
+        ValidationError::DuplicatePollDate => "error_duplicate_poll_date",
+        ValidationError::UnknownSetting => "error_unknown_setting",
+        ValidationError::InvalidSettingValue => "error_invalid_setting_value",
+        ValidationError::InvalidEventFilter => "error_invalid_event_filter",
+        ValidationError::MissingSearchText => "error_missing_search_text",
+        ValidationError::InvalidExportType => "error_invalid_export_type",
+        ValidationError::InvalidWish => "error_invalid_wish",
+        ValidationError::InvalidWishNumber => "error_invalid_wish_number",
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecurrenceRule {
+    pub recurrence: Recurrence,
+    pub until: Option<NaiveDate>,
+    pub skipped_dates: Vec<NaiveDate>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EventDateTime {
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    pub timezone: Tz,
+}
 
 /// Validates that `command_args` has at least `required_count` arguments.
 pub fn validate_argument_count(
     command_args: Option<Vec<String>>,
     required_count: usize,
-) -> Result<Vec<String>, String> {
-    let args = command_args.ok_or_else(|| "error_missing_arguments".to_string())?;
+) -> Result<Vec<String>, ValidationError> {
+    let args = command_args.ok_or(ValidationError::MissingArguments)?;
     if args.len() < required_count || args.len() > required_count {
-        return Err("error_insufficient_arguments".to_string());
+        return Err(ValidationError::ArgumentCount);
     }
     Ok(args)
 }
 
-/// Validates that `date_str` matches the `DD.MM.YYYY` format.
-pub fn validate_date_format(date_str: &str) -> Result<(), String> {
-    let _ = NaiveDate::parse_from_str(date_str, "%d.%m.%Y")
-        .map_err(|_| "error_invalid_date".to_string());
+/// Validates that `command_args` has between `min_count` and `max_count` arguments.
+pub fn validate_argument_range(
+    command_args: Option<Vec<String>>,
+    min_count: usize,
+    max_count: usize,
+) -> Result<Vec<String>, ValidationError> {
+    let args = command_args.ok_or(ValidationError::MissingArguments)?;
+    if args.len() < min_count || args.len() > max_count {
+        return Err(ValidationError::ArgumentCount);
+    }
+    Ok(args)
+}
+
+/// Parses a date given as `DD.MM.YYYY`, `DD/MM/YYYY` or ISO `YYYY-MM-DD`.
+pub fn parse_date(value: &str) -> Result<NaiveDate, ValidationError> {
+    let value = value.trim();
+    let separator = value.chars().find(|c| matches!(c, '.' | '/' | '-'));
+    let parts = match separator {
+        Some(separator) => value.split(separator).collect::<Vec<&str>>(),
+        None => return Err(ValidationError::InvalidDate),
+    };
+    let numbers = parts
+        .iter()
+        .map(|part| part.parse::<u32>())
+        .collect::<Result<Vec<u32>, _>>()
+        .map_err(|_| ValidationError::InvalidDate)?;
+
+    // The year is always written in full, so `01.02.25` is not read as the year 25
+    let (day, month, year) = match (separator, numbers.as_slice()) {
+        (Some('-'), [year, month, day]) if parts[0].len() == 4 => (*day, *month, *year),
+        (Some('.' | '/'), [day, month, year]) if parts[2].len() == 4 => (*day, *month, *year),
+        _ => return Err(ValidationError::InvalidDate),
+    };
+    NaiveDate::from_ymd_opt(year as i32, month, day).ok_or(ValidationError::ImpossibleDate)
+}
+
+/// Validates a birthdate given as a full date, see `parse_date`, or as `DD.MM` without the year.
+/// The month may also be written out, see `parse_natural_day_month`.
+/// Returns the date as `DD.MM.YYYY` or `DD.MM` with zero-padded day and month.
+pub fn validate_birthdate(value: &str) -> Result<String, ValidationError> {
+    // `5 марта 1990` is read as `5.3.1990`
+    let value = match parse_natural_day_month(value) {
+        Some((day, month, Some(year))) => format!("{day}.{month}.{year}"),
+        Some((day, month, None)) => format!("{day}.{month}"),
+        None => value.trim().to_string(),
+    };
+    let value = value.as_str();
+    let without_year = value
+        .split_once(['.', '/'])
+        .filter(|(_, month)| !month.contains(['.', '/']))
+        .and_then(|(day, month)| Some((day.parse::<u32>().ok()?, month.parse::<u32>().ok()?)));
+    if let Some((day, month)) = without_year {
+        // Any leap year accepts February 29
+        return match NaiveDate::from_ymd_opt(2000, month, day) {
+            Some(_) => Ok(format!("{day:02}.{month:02}")),
+            None => Err(ValidationError::ImpossibleDate),
+        };
+    }
+
+    let date = match parse_date(value) {
+        Err(ValidationError::InvalidDate) => return Err(ValidationError::InvalidBirthdate),
+        result => result?,
+    };
+    let today = Utc::now().date_naive();
+    if date > today {
+        return Err(ValidationError::BirthdateInFuture);
+    }
+    let oldest = today
+        .with_year(today.year() - MAX_AGE_YEARS)
+        .unwrap_or(today);
+    if date < oldest {
+        return Err(ValidationError::BirthdateTooOld);
+    }
+    Ok(date.format(DATE_FORMAT).to_string())
+}
+
+/// Validates how many birthdays `/birthdays` lists, from 1 to `MAX_BIRTHDAY_COUNT`.
+pub fn validate_birthday_count(value: &str) -> Result<usize, ValidationError> {
+    match value.trim().parse::<usize>() {
+        Ok(count) if (1..=MAX_BIRTHDAY_COUNT).contains(&count) => Ok(count),
+        _ => Err(ValidationError::InvalidBirthdayCount),
+    }
+}
+
+/// Validates an event date in the form `[date] [HH:MM[-HH:MM]] [timezone]`, see `parse_date`,
+/// or given as a phrase, see `parse_natural_datetime`.
+/// The time is read in `default_tz` unless a timezone name is given,
+/// an end time earlier than the start time belongs to the next day.
+/// Events without a time may start today, others must start in the future.
+pub fn validate_event_datetime(
+    value: &str,
+    default_tz: &str,
+) -> Result<EventDateTime, ValidationError> {
+    let mut parts = value.split_whitespace().collect::<Vec<&str>>();
+
+    let timezone = match parts.last().and_then(|last| parse_timezone(last)) {
+        Some(tz) if parts.len() > 1 => {
+            parts.pop();
+            tz
+        }
+        _ => timezone_or_default(default_tz),
+    };
+
+    // Phrases such as `tomorrow 19:00` are read relative to today in the event timezone
+    let phrase = parts.join(" ");
+    let (date, time, end_time) = if is_natural_date(&phrase) {
+        let now = Utc::now().with_timezone(&timezone).naive_local();
+        let natural =
+            parse_natural_datetime(&phrase, now).ok_or(ValidationError::InvalidEventDate)?;
+        (natural.date, natural.start, natural.end)
+    } else {
+        let (date, time) = match parts.as_slice() {
+            [date] => (*date, None),
+            [date, time] => (*date, Some(*time)),
+            _ => return Err(ValidationError::InvalidEventDate),
+        };
+        let date = match parse_date(date) {
+            Err(ValidationError::InvalidDate) => return Err(ValidationError::InvalidEventDate),
+            result => result?,
+        };
+        match time {
+            Some(time) => {
+                let mut times = time.splitn(2, '-');
+                let start = parse_time(times.next().unwrap_or_default())?;
+                let end = times.next().map(parse_time).transpose()?;
+                (date, Some(start), end)
+            }
+            None => (date, None, None),
+        }
+    };
+    let start_time = time.unwrap_or(NaiveTime::MIN);
+
+    let start_local = date.and_time(start_time);
+    let start = local_to_utc(&timezone, start_local).ok_or(ValidationError::InvalidEventDate)?;
+    let in_past = match time {
+        Some(_) => start < Utc::now(),
+        None => date < today_in(&timezone),
+    };
+    if in_past {
+        return Err(ValidationError::EventInPast);
+    }
+
+    let end = match end_time {
+        Some(end_time) => {
+            let mut end_local = date.and_time(end_time);
+            if end_local <= start_local {
+                end_local += Duration::days(1);
+            }
+            Some(local_to_utc(&timezone, end_local).ok_or(ValidationError::InvalidEventDate)?)
+        }
+        None => None,
+    };
+
+    Ok(EventDateTime {
+        start,
+        end,
+        timezone,
+    })
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime, ValidationError> {
+    NaiveTime::parse_from_str(time, TIME_FORMAT).map_err(|_| ValidationError::InvalidEventDate)
+}
+
+/// Validates a recurrence rule such as `weekly fri`, `monthly 2 fri`, `monthly last fri`
+/// or `every 3 days`, optionally followed by `until DD.MM.YYYY` and
+/// `skip DD.MM.YYYY,DD.MM.YYYY`.
+pub fn validate_recurrence(value: &str) -> Result<RecurrenceRule, ValidationError> {
+    let error = || ValidationError::InvalidRecurrence;
+    let lowercase = value.to_lowercase();
+    let words = lowercase.split_whitespace().collect::<Vec<&str>>();
+    let parse_weekday = |word: &str| word.parse::<Weekday>().map_err(|_| error());
+
+    let (recurrence, rest) = match words.as_slice() {
+        ["weekly", weekday, rest @ ..] => (Recurrence::Weekly(parse_weekday(weekday)?), rest),
+        ["monthly", nth, weekday, rest @ ..] => {
+            let nth = match *nth {
+                "last" => -1,
+                nth => nth
+                    .parse::<i8>()
+                    .ok()
+                    .filter(|nth| (1..=5).contains(nth))
+                    .ok_or_else(error)?,
+            };
+            let weekday = parse_weekday(weekday)?;
+            (Recurrence::Monthly { nth, weekday }, rest)
+        }
+        ["every", days, "days" | "day", rest @ ..] => {
+            let days = days
+                .parse::<u32>()
+                .ok()
+                .filter(|days| *days > 0)
+                .ok_or_else(error)?;
+            (Recurrence::EveryDays(days), rest)
+        }
+        _ => return Err(error()),
+    };
+
+    let parse_date = |date: &str| match parse_date(date) {
+        Err(ValidationError::InvalidDate) => Err(error()),
+        result => result,
+    };
+    let mut until = None;
+    let mut skipped_dates = Vec::new();
+    let mut rest = rest.iter();
+    while let Some(word) = rest.next() {
+        let argument = rest.next().ok_or_else(error)?;
+        match *word {
+            "until" => until = Some(parse_date(argument)?),
+            "skip" => {
+                for date in argument.split(',').filter(|date| !date.is_empty()) {
+                    skipped_dates.push(parse_date(date)?);
+                }
+            }
+            _ => return Err(error()),
+        }
+    }
+
+    Ok(RecurrenceRule {
+        recurrence,
+        until,
+        skipped_dates,
+    })
+}
+
+/// Validates reminder offsets such as `7d,1d,2h` or `30m`, or `off` for no reminders.
+/// Returns the offsets in minutes, largest first.
+pub fn validate_reminder_offsets(value: &str) -> Result<Vec<i64>, ValidationError> {
+    let error = || ValidationError::InvalidReminders;
+    let value = value.trim().to_lowercase();
+    if value == "off" {
+        return Ok(Vec::new());
+    }
+
+    let mut offsets = Vec::new();
+    for part in value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+    {
+        let unit_start = part.char_indices().last().map_or(0, |(index, _)| index);
+        let (count, unit) = part.split_at(unit_start);
+        let count = count.parse::<i64>().map_err(|_| error())?;
+        let minutes = match unit {
+            "d" => count.checked_mul(24 * 60),
+            "h" => count.checked_mul(60),
+            "m" => Some(count),
+            _ => return Err(error()),
+        }
+        .ok_or_else(error)?;
+        if minutes <= 0 || minutes > MAX_REMINDER_DAYS * 24 * 60 {
+            return Err(error());
+        }
+        offsets.push(minutes);
+    }
+
+    if offsets.is_empty() {
+        return Err(error());
+    }
+    offsets.sort_unstable_by(|a, b| b.cmp(a));
+    offsets.dedup();
+    Ok(offsets)
+}
+
+/// Validates the number of places of an event, `off` removes the limit.
+pub fn validate_capacity(value: &str) -> Result<Option<i64>, ValidationError> {
+    let value = value.trim().to_lowercase();
+    if value == "off" {
+        return Ok(None);
+    }
+    match value.parse::<i64>() {
+        Ok(capacity) if capacity > 0 && capacity <= MAX_CAPACITY => Ok(Some(capacity)),
+        _ => Err(ValidationError::InvalidCapacity),
+    }
+}
+
+/// Validates venue coordinates `latitude, longitude` in degrees, `off` removes them.
+pub fn validate_coordinates(value: &str) -> Result<Option<(f64, f64)>, ValidationError> {
+    let error = || ValidationError::InvalidCoordinates;
+    let value = value.trim().to_lowercase();
+    if value == "off" {
+        return Ok(None);
+    }
+    let parts = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse::<f64>().map_err(|_| error()))
+        .collect::<Result<Vec<f64>, ValidationError>>()?;
+    match parts.as_slice() {
+        [latitude, longitude]
+            if (-90.0..=90.0).contains(latitude) && (-180.0..=180.0).contains(longitude) =>
+        {
+            Ok(Some((*latitude, *longitude)))
+        }
+        _ => Err(error()),
+    }
+}
+
+/// Validates the dates offered by a date poll, each as accepted by `/addevent`.
+/// The same date may be offered only once.
+pub fn validate_date_poll_options(
+    values: &[String],
+    default_tz: &str,
+) -> Result<(), ValidationError> {
+    let mut starts = Vec::new();
+    for value in values {
+        let start = validate_event_datetime(value, default_tz)?.start;
+        if starts.contains(&start) {
+            return Err(ValidationError::DuplicatePollDate);
+        }
+        starts.push(start);
+    }
     Ok(())
 }
+
+/// Validates a weekly digest schedule `[weekday] [HH:MM]`, e.g. `mon 10:00`.
+pub fn validate_digest_schedule(value: &str) -> Result<(Weekday, NaiveTime), ValidationError> {
+    let error = || ValidationError::InvalidSettingValue;
+    match value.split_whitespace().collect::<Vec<&str>>().as_slice() {
+        [weekday, time] => Ok((
+            weekday.parse::<Weekday>().map_err(|_| error())?,
+            NaiveTime::parse_from_str(time, TIME_FORMAT).map_err(|_| error())?,
+        )),
+        _ => Err(error()),
+    }
+}
+
+/// Validates an event list filter: `week`, `month`, a date, see `parse_date`,
+/// or a range `DD.MM.YYYY-DD.MM.YYYY` of at most `MAX_FILTER_DAYS` days.
+pub fn validate_event_filter(value: &str) -> Result<EventFilter, ValidationError> {
+    let error = || ValidationError::InvalidEventFilter;
+    let value = value.trim().to_lowercase();
+    match value.as_str() {
+        "week" => return Ok(EventFilter::Week),
+        "month" => return Ok(EventFilter::Month),
+        _ => {}
+    }
+
+    // ISO dates contain dashes too, so a single date is tried first
+    let (from, to) = match parse_date(&value) {
+        Ok(date) => (date, date),
+        Err(_) => {
+            let (from, to) = value.split_once('-').ok_or_else(error)?;
+            (
+                parse_date(from).map_err(|_| error())?,
+                parse_date(to).map_err(|_| error())?,
+            )
+        }
+    };
+    if to < from || (to - from).num_days() > MAX_FILTER_DAYS {
+        return Err(error());
+    }
+    Ok(EventFilter::Range(from, to))
+}
+
+/// Validates the arguments of an event search: the search text,
+/// optionally followed by an event list filter as the last argument.
+pub fn validate_search_arguments(
+    command_args: Option<Vec<String>>,
+) -> Result<(String, EventFilter), ValidationError> {
+    let mut args = command_args.ok_or(ValidationError::MissingSearchText)?;
+    let filter = match args.last().map(|value| validate_event_filter(value)) {
+        Some(Ok(filter)) if args.len() > 1 => {
+            args.pop();
+            filter
+        }
+        _ => EventFilter::All,
+    };
+
+    let text = args.join(" ");
+    if !text.chars().any(char::is_alphanumeric) {
+        return Err(ValidationError::MissingSearchText);
+    }
+    Ok((text, filter))
+}
+
+/// Validates what `/export` writes, `birthdays` or `events`, `None` stands for both.
+pub fn validate_export_type(value: Option<&str>) -> Result<Option<CsvLayout>, ValidationError> {
+    match value.map(|value| value.to_lowercase()).as_deref() {
+        None => Ok(None),
+        Some("birthdays") => Ok(Some(CsvLayout::Birthdays)),
+        Some("events") => Ok(Some(CsvLayout::Events)),
+        Some(_) => Err(ValidationError::InvalidExportType),
+    }
+}
+
+/// Validates a wishlist entry, a text or a link of up to `MAX_WISH_LENGTH` characters.
+pub fn validate_wish(value: &str) -> Result<String, ValidationError> {
+    let wish = value.trim();
+    if wish.is_empty() || wish.chars().count() > MAX_WISH_LENGTH {
+        return Err(ValidationError::InvalidWish);
+    }
+    Ok(wish.to_string())
+}
+
+/// Validates the 1-based number of a wishlist entry.
+pub fn validate_wish_number(value: &str) -> Result<usize, ValidationError> {
+    match value.trim().trim_start_matches('#').parse::<usize>() {
+        Ok(number) if number > 0 => Ok(number),
+        _ => Err(ValidationError::InvalidWishNumber),
+    }
+}